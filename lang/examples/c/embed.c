@@ -0,0 +1,109 @@
+// running modu from c, build it from the lang folder with
+//
+//   cargo build
+//   cc examples/c/embed.c -I include -L target/debug -lmodu -Wl,-rpath,target/debug -o embed
+//   ./embed
+
+#include <stdio.h>
+#include "modu.h"
+
+// sum(array) -> int, errors on anything that isnt an int
+static ModuValue *sum(void *user_data, const ModuValue *const *args, size_t argc) {
+    int *calls = user_data;
+    *calls += 1;
+
+    if (argc != 1 || modu_type(args[0]) != MODU_ARRAY) {
+        return modu_error("sum() takes an array");
+    }
+
+    int64_t total = 0;
+
+    for (size_t i = 0; i < modu_array_len(args[0]); i++) {
+        ModuValue *item = modu_array_get(args[0], i);
+
+        if (modu_type(item) != MODU_INT) {
+            modu_value_free(item);
+            return modu_error("sum() only adds ints");
+        }
+
+        total += modu_as_int(item);
+        modu_value_free(item);
+    }
+
+    return modu_int(total);
+}
+
+static void print_value(const char *label, const ModuValue *value) {
+    char *text = modu_to_string(value);
+    printf("%s: %s (%s)\n", label, text, modu_type_name(value));
+    modu_string_free(text);
+}
+
+int main(void) {
+    ModuInterpreter *modu = modu_new();
+
+    int calls = 0;
+    modu_register_fn(modu, "sum", sum, &calls);
+
+    ModuValue *numbers = modu_array();
+    for (int i = 1; i <= 4; i++) {
+        ModuValue *n = modu_int(i);
+        modu_array_push(numbers, n);
+        modu_value_free(n);
+    }
+
+    modu_set_global(modu, "numbers", numbers);
+    modu_value_free(numbers);
+
+    ModuValue *result = modu_eval(modu, "let total = sum(numbers);\ntotal * 2;");
+    print_value("result", result);
+    modu_value_free(result);
+
+    // errors from callbacks can be caught by the script
+    result = modu_eval(modu, "let err = null;\ntry {\n    sum([1, \"two\"]);\n} catch e {\n    err = e;\n}\nerr;");
+    printf("caught: %s\n", modu_as_string(result));
+    modu_value_free(result);
+
+    // objects built on the modu side, read from c
+    modu_eval(modu, "let config = { \"name\": \"modu\", \"port\": 8080, \"debug\": true };");
+    ModuValue *config = modu_get_global(modu, "config");
+    ModuValue *keys = modu_object_keys(config);
+
+    for (size_t i = 0; i < modu_array_len(keys); i++) {
+        ModuValue *key = modu_array_get(keys, i);
+        ModuValue *value = modu_object_get(config, modu_as_string(key));
+
+        print_value(modu_as_string(key), value);
+
+        modu_value_free(value);
+        modu_value_free(key);
+    }
+
+    modu_value_free(keys);
+    modu_value_free(config);
+
+    // calling a modu function with c values
+    modu_eval(modu, "fn greet(name) { return \"hello \" + name; }");
+    ModuValue *name = modu_string("c");
+    const ModuValue *args[] = { name };
+    result = modu_call(modu, "greet", args, 1);
+    print_value("greet", result);
+    modu_value_free(result);
+    modu_value_free(name);
+
+    // errors that escape come back as NULL
+    result = modu_eval(modu, "let x = 1;\nx + undefined_thing;");
+    if (!result) {
+        size_t line = 0, column = 0, start = 0, end = 0;
+
+        modu_error_location(modu, &line, &column);
+        modu_error_span(modu, &start, &end);
+
+        printf("error: %s at %zu:%zu (bytes %zu..%zu)\n", modu_error_message(modu), line, column, start, end);
+    }
+
+    printf("sum() was called %d times\n", calls);
+
+    modu_free(modu);
+    return 0;
+}
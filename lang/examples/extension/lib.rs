@@ -0,0 +1,41 @@
+// a native extension, in a real project this is a crate with
+//
+//   [lib]
+//   crate-type = ["cdylib"]
+//
+//   [dependencies]
+//   modu = "<the version of modu that will load it>"
+//
+// then `import "native:target/release/libtext";` or ship it in a package
+// with `native = "libtext"` under [package] in its project.toml
+
+use modu::Value;
+use modu::extension::Module;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+fn init(module: &mut Module) {
+    module.value("version", Value::String("1.0.0".to_string()));
+
+    module.function("reverse", |args| match args.as_slice() {
+        [Value::String(s)] => Ok(Value::String(s.chars().rev().collect())),
+        _ => Err("reverse() takes a string".to_string()),
+    });
+
+    module.function("words", |args| match args.as_slice() {
+        [Value::String(s)] => Ok(Value::Array(
+            s.split_whitespace()
+                .map(|w| Value::String(w.to_string()))
+                .collect(),
+        )),
+        _ => Err("words() takes a string".to_string()),
+    });
+
+    // state lives in the closure, every import gets its own
+    let count = Arc::new(AtomicI64::new(0));
+    module.function("count", move |_| {
+        Ok(Value::Int(count.fetch_add(1, Ordering::Relaxed) + 1))
+    });
+}
+
+modu::extension!(init);
@@ -0,0 +1,124 @@
+// embedding modu from c (or anything that can call c), link against the
+// cdylib cargo builds next to the modu binary (libmodu.so, libmodu.dylib, modu.dll)
+//
+// ownership is simple: every ModuValue* a function returns is yours and has
+// to be freed with modu_value_free, every ModuValue* you pass in is only
+// borrowed (and copied if modu keeps it). strings you get back are owned by
+// whatever handed them out, except modu_to_string which you free.
+//
+// an interpreter isn't safe to use from two threads at once, but it can be
+// moved between them. callbacks run on whichever thread is running the code
+//
+// a panic inside modu never unwinds into your code. during an eval or call it
+// becomes the error (NULL and a message), anywhere else you get 0, false or NULL
+//
+//   ModuInterpreter *modu = modu_new();
+//   ModuValue *result = modu_eval(modu, "1 + 2;");
+//   if (!result) {
+//       printf("error: %s\n", modu_error_message(modu));
+//   }
+//   modu_value_free(result);
+//   modu_free(modu);
+
+#ifndef MODU_H
+#define MODU_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ModuInterpreter ModuInterpreter;
+typedef struct ModuValue ModuValue;
+
+typedef enum {
+    MODU_NULL,
+    MODU_BOOL,
+    MODU_INT,
+    MODU_FLOAT,
+    MODU_STRING,
+    MODU_ARRAY,
+    MODU_OBJECT,
+    MODU_FUNCTION,
+    MODU_ERROR,
+    MODU_NATIVE, // handles like files from std/fs or ffi libraries
+    MODU_OTHER,  // ranges, classes, instances and the like
+} ModuType;
+
+// args are borrowed for the duration of the call. return a new value (or NULL
+// for null), returning modu_error(...) raises a catchable error in the script
+typedef ModuValue *(*ModuCallback)(void *user_data, const ModuValue *const *args, size_t argc);
+
+// interpreters, globals stick around between evals
+
+ModuInterpreter *modu_new(void);
+void modu_free(ModuInterpreter *modu);
+
+// these return the value of the last expression, or NULL on error
+ModuValue *modu_eval(ModuInterpreter *modu, const char *code);
+ModuValue *modu_eval_file(ModuInterpreter *modu, const char *path);
+ModuValue *modu_call(ModuInterpreter *modu, const char *name, const ModuValue *const *args, size_t argc);
+
+bool modu_register_fn(ModuInterpreter *modu, const char *name, ModuCallback func, void *user_data);
+
+// NULL if there is no such global
+ModuValue *modu_get_global(const ModuInterpreter *modu, const char *name);
+bool modu_set_global(ModuInterpreter *modu, const char *name, const ModuValue *value);
+
+// errors, from the last eval or call. the message is NULL if it succeeded and
+// lives until the next one. spans are byte offsets into the source, lines and
+// columns start at 1, both return false when the error has no position
+const char *modu_error_message(const ModuInterpreter *modu);
+bool modu_error_span(const ModuInterpreter *modu, size_t *start, size_t *end);
+bool modu_error_location(const ModuInterpreter *modu, size_t *line, size_t *column);
+
+// making values
+
+ModuValue *modu_null(void);
+ModuValue *modu_bool(bool value);
+ModuValue *modu_int(int64_t value);
+ModuValue *modu_float(double value);
+ModuValue *modu_string(const char *value);
+ModuValue *modu_array(void);
+ModuValue *modu_object(void);
+ModuValue *modu_error(const char *message);
+
+ModuValue *modu_value_clone(const ModuValue *value);
+void modu_value_free(ModuValue *value);
+
+// reading values, asking for the wrong type gives 0, false or NULL
+
+ModuType modu_type(const ModuValue *value);
+const char *modu_type_name(const ModuValue *value);
+
+bool modu_as_bool(const ModuValue *value);
+int64_t modu_as_int(const ModuValue *value);
+double modu_as_float(const ModuValue *value);
+const char *modu_as_string(const ModuValue *value); // lives as long as the value
+
+// how print() would show it, free it with modu_string_free
+char *modu_to_string(const ModuValue *value);
+void modu_string_free(char *text);
+
+// arrays
+
+size_t modu_array_len(const ModuValue *array);
+ModuValue *modu_array_get(const ModuValue *array, size_t index);
+bool modu_array_push(ModuValue *array, const ModuValue *item);
+bool modu_array_set(ModuValue *array, size_t index, const ModuValue *item);
+
+// objects, keys come back as a sorted array of strings
+
+size_t modu_object_len(const ModuValue *object);
+ModuValue *modu_object_keys(const ModuValue *object);
+ModuValue *modu_object_get(const ModuValue *object, const char *key);
+bool modu_object_set(ModuValue *object, const char *key, const ModuValue *item);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,717 @@
+// what is defined where and what every name refers to, worked out from the
+// ast without running anything. locals resolve with the compiler's own
+// ScopeStack so they end up wherever the compiler would put them
+//
+//   let ast = parser::parse_source(&source, "main.modu")?;
+//   let analysis = analysis::analyze(&source, &ast);
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Expr, SpannedExpr};
+use crate::compiler::scope::{ScopeStack, Variable};
+use crate::lexer::{Span, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Function,
+    Parameter,
+    Class,
+    Method,
+    Module,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Range<usize>,    // just the name
+    pub range: Range<usize>,   // the whole statement
+    pub visible: Range<usize>, // where the name can be used, everywhere for globals
+    pub global: bool,
+    pub params: Vec<String>,      // for functions and methods
+    pub container: Option<usize>, // the class of a method, the function of a local
+    pub hint: Option<String>,     // "string", "array".. or the class it was made from
+    pub parent: Option<String>,   // the class a class extends
+    pub import: Option<usize>,    // for modules, index into imports
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub span: Range<usize>,
+    pub definition: Option<usize>, // None for builtins, star imports and typos
+    pub write: bool,
+}
+
+// object.name, object is a definition when it could be worked out
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub span: Range<usize>,
+    pub object: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub alias: Option<String>, // "*" puts everything in globals
+    pub span: Range<usize>,    // the path string
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub definitions: Vec<Definition>,
+    pub references: Vec<Reference>,
+    pub members: Vec<Member>,
+    pub imports: Vec<Import>,
+}
+
+impl Analysis {
+    // the global a name means at the top level
+    pub fn global(&self, name: &str) -> Option<usize> {
+        self.definitions
+            .iter()
+            .position(|d| d.global && d.name == name)
+    }
+
+    // every definition that can be used at an offset, innermost first
+    pub fn visible_at(&self, offset: usize) -> Vec<&Definition> {
+        let mut visible: Vec<&Definition> = self
+            .definitions
+            .iter()
+            .filter(|d| d.kind != SymbolKind::Method && d.visible.contains(&offset))
+            .collect();
+
+        visible.sort_by_key(|d| std::cmp::Reverse(d.visible.start));
+
+        let mut seen = std::collections::HashSet::new();
+        visible.retain(|d| seen.insert(d.name.clone()));
+        visible
+    }
+
+    // the methods of a class and the ones it extends
+    pub fn methods(&self, class: usize) -> Vec<&Definition> {
+        let mut methods = Vec::new();
+        let mut class = Some(class);
+        let mut seen = Vec::new();
+
+        while let Some(current) = class.filter(|c| !seen.contains(c)) {
+            seen.push(current);
+
+            for method in self
+                .definitions
+                .iter()
+                .filter(|d| d.kind == SymbolKind::Method && d.container == Some(current))
+            {
+                if !methods.iter().any(|m: &&Definition| m.name == method.name) {
+                    methods.push(method);
+                }
+            }
+
+            class = self.parent(current);
+        }
+
+        methods
+    }
+
+    fn parent(&self, class: usize) -> Option<usize> {
+        let parent = self.definitions[class].parent.as_deref()?;
+
+        self.global(parent)
+            .filter(|&d| self.definitions[d].kind == SymbolKind::Class)
+    }
+
+    // the definition or reference under an offset
+    pub fn definition_at(&self, offset: usize) -> Option<usize> {
+        if let Some(i) = self
+            .definitions
+            .iter()
+            .position(|d| contains(&d.span, offset))
+        {
+            return Some(i);
+        }
+
+        self.references
+            .iter()
+            .find(|r| contains(&r.span, offset))
+            .and_then(|r| r.definition)
+    }
+
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references.iter().find(|r| contains(&r.span, offset))
+    }
+
+    pub fn member_at(&self, offset: usize) -> Option<&Member> {
+        self.members.iter().find(|m| contains(&m.span, offset))
+    }
+
+    pub fn import_at(&self, offset: usize) -> Option<&Import> {
+        self.imports.iter().find(|i| contains(&i.span, offset))
+    }
+
+    // the class `self` means at an offset
+    pub fn class_at(&self, offset: usize) -> Option<usize> {
+        self.definitions
+            .iter()
+            .position(|d| d.kind == SymbolKind::Class && d.range.contains(&offset))
+    }
+}
+
+// the end counts, so a cursor right after a name is still on it
+fn contains(span: &Range<usize>, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+pub fn analyze(source: &str, ast: &[SpannedExpr]) -> Analysis {
+    let mut walker = Walker {
+        tokens: crate::lexer::lex(source).unwrap_or_default(),
+        scope: ScopeStack::new(),
+        slots: Vec::new(),
+        saved_slots: Vec::new(),
+        ends: vec![source.len()],
+        function: None,
+        class: None,
+        globals: HashMap::new(),
+        member_objects: Vec::new(),
+        analysis: Analysis::default(),
+    };
+
+    for expr in ast {
+        walker.expr(expr);
+    }
+
+    // functions can use globals defined after them
+    let Walker {
+        mut analysis,
+        globals,
+        member_objects,
+        ..
+    } = walker;
+
+    for reference in &mut analysis.references {
+        if reference.definition.is_none() {
+            reference.definition = globals.get(&reference.name).copied();
+        }
+    }
+
+    for (member, reference) in member_objects {
+        analysis.members[member].object = analysis.references[reference].definition;
+    }
+
+    analysis
+}
+
+// what `import "..."` binds when there is no alias
+pub fn import_name(path: &str) -> String {
+    if let Some(name) = path.strip_prefix("std/") {
+        return name.to_string();
+    }
+
+    if let Some(name) = path.strip_prefix("native:") {
+        return crate::extension::default_name(Path::new(name));
+    }
+
+    // the vm names it after the file it loaded, which is lib.modu for packages
+    if !path.contains('/') && !path.ends_with(".modu") {
+        return "lib".to_string();
+    }
+
+    Path::new(path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(path)
+        .to_string()
+}
+
+// the file a modu import points at, the same way the vm looks for it.
+// None for the stdlib, native extensions and things that aren't there
+pub fn resolve_import(dir: &Path, path: &str) -> Option<PathBuf> {
+    if path.starts_with("std/") || path.starts_with("native:") {
+        return None;
+    }
+
+    let resolved = if !path.contains('/') && !path.ends_with(".modu") {
+        std::iter::successors(Some(dir), |p| p.parent())
+            .find(|p| p.join(".modu").join("packages").is_dir())
+            .unwrap_or(dir)
+            .join(".modu")
+            .join("packages")
+            .join(path)
+            .join("lib.modu")
+    } else if path.ends_with(".modu") {
+        dir.join(path)
+    } else {
+        dir.join(format!("{}.modu", path))
+    };
+
+    resolved.canonicalize().ok()
+}
+
+struct Walker {
+    tokens: Vec<(Token, Span)>,
+    scope: ScopeStack,
+    slots: Vec<Option<usize>>, // definition of every slot of the current function
+    saved_slots: Vec<Vec<Option<usize>>>,
+    ends: Vec<usize>, // where the innermost scope ends
+    function: Option<usize>,
+    class: Option<usize>,
+    globals: HashMap<String, usize>,
+    member_objects: Vec<(usize, usize)>, // members whose object is a reference
+    analysis: Analysis,
+}
+
+impl Walker {
+    // names don't have spans in the ast, they are found in the tokens
+    fn name_after(&self, from: usize, name: &str) -> Range<usize> {
+        self.tokens
+            .iter()
+            .find(|(token, span)| {
+                span.start >= from && matches!(token, Token::Identifier(n) if n == name)
+            })
+            .map(|(_, span)| span.into_range())
+            .unwrap_or(from..from)
+    }
+
+    fn define(
+        &mut self,
+        name: &str,
+        kind: SymbolKind,
+        span: Range<usize>,
+        range: Span,
+        global: bool,
+    ) -> usize {
+        // globals stay visible past the end for code that is still being typed
+        let visible = if global {
+            0..usize::MAX
+        } else {
+            span.start..*self.ends.last().unwrap()
+        };
+
+        self.analysis.definitions.push(Definition {
+            name: name.to_string(),
+            kind,
+            span,
+            range: range.into_range(),
+            visible,
+            global,
+            params: Vec::new(),
+            container: self.function,
+            hint: None,
+            parent: None,
+            import: None,
+        });
+
+        self.analysis.definitions.len() - 1
+    }
+
+    fn local(&mut self, name: &str, definition: Option<usize>) {
+        let slot = self.scope.define_local(name);
+        self.slots.resize(slot + 1, None);
+        self.slots[slot] = definition;
+    }
+
+    // what the compiler's store_variable does, a new local or global the
+    // first time and the same one after that
+    fn bind(&mut self, name: &str, definition: usize) {
+        if self.scope.in_function() {
+            match self.scope.resolve(name) {
+                Variable::Local(slot) => self.slots[slot] = Some(definition),
+                Variable::Global(_) => self.local(name, Some(definition)),
+            }
+        } else {
+            self.globals.entry(name.to_string()).or_insert(definition);
+        }
+    }
+
+    fn bound(&self, name: &str) -> Option<usize> {
+        if !self.scope.in_function() {
+            return self.globals.get(name).copied();
+        }
+
+        match self.scope.resolve(name) {
+            Variable::Local(slot) => self.slots.get(slot).copied().flatten(),
+            Variable::Global(_) => None,
+        }
+    }
+
+    fn reference(&mut self, name: &str, span: Range<usize>, write: bool) {
+        let definition = match self.scope.resolve(name) {
+            Variable::Local(slot) => self.slots.get(slot).copied().flatten(),
+            Variable::Global(_) => None, // filled in at the end
+        };
+
+        self.analysis.references.push(Reference {
+            name: name.to_string(),
+            span,
+            definition,
+            write,
+        });
+    }
+
+    fn enter_function(&mut self, end: usize) -> crate::compiler::scope::SavedScopes {
+        self.saved_slots.push(std::mem::take(&mut self.slots));
+        self.ends.push(end);
+        self.scope.enter_function()
+    }
+
+    fn exit_function(&mut self, saved: crate::compiler::scope::SavedScopes) {
+        self.scope.exit_function(saved);
+        self.ends.pop();
+        self.slots = self.saved_slots.pop().unwrap();
+    }
+
+    fn function(&mut self, expr: &SpannedExpr, kind: SymbolKind) -> Option<usize> {
+        let Expr::Function { name, args, body } = &expr.node else {
+            return None;
+        };
+
+        let span = self.name_after(expr.span.start, name);
+        let after_name = span.end;
+        let global = !self.scope.in_function() && kind == SymbolKind::Function;
+        let definition = self.define(name, kind, span, expr.span, global);
+
+        self.analysis.definitions[definition].params = args.clone();
+
+        if kind == SymbolKind::Method {
+            self.analysis.definitions[definition].container = self.class;
+        }
+
+        let saved = self.enter_function(expr.span.end);
+        let outer = self.function.replace(definition);
+
+        if kind == SymbolKind::Method {
+            self.local("self", None);
+        }
+
+        let mut from = after_name;
+
+        for arg in args {
+            let span = self.name_after(from, arg);
+            from = span.end;
+
+            let param = self.define(arg, SymbolKind::Parameter, span, expr.span, false);
+            self.analysis.definitions[param].visible = body.span.into_range();
+            self.local(arg, Some(param));
+        }
+
+        self.expr(body);
+
+        self.function = outer;
+        self.exit_function(saved);
+
+        Some(definition)
+    }
+
+    fn hint(value: &SpannedExpr) -> Option<String> {
+        let hint = match &value.node {
+            Expr::String(_) => "string",
+            Expr::Int(_) => "int",
+            Expr::Float(_) => "float",
+            Expr::Array(_) => "array",
+            Expr::Object { .. } => "object",
+            Expr::Call { callee, .. } => match &callee.node {
+                Expr::Identifier(name) => return Some(name.clone()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(hint.to_string())
+    }
+
+    fn block(&mut self, body: &SpannedExpr) {
+        self.ends.push(body.span.end);
+        self.expr(body);
+        self.ends.pop();
+    }
+
+    fn expr(&mut self, expr: &SpannedExpr) {
+        let span = expr.span;
+
+        match &expr.node {
+            Expr::Let { name, value } | Expr::Const { name, value } => {
+                self.expr(value);
+
+                let name_span = self.name_after(span.start, name);
+                let is_const = matches!(expr.node, Expr::Const { .. });
+
+                // a second let of the same name is the same variable to the compiler
+                if !is_const && let Some(existing) = self.bound(name) {
+                    self.analysis.references.push(Reference {
+                        name: name.clone(),
+                        span: name_span,
+                        definition: Some(existing),
+                        write: true,
+                    });
+
+                    return;
+                }
+
+                let kind = if is_const {
+                    SymbolKind::Constant
+                } else {
+                    SymbolKind::Variable
+                };
+
+                let global = !self.scope.in_function();
+                let definition = self.define(name, kind, name_span, span, global);
+                self.analysis.definitions[definition].hint = Self::hint(value);
+
+                if is_const && self.scope.in_function() {
+                    let slot = self.scope.define_const(name);
+                    self.slots.resize(slot + 1, None);
+                    self.slots[slot] = Some(definition);
+                } else {
+                    self.bind(name, definition);
+                }
+            }
+
+            Expr::Identifier(name) => {
+                if name == "super" {
+                    return;
+                }
+
+                self.reference(name, span.into_range(), false);
+            }
+
+            Expr::Assign { target, value, .. } => {
+                self.expr(value);
+
+                match &target.node {
+                    Expr::Identifier(name) => self.reference(name, target.span.into_range(), true),
+                    _ => self.expr(target),
+                }
+            }
+
+            Expr::PropertyAccess { object, property } => {
+                self.expr(object);
+
+                let object = match &object.node {
+                    Expr::Identifier(name) if name == "self" || name == "super" => self.class,
+                    Expr::Identifier(_) => {
+                        let reference = self.analysis.references.len() - 1;
+                        let member = self.analysis.members.len();
+                        self.member_objects.push((member, reference));
+                        None
+                    }
+                    _ => None,
+                };
+
+                self.analysis.members.push(Member {
+                    name: property.clone(),
+                    span: span.end.saturating_sub(property.len())..span.end,
+                    object,
+                });
+            }
+
+            Expr::Function { name, .. } => {
+                if let Some(definition) = self.function(expr, SymbolKind::Function) {
+                    self.bind(name, definition);
+                }
+            }
+
+            Expr::Class {
+                name,
+                methods,
+                parent,
+            } => {
+                let name_span = self.name_after(span.start, name);
+                let after_name = name_span.end;
+                let global = !self.scope.in_function();
+                let class = self.define(name, SymbolKind::Class, name_span, span, global);
+                self.analysis.definitions[class].parent = parent.clone();
+
+                if let Some(parent) = parent {
+                    let parent_span = self.name_after(after_name, parent);
+                    self.reference(parent, parent_span, false);
+                }
+
+                let outer = self.class.replace(class);
+
+                for method in methods {
+                    self.function(method, SymbolKind::Method);
+                }
+
+                self.class = outer;
+                self.bind(name, class);
+            }
+
+            Expr::Import { name, alias } => {
+                let path_span = self
+                    .tokens
+                    .iter()
+                    .find(|(token, s)| s.start >= span.start && matches!(token, Token::String(_)))
+                    .map(|(_, s)| s.into_range())
+                    .unwrap_or(span.into_range());
+
+                self.analysis.imports.push(Import {
+                    path: name.clone(),
+                    alias: alias.clone(),
+                    span: path_span.clone(),
+                });
+
+                if alias.as_deref() == Some("*") {
+                    return;
+                }
+
+                // imports are always globals, even inside functions
+                let (binding, name_span) = match alias {
+                    Some(alias) => (alias.clone(), self.name_after(path_span.end, alias)),
+                    None => (import_name(name), path_span),
+                };
+
+                let definition = self.define(&binding, SymbolKind::Module, name_span, span, true);
+                self.analysis.definitions[definition].import =
+                    Some(self.analysis.imports.len() - 1);
+
+                self.globals.entry(binding).or_insert(definition);
+            }
+
+            Expr::ForLoop {
+                iterator_name,
+                iterator_range,
+                body,
+            } => {
+                self.scope.push_scope();
+                self.expr(iterator_range);
+
+                self.local("__iter__", None);
+                self.local("__index__", None);
+
+                let name_span = self.name_after(span.start, iterator_name);
+                self.ends.push(span.end);
+
+                let variable =
+                    self.define(iterator_name, SymbolKind::Variable, name_span, span, false);
+                self.local(iterator_name, Some(variable));
+
+                self.expr(body);
+
+                self.ends.pop();
+                self.scope.pop_scope();
+            }
+
+            Expr::Try {
+                try_block,
+                catch_block,
+                catch_var,
+            } => {
+                self.scope.push_scope();
+                self.ends.push(span.end);
+
+                if let Some(var) = catch_var {
+                    let name_span = self.name_after(try_block.span.end, var);
+                    let variable = self.define(var, SymbolKind::Variable, name_span, span, false);
+                    self.analysis.definitions[variable].visible = catch_block.span.into_range();
+                    self.local(var, Some(variable));
+                }
+
+                self.expr(try_block);
+                self.expr(catch_block);
+
+                self.ends.pop();
+                self.scope.pop_scope();
+            }
+
+            Expr::Block(exprs) => {
+                self.scope.push_scope();
+                self.ends.push(span.end);
+
+                for expr in exprs {
+                    self.expr(expr);
+                }
+
+                self.ends.pop();
+                self.scope.pop_scope();
+            }
+
+            Expr::If(branches) => {
+                for (condition, block) in branches {
+                    if let Some(condition) = condition {
+                        self.expr(condition);
+                    }
+
+                    self.block(block);
+                }
+            }
+
+            Expr::InfiniteLoop { body } => self.block(body),
+
+            Expr::WhileLoop { condition, body } => {
+                self.expr(condition);
+                self.block(body);
+            }
+
+            Expr::Call { callee, args } => {
+                self.expr(callee);
+
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+
+            Expr::Array(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+
+            Expr::Object { properties } => {
+                let mut values: Vec<_> = properties.values().collect();
+                values.sort_by_key(|v| v.span.start);
+
+                for value in values {
+                    self.expr(value);
+                }
+            }
+
+            Expr::IndexAccess { object, index } => {
+                self.expr(object);
+                self.expr(index);
+            }
+
+            Expr::Return(value) | Expr::Neg(value) | Expr::BitNot(value) | Expr::Not(value) => {
+                self.expr(value)
+            }
+
+            Expr::Add(a, b)
+            | Expr::Sub(a, b)
+            | Expr::Mul(a, b)
+            | Expr::Div(a, b)
+            | Expr::Mod(a, b)
+            | Expr::Pow(a, b)
+            | Expr::BitAnd(a, b)
+            | Expr::BitOr(a, b)
+            | Expr::BitXor(a, b)
+            | Expr::BitShl(a, b)
+            | Expr::BitShr(a, b)
+            | Expr::Equal(a, b)
+            | Expr::NotEqual(a, b)
+            | Expr::LessThan(a, b)
+            | Expr::LessThanOrEqual(a, b)
+            | Expr::GreaterThan(a, b)
+            | Expr::GreaterThanOrEqual(a, b)
+            | Expr::In(a, b)
+            | Expr::NotIn(a, b)
+            | Expr::And(a, b)
+            | Expr::Or(a, b)
+            | Expr::Range { start: a, end: b }
+            | Expr::InclusiveRange { start: a, end: b } => {
+                self.expr(a);
+                self.expr(b);
+            }
+
+            Expr::Int(_)
+            | Expr::Float(_)
+            | Expr::String(_)
+            | Expr::Bool(_)
+            | Expr::Null
+            | Expr::Break
+            | Expr::Continue => {}
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::lexer::Span;
+use serde::Serialize;
 use std::collections::HashMap;
 
 pub type SpannedExpr = Spanned<Expr>;
@@ -8,7 +9,26 @@ pub struct Spanned<T> {
     pub span: Span,
 }
 
-#[derive(Debug, Clone)]
+// spans are left out so the same program compares equal however it's laid out
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+// as {"span": [start, end], "node": ...}, for modu ast --format json
+impl<T: Serialize> Serialize for Spanned<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Spanned", 2)?;
+        state.serialize_field("span", &[self.span.start, self.span.end])?;
+        state.serialize_field("node", &self.node)?;
+        state.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum AssignOp {
     Add,
     Sub,
@@ -17,7 +37,7 @@ pub enum AssignOp {
     Mod,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum Expr {
     Int(i64),
     Float(f64),
@@ -0,0 +1,638 @@
+// the c api, see include/modu.h for how to use it and who owns what.
+// pointers coming in are trusted to follow the rules written there.
+// a panic can't unwind into c, that aborts the host, so anything that runs
+// modu or host rust code catches it and fails like it would for an error
+#![allow(clippy::missing_safety_doc)]
+
+use std::any::Any;
+use std::cell::OnceCell;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::error::ModuError;
+use crate::interpreter::Interpreter;
+use crate::vm::value::Value;
+
+pub struct ModuInterpreter {
+    interpreter: Interpreter,
+    error: Option<ModuError>,
+    message: Option<CString>,
+}
+
+// a value owned by the host. an Err is what modu_error makes, returning one
+// from a callback raises it in the script
+pub struct ModuValue {
+    value: Result<Value, String>,
+    text: OnceCell<CString>,
+    type_name: OnceCell<CString>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuType {
+    Null,
+    Bool,
+    Int,
+    Float,
+    String,
+    Array,
+    Object,
+    Function,
+    Error,
+    Native, // handles from std/fs, ffi or the host, see NativeObject
+    Other,
+}
+
+pub type ModuCallback = unsafe extern "C" fn(
+    user_data: *mut c_void,
+    args: *const *const ModuValue,
+    argc: usize,
+) -> *mut ModuValue;
+
+// the host promises the callback is fine with being called from
+// whichever thread runs the interpreter
+struct Callback {
+    func: ModuCallback,
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    fn call(&self, args: Vec<Value>) -> Result<Value, String> {
+        let args: Vec<ModuValue> = args.into_iter().map(ModuValue::new).collect();
+        let pointers: Vec<*const ModuValue> = args.iter().map(|a| a as *const _).collect();
+
+        let result = unsafe { (self.func)(self.user_data, pointers.as_ptr(), pointers.len()) };
+
+        if result.is_null() {
+            return Ok(Value::Null);
+        }
+
+        unsafe { Box::from_raw(result) }.value
+    }
+}
+
+impl ModuValue {
+    fn new(value: Value) -> Self {
+        Self {
+            value: Ok(value),
+            text: OnceCell::new(),
+            type_name: OnceCell::new(),
+        }
+    }
+
+    fn value(&self) -> Option<&Value> {
+        self.value.as_ref().ok()
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+// what the function gives back when it fails, if the body panics
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(failed)
+}
+
+fn into_handle(value: Value) -> *mut ModuValue {
+    Box::into_raw(Box::new(ModuValue::new(value)))
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+// a null handle is read as a modu null
+unsafe fn value_arg(ptr: *const ModuValue) -> Value {
+    match unsafe { ptr.as_ref() }.and_then(ModuValue::value) {
+        Some(value) => value.clone(),
+        None => Value::Null,
+    }
+}
+
+// strings with a nul in them get cut off there, c wouldnt see past it anyway
+fn c_string(text: &str) -> CString {
+    let end = text.find('\0').unwrap_or(text.len());
+    CString::new(&text[..end]).unwrap_or_default()
+}
+
+impl ModuInterpreter {
+    // evals and calls. a panic in the vm, or in a rust function or callback it
+    // ran, becomes the error instead
+    fn run(
+        &mut self,
+        body: impl FnOnce(&mut Interpreter) -> Result<Value, ModuError>,
+    ) -> *mut ModuValue {
+        let result = catch_unwind(AssertUnwindSafe(|| body(&mut self.interpreter)));
+        let result = result.unwrap_or_else(|panic| {
+            let message = format!("internal error: {}", panic_message(panic.as_ref()));
+            Err(ModuError::from(message))
+        });
+
+        self.finish(result)
+    }
+
+    fn finish(&mut self, result: Result<Value, ModuError>) -> *mut ModuValue {
+        match result {
+            Ok(value) => {
+                self.clear_error();
+                into_handle(value)
+            }
+
+            Err(e) => {
+                self.set_error(e);
+                std::ptr::null_mut()
+            }
+        }
+    }
+
+    fn set_error(&mut self, error: ModuError) {
+        self.message = Some(c_string(&error.message));
+        self.error = Some(error);
+    }
+
+    fn clear_error(&mut self) {
+        self.error = None;
+        self.message = None;
+    }
+}
+
+// interpreters
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_new() -> *mut ModuInterpreter {
+    Box::into_raw(Box::new(ModuInterpreter {
+        interpreter: Interpreter::new(),
+        error: None,
+        message: None,
+    }))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_free(modu: *mut ModuInterpreter) {
+    // dropping runs the finalizers of native values
+    if !modu.is_null() {
+        guard((), || drop(unsafe { Box::from_raw(modu) }));
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_eval(
+    modu: *mut ModuInterpreter,
+    code: *const c_char,
+) -> *mut ModuValue {
+    let modu = unsafe { &mut *modu };
+
+    match unsafe { str_arg(code) } {
+        Some(code) => modu.run(|interpreter| interpreter.eval(code)),
+        None => modu.finish(Err(ModuError::from("code must be valid utf-8".to_string()))),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_eval_file(
+    modu: *mut ModuInterpreter,
+    path: *const c_char,
+) -> *mut ModuValue {
+    let modu = unsafe { &mut *modu };
+
+    match unsafe { str_arg(path) } {
+        Some(path) => modu.run(|interpreter| interpreter.eval_file(path)),
+        None => modu.finish(Err(ModuError::from("path must be valid utf-8".to_string()))),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_call(
+    modu: *mut ModuInterpreter,
+    name: *const c_char,
+    args: *const *const ModuValue,
+    argc: usize,
+) -> *mut ModuValue {
+    let modu = unsafe { &mut *modu };
+
+    let args = if args.is_null() {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(args, argc) }
+            .iter()
+            .map(|&arg| unsafe { value_arg(arg) })
+            .collect()
+    };
+
+    match unsafe { str_arg(name) } {
+        Some(name) => modu.run(|interpreter| interpreter.call(name, args)),
+        None => modu.finish(Err(ModuError::from("name must be valid utf-8".to_string()))),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_register_fn(
+    modu: *mut ModuInterpreter,
+    name: *const c_char,
+    func: ModuCallback,
+    user_data: *mut c_void,
+) -> bool {
+    let modu = unsafe { &mut *modu };
+
+    let Some(name) = (unsafe { str_arg(name) }) else {
+        return false;
+    };
+
+    // callbacks run inside an eval or call, which catches what they panic with
+    let callback = Callback { func, user_data };
+
+    // replacing a global drops the old value, which can run a finalizer
+    guard(false, || {
+        modu.interpreter
+            .register_fn(name, move |args| callback.call(args));
+        true
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_get_global(
+    modu: *const ModuInterpreter,
+    name: *const c_char,
+) -> *mut ModuValue {
+    let modu = unsafe { &*modu };
+
+    match unsafe { str_arg(name) }.and_then(|name| modu.interpreter.get_global(name)) {
+        Some(value) => into_handle(value),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_set_global(
+    modu: *mut ModuInterpreter,
+    name: *const c_char,
+    value: *const ModuValue,
+) -> bool {
+    let modu = unsafe { &mut *modu };
+
+    let Some(name) = (unsafe { str_arg(name) }) else {
+        return false;
+    };
+
+    let value = unsafe { value_arg(value) };
+
+    guard(false, || {
+        modu.interpreter.set_global(name, value);
+        true
+    })
+}
+
+// errors from the last eval or call
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_error_message(modu: *const ModuInterpreter) -> *const c_char {
+    match &unsafe { &*modu }.message {
+        Some(message) => message.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_error_span(
+    modu: *const ModuInterpreter,
+    start: *mut usize,
+    end: *mut usize,
+) -> bool {
+    let Some(span) = unsafe { &*modu }
+        .error
+        .as_ref()
+        .and_then(|e| e.span.clone())
+    else {
+        return false;
+    };
+
+    unsafe {
+        if !start.is_null() {
+            *start = span.start;
+        }
+
+        if !end.is_null() {
+            *end = span.end;
+        }
+    }
+
+    true
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_error_location(
+    modu: *const ModuInterpreter,
+    line: *mut usize,
+    column: *mut usize,
+) -> bool {
+    let Some((l, c)) = unsafe { &*modu }.error.as_ref().and_then(|e| e.location()) else {
+        return false;
+    };
+
+    unsafe {
+        if !line.is_null() {
+            *line = l;
+        }
+
+        if !column.is_null() {
+            *column = c;
+        }
+    }
+
+    true
+}
+
+// making values
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_null() -> *mut ModuValue {
+    into_handle(Value::Null)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_bool(value: bool) -> *mut ModuValue {
+    into_handle(Value::Bool(value))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_int(value: i64) -> *mut ModuValue {
+    into_handle(Value::Int(value))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_float(value: f64) -> *mut ModuValue {
+    into_handle(Value::Float(value))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_string(value: *const c_char) -> *mut ModuValue {
+    if value.is_null() {
+        return into_handle(Value::Null);
+    }
+
+    let text = unsafe { CStr::from_ptr(value) }
+        .to_string_lossy()
+        .to_string();
+    into_handle(Value::String(text))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_array() -> *mut ModuValue {
+    into_handle(Value::Array(Vec::new()))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn modu_object() -> *mut ModuValue {
+    into_handle(Value::Object(Default::default()))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_error(message: *const c_char) -> *mut ModuValue {
+    let message = if message.is_null() {
+        "error".to_string()
+    } else {
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .to_string()
+    };
+
+    Box::into_raw(Box::new(ModuValue {
+        value: Err(message),
+        text: OnceCell::new(),
+        type_name: OnceCell::new(),
+    }))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_value_clone(value: *const ModuValue) -> *mut ModuValue {
+    let value = unsafe { &*value };
+
+    Box::into_raw(Box::new(ModuValue {
+        value: value.value.clone(),
+        text: OnceCell::new(),
+        type_name: OnceCell::new(),
+    }))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_value_free(value: *mut ModuValue) {
+    if !value.is_null() {
+        guard((), || drop(unsafe { Box::from_raw(value) }));
+    }
+}
+
+// reading values
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_type(value: *const ModuValue) -> ModuType {
+    match &unsafe { &*value }.value {
+        Err(_) => ModuType::Error,
+        Ok(Value::Null) => ModuType::Null,
+        Ok(Value::Bool(_)) => ModuType::Bool,
+        Ok(Value::Int(_)) => ModuType::Int,
+        Ok(Value::Float(_)) => ModuType::Float,
+        Ok(Value::String(_)) => ModuType::String,
+        Ok(Value::Array(_)) => ModuType::Array,
+        Ok(Value::Object(_)) => ModuType::Object,
+        Ok(
+            Value::Function { .. }
+            | Value::NativeFn(_)
+            | Value::BuiltinFn(_)
+            | Value::InstanceFn { .. },
+        ) => ModuType::Function,
+        Ok(Value::Native(_)) => ModuType::Native,
+        Ok(_) => ModuType::Other,
+    }
+}
+
+// the name modu uses, like type() does
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_type_name(value: *const ModuValue) -> *const c_char {
+    let name = match unsafe { &*value }.value() {
+        Some(value) => value.type_name(),
+        None => "error",
+    };
+
+    unsafe { &*value }
+        .type_name
+        .get_or_init(|| c_string(name))
+        .as_ptr()
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_as_bool(value: *const ModuValue) -> bool {
+    unsafe { &*value }.value().is_some_and(Value::truthy)
+}
+
+// floats are truncated, anything else is 0
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_as_int(value: *const ModuValue) -> i64 {
+    match unsafe { &*value }.value() {
+        Some(Value::Int(i)) => *i,
+        Some(Value::Float(f)) => *f as i64,
+        Some(Value::Bool(b)) => *b as i64,
+        _ => 0,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_as_float(value: *const ModuValue) -> f64 {
+    match unsafe { &*value }.value() {
+        Some(Value::Float(f)) => *f,
+        Some(Value::Int(i)) => *i as f64,
+        _ => 0.0,
+    }
+}
+
+// NULL unless it is a string (or an error, which gives its message),
+// the pointer lives as long as the handle does
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_as_string(value: *const ModuValue) -> *const c_char {
+    let value = unsafe { &*value };
+
+    let text = match &value.value {
+        Ok(Value::String(s)) => s,
+        Err(message) => message,
+        _ => return std::ptr::null(),
+    };
+
+    value.text.get_or_init(|| c_string(text)).as_ptr()
+}
+
+// how print() would show it, free it with modu_string_free
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_to_string(value: *const ModuValue) -> *mut c_char {
+    // native values are shown by the host's display function
+    guard(std::ptr::null_mut(), || {
+        let text = match &unsafe { &*value }.value {
+            Ok(value) => value.to_string(),
+            Err(message) => message.clone(),
+        };
+
+        c_string(&text).into_raw()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(unsafe { CString::from_raw(text) });
+    }
+}
+
+// arrays
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_array_len(array: *const ModuValue) -> usize {
+    match unsafe { &*array }.value() {
+        Some(Value::Array(items)) => items.len(),
+        _ => 0,
+    }
+}
+
+// NULL when out of range or not an array
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_array_get(array: *const ModuValue, index: usize) -> *mut ModuValue {
+    match unsafe { &*array }.value() {
+        Some(Value::Array(items)) if index < items.len() => into_handle(items[index].clone()),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_array_push(array: *mut ModuValue, item: *const ModuValue) -> bool {
+    let item = unsafe { value_arg(item) };
+
+    match &mut unsafe { &mut *array }.value {
+        Ok(Value::Array(items)) => {
+            items.push(item);
+            true
+        }
+
+        _ => false,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_array_set(
+    array: *mut ModuValue,
+    index: usize,
+    item: *const ModuValue,
+) -> bool {
+    let item = unsafe { value_arg(item) };
+
+    match &mut unsafe { &mut *array }.value {
+        Ok(Value::Array(items)) if index < items.len() => {
+            items[index] = item;
+            true
+        }
+
+        _ => false,
+    }
+}
+
+// objects
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_object_len(object: *const ModuValue) -> usize {
+    match unsafe { &*object }.value() {
+        Some(Value::Object(map)) => map.len(),
+        _ => 0,
+    }
+}
+
+// an array of the keys, sorted so walking an object is deterministic
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_object_keys(object: *const ModuValue) -> *mut ModuValue {
+    let mut keys: Vec<String> = match unsafe { &*object }.value() {
+        Some(Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+
+    keys.sort();
+    into_handle(Value::Array(keys.into_iter().map(Value::String).collect()))
+}
+
+// NULL when the key isnt there
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_object_get(
+    object: *const ModuValue,
+    key: *const c_char,
+) -> *mut ModuValue {
+    let value = match (unsafe { &*object }.value(), unsafe { str_arg(key) }) {
+        (Some(Value::Object(map)), Some(key)) => map.get(key).cloned(),
+        _ => None,
+    };
+
+    value.map_or(std::ptr::null_mut(), into_handle)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn modu_object_set(
+    object: *mut ModuValue,
+    key: *const c_char,
+    item: *const ModuValue,
+) -> bool {
+    let item = unsafe { value_arg(item) };
+
+    match (&mut unsafe { &mut *object }.value, unsafe { str_arg(key) }) {
+        (Ok(Value::Object(map)), Some(key)) => {
+            map.insert(key.to_string(), item);
+            true
+        }
+
+        _ => false,
+    }
+}
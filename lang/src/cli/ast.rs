@@ -0,0 +1,25 @@
+use super::CliError;
+use crate::inspect;
+use crate::vm::chunk::SourceFile;
+
+fn usage() {
+    println!("Usage: modu ast <file> [--format json]");
+}
+
+pub fn ast() -> Result<(), CliError> {
+    let Some((path, code, json)) = super::disasm::args(usage)? else {
+        return Ok(());
+    };
+
+    let ast = inspect::parse(&code, &path)?;
+    let source = SourceFile::new(&path, &code);
+
+    if json {
+        let report = inspect::ast_json(&ast, &source);
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", inspect::ast_text(&ast, &source));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use super::{CliError, EXIT_COMPILE, EXIT_PARSE};
+use crate::analysis;
+use crate::compiler::compiler::Compiler;
+use crate::error::{ErrorKind, ModuError, Severity};
+use crate::parser::parse_source;
+use crate::vm::chunk::SourceFile;
+use colored::Colorize;
+use serde_json::json;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+fn usage() {
+    println!("Usage: modu check [paths] [--format json]");
+}
+
+fn stdlib_exists(name: &str) -> bool {
+    let context = crate::stdlib::Context {
+        permissions: std::sync::Arc::new(crate::Permissions::default()),
+        owner: usize::MAX,
+    };
+
+    crate::stdlib::get(name, &context).is_some()
+}
+
+// everything wrong with one file without running it, and the files it imports
+fn check_file(path: &Path, errors: &mut Vec<ModuError>) -> Result<Vec<PathBuf>, CliError> {
+    let name = path.display().to_string();
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| CliError::Failed(format!("could not read {}: {}", name, e)))?;
+
+    let ast = match parse_source(&text, &name) {
+        Ok(ast) => ast,
+        Err(parse_errors) => {
+            errors.extend(parse_errors);
+            return Ok(Vec::new());
+        }
+    };
+
+    let source = SourceFile::new(&name, &text);
+    let first = errors.len();
+
+    // a statement at a time, so one that doesn't compile doesn't hide the rest
+    let mut compiler = Compiler::new();
+
+    for expr in &ast {
+        if let Err(e) = compiler.compile_program(vec![expr.clone()]) {
+            errors.push(
+                ModuError::new(ErrorKind::Compile, e)
+                    .with_code(6)
+                    .at(expr.span.into_range(), source.clone()),
+            );
+        }
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut imports = Vec::new();
+
+    for import in analysis::analyze(&text, &ast).imports {
+        let missing = if let Some(module) = import.path.strip_prefix("std/") {
+            !stdlib_exists(module)
+        } else if import.path.starts_with("native:") {
+            false
+        } else {
+            match analysis::resolve_import(dir, &import.path) {
+                Some(resolved) => {
+                    imports.push(resolved);
+                    false
+                }
+                None => true,
+            }
+        };
+
+        if missing {
+            errors.push(
+                ModuError::new(
+                    ErrorKind::Compile,
+                    format!("cannot find module '{}'", import.path),
+                )
+                .with_code(7)
+                .at(import.span, source.clone()),
+            );
+        }
+    }
+
+    errors[first..].sort_by_key(|e| e.span.as_ref().map(|span| span.start));
+    Ok(imports)
+}
+
+pub fn check() -> Result<(), CliError> {
+    let args = std::env::args().skip(2).collect::<Vec<String>>();
+
+    let mut json = false;
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => match iter.next().map(String::as_str) {
+                Some("json") => json = true,
+                Some("text") => json = false,
+                _ => return Err(CliError::Usage("--format expects text or json".to_string())),
+            },
+            "--format=json" => json = true,
+            "--format=text" => json = false,
+
+            "--help" | "-h" => {
+                usage();
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                usage();
+                return Err(CliError::Usage(format!("unknown flag {}", flag)));
+            }
+
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut queue = VecDeque::new();
+
+    for path in &paths {
+        if !path.exists() {
+            return Err(CliError::Usage(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+
+        let mut files = Vec::new();
+        super::fmt::collect(path, &mut files);
+        queue.extend(files);
+    }
+
+    let mut seen = HashSet::new();
+    let mut checked = 0;
+    let mut errors = Vec::new();
+
+    while let Some(path) = queue.pop_front() {
+        // the same file can be named and imported, or imported twice
+        if !seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())) {
+            continue;
+        }
+
+        checked += 1;
+        queue.extend(check_file(&path, &mut errors)?);
+    }
+
+    if json {
+        let report: Vec<_> = errors
+            .iter()
+            .map(|e| {
+                let file = e.source.as_ref().map(|s| s.path.display().to_string());
+                let (line, column) = e.location().unzip();
+                let end = match (&e.span, &e.source) {
+                    (Some(span), Some(source)) => {
+                        Some((source.line(span.end), source.column(span.end)))
+                    }
+                    _ => None,
+                };
+                let (end_line, end_column) = end.unzip();
+
+                json!({
+                    "file": file,
+                    "severity": match e.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    },
+                    "code": e.code_name(),
+                    "message": e.message,
+                    "help": e.help,
+                    "line": line,
+                    "column": column,
+                    "end_line": end_line,
+                    "end_column": end_column,
+                })
+            })
+            .collect();
+
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        for e in &errors {
+            eprint!("{}", e.render());
+        }
+
+        let summary = format!(
+            "{} error{} in {} file{}",
+            errors.len(),
+            if errors.len() == 1 { "" } else { "s" },
+            checked,
+            if checked == 1 { "" } else { "s" },
+        );
+
+        if errors.is_empty() {
+            eprintln!("{}", summary.green());
+        } else {
+            eprintln!("{}", summary);
+        }
+    }
+
+    if errors.iter().any(|e| e.kind == ErrorKind::Parse) {
+        Err(CliError::Exit(EXIT_PARSE))
+    } else if !errors.is_empty() {
+        Err(CliError::Exit(EXIT_COMPILE))
+    } else {
+        Ok(())
+    }
+}
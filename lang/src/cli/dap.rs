@@ -0,0 +1,655 @@
+// a debug adapter protocol server over stdio, so editors can debug modu
+// https://microsoft.github.io/debug-adapter-protocol/specification
+//
+// the program runs on its own thread with a Debugger on its vm, requests
+// that need the vm (stack traces, variables, stepping) are sent over to it
+// and answered from there, the rest are answered here
+
+use super::CliError;
+use crate::debugger::{Breakpoints, Debugger, Frontend, Reason, Resume};
+use crate::error::ErrorKind;
+use crate::interpreter::Interpreter;
+use crate::vm::value::Value;
+use crate::vm::vm::{InterruptHandle, VM};
+use serde_json::{Value as Json, json};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+// there is only ever one thread as far as the editor is concerned
+const THREAD_ID: i64 = 1;
+
+// messages are json with a Content-Length header in front, lsp does the same
+pub(super) fn read_message(reader: &mut impl BufRead) -> Option<Json> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        let line = line.trim();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+
+    serde_json::from_slice(&body).ok()
+}
+
+struct Output {
+    writer: Box<dyn Write + Send>,
+    seq: i64,
+}
+
+// both threads send things to the editor, this keeps the messages whole
+#[derive(Clone)]
+struct Client(Arc<Mutex<Output>>);
+
+impl Client {
+    fn new(writer: impl Write + Send + 'static) -> Self {
+        Client(Arc::new(Mutex::new(Output {
+            writer: Box::new(writer),
+            seq: 0,
+        })))
+    }
+
+    fn send(&self, mut message: Json) {
+        let mut output = self.0.lock().unwrap();
+
+        output.seq += 1;
+        message["seq"] = json!(output.seq);
+
+        let body = message.to_string();
+        let _ = write!(
+            output.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = output.writer.flush();
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    fn respond(&self, request: &Json, body: Json) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn fail(&self, request: &Json, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+            "body": { "error": { "id": 1, "format": message } },
+        }));
+    }
+}
+
+// what the program prints shows up in the editor's debug console
+struct OutputEvents {
+    client: Client,
+    category: &'static str,
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.client.event(
+            "output",
+            json!({ "category": self.category, "output": String::from_utf8_lossy(buf) }),
+        );
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// replaces every breakpoint in the file, which is how the editor sends them
+fn set_breakpoints(client: &Client, request: &Json, breakpoints: &mut Breakpoints) {
+    let args = &request["arguments"];
+
+    let Some(path) = args["source"]["path"].as_str() else {
+        client.fail(request, "setBreakpoints needs a source path");
+        return;
+    };
+
+    let path = PathBuf::from(path);
+    let path = path.canonicalize().unwrap_or(path);
+
+    breakpoints.clear(&path);
+
+    let lines: Vec<i64> = args["breakpoints"]
+        .as_array()
+        .map(|list| list.iter().filter_map(|b| b["line"].as_i64()).collect())
+        .unwrap_or_default();
+
+    for &line in &lines {
+        breakpoints.add(&path, line as usize);
+    }
+
+    let verified: Vec<Json> = lines
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect();
+
+    client.respond(request, json!({ "breakpoints": verified }));
+}
+
+// what a variablesReference points at, they only last while it is paused
+enum Handle {
+    Locals(usize),
+    Globals,
+    Value(Value),
+}
+
+struct Adapter {
+    client: Client,
+    requests: Receiver<Json>,
+    handles: Vec<Handle>,
+}
+
+impl Adapter {
+    fn handle(&mut self, handle: Handle) -> usize {
+        self.handles.push(handle);
+        self.handles.len()
+    }
+
+    // arrays, objects and instances can be opened up in the editor
+    fn reference(&mut self, value: &Value) -> usize {
+        let expandable = match value {
+            Value::Array(items) => !items.is_empty(),
+            Value::Object(members) => !members.is_empty(),
+            Value::Instance { properties, .. } => !properties.is_empty(),
+            _ => false,
+        };
+
+        if expandable {
+            self.handle(Handle::Value(value.clone()))
+        } else {
+            0
+        }
+    }
+
+    fn variable(&mut self, name: &str, value: &Value) -> Json {
+        json!({
+            "name": name,
+            "value": super::debug::show(value),
+            "type": value.type_name(),
+            "variablesReference": self.reference(value),
+        })
+    }
+
+    fn variables(&mut self, vm: &VM, reference: usize) -> Option<Vec<Json>> {
+        let handle = self.handles.get(reference.checked_sub(1)?)?;
+
+        let members: Vec<(String, Value)> = match handle {
+            Handle::Locals(depth) => vm.locals(*depth),
+            Handle::Globals => {
+                let mut globals: Vec<_> = vm
+                    .globals
+                    .iter()
+                    .filter(|(_, v)| !matches!(v, Value::BuiltinFn(_)))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+
+                globals.sort_by(|a, b| a.0.cmp(&b.0));
+                globals
+            }
+
+            Handle::Value(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v.clone()))
+                .collect(),
+
+            Handle::Value(Value::Object(members))
+            | Handle::Value(Value::Instance {
+                properties: members,
+                ..
+            }) => {
+                let mut members: Vec<_> = members
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+
+                members.sort_by(|a, b| a.0.cmp(&b.0));
+                members
+            }
+
+            Handle::Value(_) => Vec::new(),
+        };
+
+        Some(
+            members
+                .into_iter()
+                .map(|(name, value)| self.variable(&name, &value))
+                .collect(),
+        )
+    }
+
+    fn stack_trace(&self, vm: &VM) -> Json {
+        let frames: Vec<Json> = vm
+            .frames()
+            .iter()
+            .rev()
+            .map(|frame| match &frame.source {
+                Some(source) => json!({
+                    "id": frame.depth,
+                    "name": frame.name,
+                    "line": source.line(frame.span.start),
+                    "column": source.column(frame.span.start),
+                    "source": {
+                        "name": source.path.file_name().map(|n| n.to_string_lossy()),
+                        "path": source.path,
+                    },
+                }),
+
+                None => json!({ "id": frame.depth, "name": frame.name, "line": 0, "column": 0 }),
+            })
+            .collect();
+
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn evaluate(&mut self, vm: &VM, request: &Json) {
+        let args = &request["arguments"];
+        let expression = args["expression"].as_str().unwrap_or("").trim();
+
+        // frameId is the depth, no frame means the innermost one
+        let depth = args["frameId"]
+            .as_u64()
+            .map(|id| id as usize)
+            .unwrap_or_else(|| vm.depth().saturating_sub(1));
+
+        let code = if expression.ends_with(';') || expression.ends_with('}') {
+            expression.to_string()
+        } else {
+            format!("{};", expression)
+        };
+
+        match vm.evaluate(depth, &code) {
+            Ok(value) => {
+                let body = json!({
+                    "result": super::debug::show(&value),
+                    "type": value.type_name(),
+                    "variablesReference": self.reference(&value),
+                });
+
+                self.client.respond(request, body);
+            }
+
+            Err(e) => self.client.fail(request, &e.message),
+        }
+    }
+
+    // answers a request sent over from the main thread, Some when the
+    // program should get going again
+    fn request(
+        &mut self,
+        vm: &VM,
+        request: &Json,
+        breakpoints: &mut Breakpoints,
+        paused: bool,
+    ) -> Option<Resume> {
+        let command = request["command"].as_str().unwrap_or("");
+
+        let resume = match command {
+            "continue" => Resume::Continue,
+            "next" => Resume::StepOver,
+            "stepIn" => Resume::StepIn,
+            "stepOut" => Resume::StepOut,
+
+            // the main thread answers these once the program is done
+            "disconnect" | "terminate" => return Some(Resume::Stop),
+
+            "setBreakpoints" => {
+                set_breakpoints(&self.client, request, breakpoints);
+                return None;
+            }
+
+            _ if !paused => {
+                self.client.fail(request, "the program is running");
+                return None;
+            }
+
+            "stackTrace" => {
+                self.client.respond(request, self.stack_trace(vm));
+                return None;
+            }
+
+            "scopes" => {
+                let depth = request["arguments"]["frameId"].as_u64().unwrap_or(0) as usize;
+                let locals = self.handle(Handle::Locals(depth));
+                let globals = self.handle(Handle::Globals);
+
+                let body = json!({ "scopes": [
+                    { "name": "Locals", "variablesReference": locals, "expensive": false },
+                    { "name": "Globals", "variablesReference": globals, "expensive": false },
+                ]});
+
+                self.client.respond(request, body);
+                return None;
+            }
+
+            "variables" => {
+                let reference = request["arguments"]["variablesReference"]
+                    .as_u64()
+                    .unwrap_or(0) as usize;
+
+                match self.variables(vm, reference) {
+                    Some(variables) => self
+                        .client
+                        .respond(request, json!({ "variables": variables })),
+                    None => self.client.fail(request, "unknown variablesReference"),
+                }
+
+                return None;
+            }
+
+            "evaluate" => {
+                self.evaluate(vm, request);
+                return None;
+            }
+
+            command => {
+                self.client
+                    .fail(request, &format!("unsupported request '{}'", command));
+                return None;
+            }
+        };
+
+        if !paused {
+            self.client.fail(request, "the program is not paused");
+            return None;
+        }
+
+        self.client
+            .respond(request, json!({ "allThreadsContinued": true }));
+        Some(resume)
+    }
+}
+
+impl Frontend for Adapter {
+    fn paused(&mut self, vm: &VM, reason: &Reason, breakpoints: &mut Breakpoints) -> Resume {
+        self.handles.clear();
+
+        let mut body = json!({ "threadId": THREAD_ID, "allThreadsStopped": true });
+
+        body["reason"] = json!(match reason {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+            Reason::Pause => "pause",
+            Reason::Error(_) => "exception",
+        });
+
+        if let Reason::Error(e) = reason {
+            body["description"] = json!("Uncaught error");
+            body["text"] = json!(e.message);
+        }
+
+        self.client.event("stopped", body);
+
+        loop {
+            // the main thread is gone, nobody is going to resume it
+            let Ok(request) = self.requests.recv() else {
+                return Resume::Stop;
+            };
+
+            if let Some(resume) = self.request(vm, &request, breakpoints, true) {
+                return resume;
+            }
+        }
+    }
+
+    fn poll(&mut self, vm: &VM, breakpoints: &mut Breakpoints) -> bool {
+        loop {
+            let Ok(request) = self.requests.try_recv() else {
+                return false;
+            };
+
+            if request["command"] == "pause" {
+                self.client.respond(&request, json!({}));
+                return true;
+            }
+
+            // stopping while it runs goes through the interrupt handle
+            self.request(vm, &request, breakpoints, false);
+        }
+    }
+}
+
+struct Launch {
+    program: PathBuf,
+    stop_on_entry: bool,
+    no_debug: bool,
+}
+
+struct Program {
+    requests: Sender<Json>,
+    interrupt: InterruptHandle,
+    thread: JoinHandle<()>,
+}
+
+fn start(
+    client: &Client,
+    launch: &Launch,
+    breakpoints: Breakpoints,
+    break_on_error: bool,
+) -> Program {
+    let (sender, requests) = channel();
+
+    let mut interpreter = Interpreter::new();
+
+    interpreter.set_stdout(OutputEvents {
+        client: client.clone(),
+        category: "stdout",
+    });
+
+    interpreter.set_stderr(OutputEvents {
+        client: client.clone(),
+        category: "stderr",
+    });
+
+    // stdin is where the editor talks to us, input() gets nothing
+    interpreter.set_stdin(std::io::empty());
+
+    if !launch.no_debug {
+        let adapter = Adapter {
+            client: client.clone(),
+            requests,
+            handles: Vec::new(),
+        };
+
+        let debugger = Debugger::new(adapter)
+            .with_breakpoints(breakpoints)
+            .stop_on_entry(launch.stop_on_entry)
+            .break_on_error(break_on_error);
+
+        interpreter.vm().set_hook(debugger);
+    }
+
+    let interrupt = interpreter.interrupt_handle();
+    let client = client.clone();
+    let program = launch.program.clone();
+
+    let thread = std::thread::spawn(move || {
+        let code = match interpreter.eval_file(&program) {
+            Ok(_) => 0,
+            Err(e) => match e.kind {
+                ErrorKind::Interrupted => 0,
+                ErrorKind::Exit(code) => code as i32,
+                _ => {
+                    client.event(
+                        "output",
+                        json!({ "category": "stderr", "output": format!("{}\n", e) }),
+                    );
+                    1
+                }
+            },
+        };
+
+        client.event("exited", json!({ "exitCode": code }));
+        client.event("terminated", json!({}));
+    });
+
+    Program {
+        requests: sender,
+        interrupt,
+        thread,
+    }
+}
+
+fn stop(program: Option<Program>) {
+    if let Some(program) = program {
+        program.interrupt.interrupt();
+
+        // wakes it up if it is paused
+        let _ = program.requests.send(json!({ "command": "disconnect" }));
+        let _ = program.thread.join();
+    }
+}
+
+pub fn dap() -> Result<(), CliError> {
+    let client = Client::new(std::io::stdout());
+    let mut stdin = std::io::stdin().lock();
+
+    let mut breakpoints = Breakpoints::default();
+    let mut break_on_error = true;
+    let mut launch: Option<Launch> = None;
+    let mut program: Option<Program> = None;
+
+    while let Some(request) = read_message(&mut stdin) {
+        if request["type"] != "request" {
+            continue;
+        }
+
+        let args = &request["arguments"];
+
+        match request["command"].as_str().unwrap_or("") {
+            "initialize" => {
+                let capabilities = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                    "exceptionBreakpointFilters": [{
+                        "filter": "uncaught",
+                        "label": "Uncaught errors",
+                        "default": true,
+                    }],
+                });
+
+                client.respond(&request, capabilities);
+                client.event("initialized", json!({}));
+            }
+
+            "launch" => {
+                let Some(file) = args["program"].as_str() else {
+                    client.fail(&request, "launch needs a program");
+                    continue;
+                };
+
+                let Ok(path) = std::path::Path::new(file).canonicalize() else {
+                    client.fail(&request, &format!("File not found: {}", file));
+                    continue;
+                };
+
+                launch = Some(Launch {
+                    program: path,
+                    stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+                    no_debug: args["noDebug"].as_bool().unwrap_or(false),
+                });
+
+                client.respond(&request, json!({}));
+            }
+
+            "setExceptionBreakpoints" => {
+                break_on_error = args["filters"]
+                    .as_array()
+                    .is_some_and(|filters| filters.iter().any(|f| f == "uncaught"));
+
+                client.respond(&request, json!({}));
+            }
+
+            "configurationDone" => {
+                let Some(launch) = &launch else {
+                    client.fail(&request, "launch has to come first");
+                    continue;
+                };
+
+                client.respond(&request, json!({}));
+
+                if program.is_none() {
+                    program = Some(start(&client, launch, breakpoints.clone(), break_on_error));
+                }
+            }
+
+            "threads" => {
+                client.respond(
+                    &request,
+                    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+                );
+            }
+
+            "disconnect" => {
+                stop(program.take());
+                client.respond(&request, json!({}));
+                return Ok(());
+            }
+
+            "terminate" => {
+                stop(program.take());
+                client.respond(&request, json!({}));
+            }
+
+            // before the program starts they are kept here
+            "setBreakpoints" if program.is_none() => {
+                set_breakpoints(&client, &request, &mut breakpoints);
+            }
+
+            _ => match &program {
+                Some(program) if !program.thread.is_finished() => {
+                    if let Err(unsent) = program.requests.send(request) {
+                        client.fail(&unsent.0, "the program is not running");
+                    }
+                }
+
+                _ if request["command"] == "setBreakpoints" => {
+                    set_breakpoints(&client, &request, &mut breakpoints);
+                }
+
+                _ => client.fail(&request, "the program is not running"),
+            },
+        }
+    }
+
+    stop(program);
+    Ok(())
+}
@@ -0,0 +1,340 @@
+use super::{CliError, EXIT_USAGE};
+use crate::debugger::{Breakpoints, Debugger, Frontend, Reason, Resume};
+use crate::error::ErrorKind;
+use crate::vm::debug::Frame;
+use crate::vm::value::Value;
+use crate::vm::vm::VM;
+use colored::Colorize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const HELP: &str = "Commands:
+    break <line>           - Stop at a line of the file being debugged (b)
+    break <file>:<line>    - Stop at a line of another file, like one it imports
+    delete <line>          - Remove a breakpoint, also takes <file>:<line> (d)
+    breakpoints            - List breakpoints
+    continue               - Run until the next breakpoint (c)
+    step                   - Go to the next line, into calls (s)
+    next                   - Go to the next line, over calls (n)
+    out                    - Run until the current function returns (o)
+    backtrace              - Show the calls that got here (bt)
+    frame <n>              - Look at another frame from the backtrace (f)
+    locals                 - Show the variables of the frame
+    globals                - Show the global variables
+    print <expr>           - Evaluate an expression in the frame (p)
+    list                   - Show the code around the current line (l)
+    quit                   - Stop the program (q)
+An empty line repeats the last step, next, out or continue. What print runs can't
+change variables, but writing to a file or reading stdin does affect the program.";
+
+struct Terminal {
+    file: PathBuf, // breakpoints without a file go here
+    frame: usize,  // the one being looked at, 0 is the top
+    repeat: Option<Resume>,
+}
+
+// paths are shown relative to where modu was started from
+fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn location(frame: &Frame) -> String {
+    match (frame.path(), frame.line()) {
+        (Some(path), Some(line)) => format!("{}:{}", display_path(path), line),
+        _ => "<unknown>".to_string(),
+    }
+}
+
+pub(super) fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("\"{}\"", s),
+        _ => value.to_string(),
+    }
+}
+
+impl Terminal {
+    fn selected<'a>(&self, frames: &'a [Frame]) -> &'a Frame {
+        &frames[frames.len() - 1 - self.frame.min(frames.len() - 1)]
+    }
+
+    fn breakpoint(&self, spec: &str) -> Option<(PathBuf, usize)> {
+        match spec.rsplit_once(':') {
+            Some((file, line)) => Some((PathBuf::from(file), line.trim().parse().ok()?)),
+            None => Some((self.file.clone(), spec.trim().parse().ok()?)),
+        }
+    }
+
+    fn print_line(&self, frame: &Frame) {
+        let (Some(source), Some(line)) = (&frame.source, frame.line()) else {
+            return;
+        };
+
+        if let Some(text) = source.line_text(line) {
+            println!("{:>4} | {}", line, text);
+        }
+    }
+
+    fn list(&self, frame: &Frame) {
+        let (Some(source), Some(current)) = (&frame.source, frame.line()) else {
+            println!("No source for this frame");
+            return;
+        };
+
+        let first = current.saturating_sub(5).max(1);
+        let last = (current + 5).min(source.line_count());
+
+        for line in first..=last {
+            let marker = if line == current { ">" } else { " " };
+            println!(
+                "{} {:>4} | {}",
+                marker,
+                line,
+                source.line_text(line).unwrap_or_default()
+            );
+        }
+    }
+
+    fn backtrace(&self, frames: &[Frame]) {
+        for (i, frame) in frames.iter().rev().enumerate() {
+            let marker = if i == self.frame { ">" } else { " " };
+            println!("{} #{} {} at {}", marker, i, frame.name, location(frame));
+        }
+    }
+}
+
+impl Frontend for Terminal {
+    fn paused(&mut self, vm: &VM, reason: &Reason, breakpoints: &mut Breakpoints) -> Resume {
+        let frames = vm.frames();
+        self.frame = 0;
+
+        let Some(top) = frames.last() else {
+            return Resume::Continue;
+        };
+
+        match reason {
+            Reason::Error(e) => {
+                println!(
+                    "{} at {}: {}",
+                    "Uncaught error".red(),
+                    location(top),
+                    e.message
+                );
+            }
+
+            Reason::Breakpoint => println!("Paused at {} (breakpoint)", location(top)),
+            Reason::Entry | Reason::Step | Reason::Pause => println!("Paused at {}", location(top)),
+        }
+
+        self.print_line(top);
+
+        loop {
+            print!("{} ", "(modu)".dimmed());
+            std::io::stdout().flush().unwrap();
+
+            let mut line = String::new();
+
+            // nobody left to type commands
+            if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                println!();
+                return Resume::Stop;
+            }
+
+            let line = line.trim();
+            let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+            let arg = arg.trim();
+
+            let resume = match command {
+                "" => self.repeat,
+
+                "c" | "continue" => Some(Resume::Continue),
+                "s" | "step" => Some(Resume::StepIn),
+                "n" | "next" => Some(Resume::StepOver),
+                "o" | "out" => Some(Resume::StepOut),
+                "q" | "quit" => return Resume::Stop,
+
+                "b" | "break" => {
+                    match self.breakpoint(arg) {
+                        Some((file, line)) => {
+                            breakpoints.add(&file, line);
+                            println!("Breakpoint set at {}:{}", display_path(&file), line);
+                        }
+
+                        None => println!("Usage: break <line> or break <file>:<line>"),
+                    }
+
+                    None
+                }
+
+                "d" | "delete" => {
+                    match self.breakpoint(arg) {
+                        Some((file, line)) if breakpoints.remove(&file, line) => {
+                            println!("Removed breakpoint at {}:{}", display_path(&file), line);
+                        }
+
+                        Some((file, line)) => {
+                            println!("No breakpoint at {}:{}", display_path(&file), line);
+                        }
+
+                        None => println!("Usage: delete <line> or delete <file>:<line>"),
+                    }
+
+                    None
+                }
+
+                "breakpoints" => {
+                    if breakpoints.iter().next().is_none() {
+                        println!("No breakpoints");
+                    }
+
+                    for (file, line) in breakpoints.iter() {
+                        println!("{}:{}", display_path(file), line);
+                    }
+
+                    None
+                }
+
+                "bt" | "backtrace" => {
+                    self.backtrace(&frames);
+                    None
+                }
+
+                "f" | "frame" => {
+                    match arg.parse::<usize>() {
+                        Ok(n) if n < frames.len() => {
+                            self.frame = n;
+                            let frame = self.selected(&frames);
+
+                            println!("#{} {} at {}", n, frame.name, location(frame));
+                            self.print_line(frame);
+                        }
+
+                        _ => println!("Usage: frame <0-{}>", frames.len() - 1),
+                    }
+
+                    None
+                }
+
+                "locals" => {
+                    let locals = vm.locals(self.selected(&frames).depth);
+
+                    if locals.is_empty() {
+                        println!("No locals");
+                    }
+
+                    for (name, value) in locals {
+                        println!("{} = {}", name, show(&value));
+                    }
+
+                    None
+                }
+
+                "globals" => {
+                    let mut globals: Vec<_> = vm
+                        .globals
+                        .iter()
+                        .filter(|(_, v)| !matches!(v, Value::BuiltinFn(_)))
+                        .collect();
+
+                    globals.sort_by(|a, b| a.0.cmp(b.0));
+
+                    for (name, value) in globals {
+                        println!("{} = {}", name, show(value));
+                    }
+
+                    None
+                }
+
+                "p" | "print" => {
+                    if arg.is_empty() {
+                        println!("Usage: print <expr>");
+                        continue;
+                    }
+
+                    let code = if arg.ends_with(';') || arg.ends_with('}') {
+                        arg.to_string()
+                    } else {
+                        format!("{};", arg)
+                    };
+
+                    match vm.evaluate(self.selected(&frames).depth, &code) {
+                        Ok(value) => println!("{}", show(&value)),
+                        Err(e) => println!("{}", e.message.red()),
+                    }
+
+                    None
+                }
+
+                "l" | "list" => {
+                    self.list(self.selected(&frames));
+                    None
+                }
+
+                "h" | "help" => {
+                    println!("{}", HELP);
+                    None
+                }
+
+                command => {
+                    println!("Unknown command '{}', try help", command);
+                    None
+                }
+            };
+
+            if let Some(resume) = resume {
+                self.repeat = Some(resume);
+                return resume;
+            }
+        }
+    }
+}
+
+pub fn debug() -> Result<(), CliError> {
+    let args = std::env::args().collect::<Vec<String>>();
+
+    let Some(file) = args.iter().skip(2).find(|arg| !arg.starts_with("--")) else {
+        println!("Usage: modu debug <file> [--no-break-on-error]");
+        return Err(CliError::Exit(EXIT_USAGE));
+    };
+
+    let Ok(path) = std::path::Path::new(file).canonicalize() else {
+        return Err(CliError::Usage(format!("file not found: {}", file)));
+    };
+
+    let terminal = Terminal {
+        file: path.clone(),
+        frame: 0,
+        repeat: None,
+    };
+
+    let debugger =
+        Debugger::new(terminal).break_on_error(!args.contains(&"--no-break-on-error".to_string()));
+
+    let mut interpreter = crate::interpreter::Interpreter::new();
+    interpreter.vm().set_hook(debugger);
+
+    // input() and the debugger read from the same stdin, so it can't read ahead
+    interpreter.set_stdin(std::io::BufReader::with_capacity(1, std::io::stdin()));
+
+    println!("Debugging {}, type help for commands", file);
+
+    // the output goes with the session, the status is the program's
+    match interpreter.eval_file(&path) {
+        Ok(_) => println!("Program finished"),
+        Err(e) if e.kind == ErrorKind::Interrupted => println!("Program stopped"),
+        Err(e) => {
+            match e.kind {
+                ErrorKind::Exit(code) => println!("Program exited with code {}", code),
+                _ => println!("{}", e.render()),
+            }
+
+            return Err(CliError::Exit(super::exit_code(e.kind)));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use super::CliError;
+use crate::inspect;
+
+fn usage() {
+    println!("Usage: modu disasm <file> [--format json]");
+}
+
+// the args modu disasm and modu ast share, the file and if it's json
+pub(super) fn args(usage: fn()) -> Result<Option<(String, String, bool)>, CliError> {
+    let args = std::env::args().skip(2).collect::<Vec<String>>();
+
+    let mut json = false;
+    let mut path = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => match iter.next().map(String::as_str) {
+                Some("json") => json = true,
+                Some("text") => json = false,
+                _ => return Err(CliError::Usage("--format expects text or json".to_string())),
+            },
+            "--format=json" => json = true,
+            "--format=text" => json = false,
+
+            "--help" | "-h" => {
+                usage();
+                return Ok(None);
+            }
+            flag if flag.starts_with("--") => {
+                usage();
+                return Err(CliError::Usage(format!("unknown flag {}", flag)));
+            }
+
+            file if path.is_none() => path = Some(file.to_string()),
+            file => return Err(CliError::Usage(format!("unexpected argument {}", file))),
+        }
+    }
+
+    let Some(path) = path else {
+        usage();
+        return Err(CliError::Usage("no file given".to_string()));
+    };
+
+    let code = std::fs::read_to_string(&path)
+        .map_err(|e| CliError::Usage(format!("could not read {}: {}", path, e)))?;
+
+    Ok(Some((path, code, json)))
+}
+
+pub fn disasm() -> Result<(), CliError> {
+    let Some((path, code, json)) = args(usage)? else {
+        return Ok(());
+    };
+
+    let chunks = inspect::compile(&code, &path)?;
+
+    if json {
+        let report = inspect::disassemble_json(&chunks);
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print!("{}", inspect::disassemble(&chunks));
+    }
+
+    Ok(())
+}
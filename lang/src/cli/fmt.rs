@@ -0,0 +1,175 @@
+use super::{CliError, EXIT_FAILURE, EXIT_PARSE};
+use crate::formatter::{Options, format};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+fn usage() {
+    println!("Usage: modu fmt [paths] [--check] [--indent <n>]");
+}
+
+// [fmt] indent = 2 in project.toml sets the default
+fn project_indent() -> Option<usize> {
+    let contents = std::fs::read_to_string("project.toml").ok()?;
+    let toml: toml::Value = toml::from_str(&contents).ok()?;
+
+    toml.get("fmt")?
+        .get("indent")?
+        .as_integer()
+        .and_then(|n| usize::try_from(n).ok())
+}
+
+// .modu files under a directory, leaving out hidden ones like .modu/packages
+pub(super) fn collect(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+        if hidden {
+            continue;
+        }
+
+        if entry.is_dir() {
+            collect(&entry, files);
+        } else if entry.extension().is_some_and(|ext| ext == "modu") {
+            files.push(entry);
+        }
+    }
+}
+
+pub fn fmt() -> Result<(), CliError> {
+    let args = std::env::args().skip(2).collect::<Vec<String>>();
+
+    let mut options = Options::default();
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    if let Some(indent) = project_indent() {
+        options.indent = indent;
+    }
+
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let indent = match arg.as_str() {
+            "--check" => {
+                check = true;
+                continue;
+            }
+
+            "--indent" => iter.next().map(String::as_str),
+            "--help" | "-h" => {
+                usage();
+                return Ok(());
+            }
+            _ => match arg.strip_prefix("--indent=") {
+                Some(value) => Some(value),
+                None if arg.starts_with("--") => {
+                    usage();
+                    return Err(CliError::Usage(format!("unknown flag {}", arg)));
+                }
+                None => {
+                    paths.push(PathBuf::from(arg));
+                    continue;
+                }
+            },
+        };
+
+        match indent.and_then(|n| n.parse().ok()) {
+            Some(n) => options.indent = n,
+            None => {
+                return Err(CliError::Usage(
+                    "--indent expects a number of spaces".to_string(),
+                ));
+            }
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut files = Vec::new();
+
+    for path in &paths {
+        if !path.exists() {
+            return Err(CliError::Usage(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+
+        collect(path, &mut files);
+    }
+
+    let mut failed = false;
+    let mut unparsed = false;
+    let mut unformatted = 0;
+
+    for file in &files {
+        let name = file.display().to_string();
+
+        let source = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{} could not read {}: {}", "error:".red().bold(), name, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let formatted = match format(&source, &name, &options) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for err in errors {
+                    eprint!("{}", err.render());
+                }
+
+                unparsed = true;
+                continue;
+            }
+        };
+
+        if formatted == source {
+            continue;
+        }
+
+        if check {
+            println!("{} is not formatted", name);
+            unformatted += 1;
+        } else if let Err(e) = std::fs::write(file, formatted) {
+            eprintln!("{} could not write {}: {}", "error:".red().bold(), name, e);
+            failed = true;
+        } else {
+            println!("Formatted {}", name);
+        }
+    }
+
+    if check && unformatted > 0 {
+        println!(
+            "{} of {} files need formatting, run modu fmt to fix them",
+            unformatted,
+            files.len()
+        );
+    }
+
+    if unparsed {
+        Err(CliError::Exit(EXIT_PARSE))
+    } else if failed || unformatted > 0 {
+        Err(CliError::Exit(EXIT_FAILURE))
+    } else {
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
-pub fn help() {
+use super::CliError;
+
+pub fn help() -> Result<(), CliError> {
     let args = std::env::args().collect::<Vec<String>>();
 
     let stdlib = if args.len() > 2 {
@@ -14,9 +16,10 @@ pub fn help() {
         println!("  json");
         println!("  math");
         println!("  os");
+        println!("  test");
         println!("  time");
         println!("  uuid");
-        return;
+        return Ok(());
     };
 
     let contents = match stdlib.as_str() {
@@ -28,13 +31,17 @@ pub fn help() {
         "json" => include_str!("../stdlib/json.txt"),
         "math" => include_str!("../stdlib/math.txt"),
         "os" => include_str!("../stdlib/os.txt"),
+        "test" => include_str!("../stdlib/test.txt"),
         "time" => include_str!("../stdlib/time.txt"),
         "uuid" => include_str!("../stdlib/uuid.txt"),
         stdlib => {
-            println!("Unknown standard library module: {}", stdlib);
-            return;
+            return Err(CliError::Usage(format!(
+                "unknown standard library module: {}",
+                stdlib
+            )));
         }
     };
 
     println!("{}", contents);
+    Ok(())
 }
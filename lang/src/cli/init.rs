@@ -1,4 +1,6 @@
-pub fn init() {
+use super::CliError;
+
+pub fn init() -> Result<(), CliError> {
     use std::io::Write;
 
     let mut package_name = String::new();
@@ -26,7 +28,7 @@ pub fn init() {
             file.set_len(0).unwrap();
         } else {
             println!("Aborted");
-            return;
+            return Ok(());
         }
     }
 
@@ -37,16 +39,16 @@ pub fn init() {
     package_name = package_name.trim().to_string();
 
     if package_name.is_empty() {
-        println!("Package name cannot be empty");
-        return;
+        return Err(CliError::Failed("package name cannot be empty".to_string()));
     }
 
     if !package_name
         .chars()
         .all(|c| c.is_alphanumeric() || c == '_')
     {
-        println!("Package name can only contain alphanumeric characters and underscores");
-        return;
+        return Err(CliError::Failed(
+            "package name can only contain alphanumeric characters and underscores".to_string(),
+        ));
     }
 
     print!("Enter package version: ");
@@ -54,8 +56,9 @@ pub fn init() {
     std::io::stdin().read_line(&mut package_version).unwrap();
 
     if package_version.trim().is_empty() {
-        println!("Package version cannot be empty");
-        return;
+        return Err(CliError::Failed(
+            "package version cannot be empty".to_string(),
+        ));
     }
 
     print!("Enter description (optional): ");
@@ -104,4 +107,6 @@ pub fn init() {
             .write_all("print(\"Hello, world!\");".as_bytes())
             .unwrap();
     }
+
+    Ok(())
 }
@@ -1,5 +1,6 @@
 use std::io::{Read, Write};
 
+use super::CliError;
 use toml;
 
 fn install_package(backend: &str, name: &str, version: &str) -> Result<serde_json::Value, String> {
@@ -90,13 +91,14 @@ fn install_package(backend: &str, name: &str, version: &str) -> Result<serde_jso
     Ok(package)
 }
 
-pub fn install() {
+pub fn install() -> Result<(), CliError> {
     let mut content = String::new();
     let file = std::fs::File::open("project.toml");
 
     if file.is_err() {
-        println!("No project.toml found. Run `modu init` to create a new project");
-        return;
+        return Err(CliError::Failed(
+            "no project.toml found, run `modu init` to create a new project".to_string(),
+        ));
     }
 
     file.unwrap().read_to_string(&mut content).unwrap();
@@ -161,18 +163,25 @@ pub fn install() {
     println!("Installing packages, using backend {}\n", backend_url);
 
     if args.len() < 3 {
+        let mut failed = 0;
+
         for (name, version) in dependencies.iter() {
             match install_package(&backend_url, name, version.as_str().unwrap()) {
                 Ok(_) => {
                     println!("Package {} installed\n", name);
                 }
                 Err(_) => {
-                    println!("Failed to install package {}", name);
+                    eprintln!("Failed to install package {}", name);
+                    failed += 1;
                 }
             };
         }
 
-        return;
+        if failed > 0 {
+            return Err(CliError::Failed(format!("{} packages failed to install", failed)));
+        }
+
+        return Ok(());
     }
 
     let name = &(args[2].clone().split("@").collect::<Vec<&str>>()[0].to_string());
@@ -185,8 +194,7 @@ pub fn install() {
     let package = match install_package(&backend_url, name, &version) {
         Ok(package) => package,
         Err(_) => {
-            println!("Failed to install package {}", name);
-            return;
+            return Err(CliError::Failed(format!("failed to install package {}", name)));
         }
     };
 
@@ -200,4 +208,5 @@ pub fn install() {
         .unwrap();
 
     println!("Package {} installed", name);
+    Ok(())
 }
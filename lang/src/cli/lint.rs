@@ -0,0 +1,166 @@
+use super::{CliError, EXIT_FAILURE, EXIT_PARSE};
+use crate::linter::{self, Config, RULES};
+use crate::parser::parse_source;
+use crate::vm::chunk::SourceFile;
+use serde_json::json;
+use std::path::PathBuf;
+
+fn usage() {
+    println!("Usage: modu lint [paths] [--format json] [--allow <rule>] [--deny <rule>]");
+    println!("Rules:");
+
+    for (rule, description) in RULES {
+        println!("  {:<22} {}", rule, description);
+    }
+}
+
+fn config() -> Result<Config, String> {
+    let Ok(contents) = std::fs::read_to_string("project.toml") else {
+        return Ok(Config::default());
+    };
+
+    let project: toml::Value =
+        toml::from_str(&contents).map_err(|e| format!("project.toml: {}", e))?;
+
+    Config::from_toml(&project)
+}
+
+pub fn lint() -> Result<(), CliError> {
+    let args = std::env::args().skip(2).collect::<Vec<String>>();
+
+    let mut config = config().map_err(CliError::Failed)?;
+    let mut json = false;
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => match iter.next().map(String::as_str) {
+                Some("json") => json = true,
+                Some("text") => json = false,
+                _ => return Err(CliError::Usage("--format expects text or json".to_string())),
+            },
+            "--format=json" => json = true,
+            "--format=text" => json = false,
+
+            "--allow" | "--deny" => {
+                let Some(rule) = iter.next() else {
+                    return Err(CliError::Usage(format!("{} expects a rule", arg)));
+                };
+
+                config.set(rule, arg == "--deny").map_err(CliError::Usage)?;
+            }
+
+            "--help" | "-h" => {
+                usage();
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                usage();
+                return Err(CliError::Usage(format!("unknown flag {}", flag)));
+            }
+
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+
+    let mut files = Vec::new();
+
+    for path in &paths {
+        if !path.exists() {
+            return Err(CliError::Usage(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+
+        super::fmt::collect(path, &mut files);
+    }
+
+    let mut report = Vec::new();
+    let mut warnings = 0;
+    let mut errors = 0;
+
+    for path in &files {
+        let name = path.display().to_string();
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| CliError::Failed(format!("could not read {}: {}", name, e)))?;
+
+        let file = SourceFile::new(&name, &text);
+
+        let ast = match parse_source(&text, &name) {
+            Ok(ast) => ast,
+            Err(parse_errors) => {
+                for err in parse_errors {
+                    errors += 1;
+
+                    if json {
+                        let (line, column) = err.location().unwrap_or((1, 1));
+
+                        report.push(json!({
+                            "file": name,
+                            "severity": "error",
+                            "rule": "syntax",
+                            "code": err.code_name(),
+                            "message": err.message,
+                            "help": err.help,
+                            "line": line,
+                            "column": column,
+                        }));
+                    } else {
+                        eprint!("{}", err.render());
+                    }
+                }
+
+                continue;
+            }
+        };
+
+        for warning in linter::lint(&file, &ast, &config) {
+            warnings += 1;
+
+            if json {
+                report.push(json!({
+                    "file": name,
+                    "severity": "warning",
+                    "rule": warning.rule,
+                    "message": warning.message,
+                    "help": warning.help,
+                    "line": file.line(warning.span.start),
+                    "column": file.column(warning.span.start),
+                    "end_line": file.line(warning.span.end),
+                    "end_column": file.column(warning.span.end),
+                }));
+            } else {
+                eprint!("{}", warning.render(&file));
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else if warnings + errors > 0 {
+        eprintln!(
+            "{} warning{} and {} error{} in {} file{}",
+            warnings,
+            if warnings == 1 { "" } else { "s" },
+            errors,
+            if errors == 1 { "" } else { "s" },
+            files.len(),
+            if files.len() == 1 { "" } else { "s" },
+        );
+    }
+
+    if errors > 0 {
+        Err(CliError::Exit(EXIT_PARSE))
+    } else if warnings > 0 {
+        Err(CliError::Exit(EXIT_FAILURE))
+    } else {
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
+use super::CliError;
 use reqwest;
 use std::{env, io::Read, io::Write};
 
-pub fn login() {
+pub fn login() -> Result<(), CliError> {
     let path;
 
     if cfg!(windows) {
@@ -42,7 +43,7 @@ pub fn login() {
 
         if overwrite.trim() != "y" {
             println!("Aborted");
-            return;
+            return Ok(());
         }
     }
 
@@ -101,8 +102,7 @@ pub fn login() {
         .unwrap();
 
     if res.status().as_u16() != 200 {
-        println!("{}", res.text().unwrap());
-        return;
+        return Err(CliError::Failed(res.text().unwrap()));
     }
 
     let user_id = res.text().unwrap();
@@ -114,4 +114,5 @@ pub fn login() {
     let toml = toml::to_string(&toml).unwrap();
 
     config_file.write_all(toml.as_bytes()).unwrap();
+    Ok(())
 }
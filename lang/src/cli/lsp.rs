@@ -0,0 +1,958 @@
+// a language server over stdio, diagnostics while typing plus completion,
+// hover, go to definition, references and the outline of a file
+// https://microsoft.github.io/language-server-protocol/specification
+//
+// everything comes from analysis::analyze, files that are imported but not
+// open in the editor are read from disk when they are needed
+
+use super::dap::read_message;
+use super::{CliError, EXIT_FAILURE};
+use crate::analysis::{Analysis, Definition, SymbolKind, analyze, resolve_import};
+use crate::error::Severity;
+use crate::vm::value::Value;
+use serde_json::{Value as Json, json};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const KEYWORDS: &[&str] = &[
+    "let", "const", "fn", "class", "extends", "return", "if", "else", "loop", "for", "in", "while",
+    "break", "continue", "import", "as", "try", "catch", "and", "or", "not", "null", "true",
+    "false",
+];
+
+struct Document {
+    path: PathBuf,
+    text: String,
+    analysis: Rc<Analysis>, // from the last time it parsed, so it works mid-typing
+}
+
+// a file read from disk or an open one, with what analyze made of it
+type File = Rc<(String, Analysis)>;
+
+// where something is defined
+#[derive(Clone, PartialEq)]
+struct Target {
+    path: PathBuf,
+    definition: usize,
+}
+
+struct Server {
+    documents: HashMap<String, Document>, // by uri
+    root: Option<PathBuf>,
+    files: RefCell<HashMap<PathBuf, Option<File>>>, // cleared on every change
+    shutdown: bool,
+}
+
+fn send(message: Json) {
+    let body = message.to_string();
+    let mut stdout = std::io::stdout().lock();
+
+    let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = stdout.flush();
+}
+
+fn notify(method: &str, params: Json) {
+    send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+// lsp positions count utf-16 units
+fn position(text: &str, offset: usize) -> Json {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character: usize = before[start..].chars().map(char::len_utf16).sum();
+
+    json!({ "line": line, "character": character })
+}
+
+fn offset(text: &str, position: &Json) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+    let start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        },
+    };
+
+    let mut units = 0;
+
+    for (i, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + i;
+        }
+
+        units += c.len_utf16();
+    }
+
+    text.len()
+}
+
+fn range(text: &str, span: &std::ops::Range<usize>) -> Json {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// a stdlib module the way scripts get it, for its member names
+fn stdlib_module(path: &str) -> Option<HashMap<String, Value>> {
+    let context = crate::stdlib::Context {
+        permissions: std::sync::Arc::new(crate::Permissions::default()),
+        owner: usize::MAX,
+    };
+
+    match crate::stdlib::get(path.strip_prefix("std/")?, &context)? {
+        Value::Object(members) => Some(members),
+        _ => None,
+    }
+}
+
+fn natives(type_name: &str) -> Vec<String> {
+    match type_name {
+        "string" => crate::natives::string::list_fns(),
+        "array" => crate::natives::array::list_fns(),
+        "object" => crate::natives::object::list_fns(),
+        "int" => crate::natives::int::list_fns(),
+        "float" => crate::natives::float::list_fns(),
+        _ => Vec::new(),
+    }
+}
+
+fn builtins() -> Vec<String> {
+    crate::functions::get_functions(&crate::Stdio::default(), &Default::default())
+        .into_iter()
+        .map(|f| f.name)
+        .collect()
+}
+
+fn completion_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Method => 2,
+        SymbolKind::Function => 3,
+        SymbolKind::Variable | SymbolKind::Parameter => 6,
+        SymbolKind::Class => 7,
+        SymbolKind::Module => 9,
+        SymbolKind::Constant => 21,
+    }
+}
+
+fn symbol_kind(kind: SymbolKind) -> u8 {
+    match kind {
+        SymbolKind::Module => 2,
+        SymbolKind::Class => 5,
+        SymbolKind::Method => 6,
+        SymbolKind::Function => 12,
+        SymbolKind::Variable | SymbolKind::Parameter => 13,
+        SymbolKind::Constant => 14,
+    }
+}
+
+fn signature(definition: &Definition) -> String {
+    match definition.kind {
+        SymbolKind::Function | SymbolKind::Method => {
+            format!("fn {}({})", definition.name, definition.params.join(", "))
+        }
+        SymbolKind::Class => match &definition.parent {
+            Some(parent) => format!("class {} extends {}", definition.name, parent),
+            None => format!("class {}", definition.name),
+        },
+        SymbolKind::Constant => format!("const {}", definition.name),
+        SymbolKind::Parameter => format!("{} (parameter)", definition.name),
+        SymbolKind::Variable | SymbolKind::Module => format!("let {}", definition.name),
+    }
+}
+
+fn item(label: &str, kind: u8, detail: impl Into<String>) -> Json {
+    json!({ "label": label, "kind": kind, "detail": detail.into() })
+}
+
+impl Server {
+    fn file(&self, path: &Path) -> Option<File> {
+        self.files
+            .borrow_mut()
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                if let Some(document) = self.documents.values().find(|d| d.path == path) {
+                    return Some(Rc::new((
+                        document.text.clone(),
+                        (*document.analysis).clone(),
+                    )));
+                }
+
+                let text = std::fs::read_to_string(path).ok()?;
+                let ast = crate::parser::parse_source(&text, &path.display().to_string()).ok()?;
+                let analysis = analyze(&text, &ast);
+
+                Some(Rc::new((text, analysis)))
+            })
+            .clone()
+    }
+
+    fn document(&self, params: &Json) -> Option<(&Document, usize)> {
+        let document = self
+            .documents
+            .get(params["textDocument"]["uri"].as_str()?)?;
+
+        let offset = offset(&document.text, &params["position"]);
+        Some((document, offset))
+    }
+
+    fn imported(&self, from: &Path, import: usize, analysis: &Analysis) -> Option<PathBuf> {
+        resolve_import(from.parent()?, &analysis.imports[import].path)
+    }
+
+    // a name nothing in the file defines can come from a star import
+    fn star_import(&self, path: &Path, analysis: &Analysis, name: &str) -> Option<Target> {
+        (0..analysis.imports.len())
+            .filter(|&i| analysis.imports[i].alias.as_deref() == Some("*"))
+            .filter_map(|i| self.imported(path, i, analysis))
+            .find_map(|file| {
+                let definition = self.file(&file)?.1.global(name)?;
+                Some(Target {
+                    path: file,
+                    definition,
+                })
+            })
+    }
+
+    fn member_target(
+        &self,
+        path: &Path,
+        analysis: &Analysis,
+        object: Option<usize>,
+        name: &str,
+    ) -> Option<Target> {
+        let object = &analysis.definitions[object?];
+
+        let class = match object.kind {
+            SymbolKind::Module => {
+                let file = self.imported(path, object.import?, analysis)?;
+                let definition = self.file(&file)?.1.global(name)?;
+
+                return Some(Target {
+                    path: file,
+                    definition,
+                });
+            }
+
+            SymbolKind::Class => analysis.global(&object.name)?,
+            _ => analysis
+                .global(object.hint.as_deref()?)
+                .filter(|&c| analysis.definitions[c].kind == SymbolKind::Class)?,
+        };
+
+        let method = analysis
+            .methods(class)
+            .into_iter()
+            .find(|m| m.name == name)?;
+        let definition = analysis
+            .definitions
+            .iter()
+            .position(|d| std::ptr::eq(d, method))?;
+
+        Some(Target {
+            path: path.to_path_buf(),
+            definition,
+        })
+    }
+
+    // what the name at an offset refers to
+    fn target(&self, path: &Path, analysis: &Analysis, offset: usize) -> Option<Target> {
+        if let Some(member) = analysis.member_at(offset) {
+            return self.member_target(path, analysis, member.object, &member.name);
+        }
+
+        if let Some(definition) = analysis.definition_at(offset) {
+            return Some(Target {
+                path: path.to_path_buf(),
+                definition,
+            });
+        }
+
+        let reference = analysis.reference_at(offset)?;
+        self.star_import(path, analysis, &reference.name)
+    }
+
+    fn location(&self, target: &Target) -> Option<Json> {
+        let file = self.file(&target.path)?;
+        let (text, analysis) = &*file;
+
+        Some(json!({
+            "uri": path_to_uri(&target.path),
+            "range": range(text, &analysis.definitions[target.definition].span),
+        }))
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.document(params) else {
+            return Json::Null;
+        };
+
+        let analysis = &document.analysis;
+
+        // on the path of an import goes to the file
+        if let Some(import) = analysis.import_at(offset)
+            && let Some(file) = document
+                .path
+                .parent()
+                .and_then(|dir| resolve_import(dir, &import.path))
+        {
+            let start = json!({ "line": 0, "character": 0 });
+            return json!({ "uri": path_to_uri(&file), "range": { "start": start, "end": start } });
+        }
+
+        self.target(&document.path, analysis, offset)
+            .and_then(|target| self.location(&target))
+            .unwrap_or(Json::Null)
+    }
+
+    // the open files, the files they import and every .modu in the workspace
+    fn known_files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.documents.values().map(|d| d.path.clone()).collect();
+
+        if let Some(root) = &self.root {
+            let mut dirs = vec![root.clone()];
+
+            while let Some(dir) = dirs.pop() {
+                let Ok(entries) = std::fs::read_dir(&dir) else {
+                    continue;
+                };
+
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+                    if path.is_dir() && !hidden {
+                        dirs.push(path);
+                    } else if path.extension().is_some_and(|e| e == "modu") {
+                        files.push(path.canonicalize().unwrap_or(path));
+                    }
+                }
+            }
+        }
+
+        let mut i = 0;
+
+        while i < files.len() {
+            if let Some(file) = self.file(&files[i]) {
+                for import in 0..file.1.imports.len() {
+                    if let Some(imported) = self.imported(&files[i], import, &file.1)
+                        && !files.contains(&imported)
+                    {
+                        files.push(imported);
+                    }
+                }
+            }
+
+            i += 1;
+        }
+
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    fn references(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.document(params) else {
+            return Json::Null;
+        };
+
+        let Some(target) = self.target(&document.path, &document.analysis, offset) else {
+            return json!([]);
+        };
+
+        let declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+
+        let mut locations = Vec::new();
+
+        for path in self.known_files() {
+            let Some(file) = self.file(&path) else {
+                continue;
+            };
+
+            let (text, analysis) = &*file;
+            let mut spans = Vec::new();
+
+            if path == target.path && declaration {
+                spans.push(analysis.definitions[target.definition].span.clone());
+            }
+
+            for reference in &analysis.references {
+                let found = match reference.definition {
+                    Some(definition) => Some(Target {
+                        path: path.clone(),
+                        definition,
+                    }),
+                    None => self.star_import(&path, analysis, &reference.name),
+                };
+
+                if found.as_ref() == Some(&target) {
+                    spans.push(reference.span.clone());
+                }
+            }
+
+            for member in &analysis.members {
+                if self.member_target(&path, analysis, member.object, &member.name)
+                    == Some(target.clone())
+                {
+                    spans.push(member.span.clone());
+                }
+            }
+
+            spans.sort_by_key(|s| s.start);
+            spans.dedup();
+
+            for span in spans {
+                locations.push(json!({ "uri": path_to_uri(&path), "range": range(text, &span) }));
+            }
+        }
+
+        json!(locations)
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.document(params) else {
+            return Json::Null;
+        };
+
+        let analysis = &document.analysis;
+
+        let text = match self.target(&document.path, analysis, offset) {
+            Some(target) => {
+                let Some(file) = self.file(&target.path) else {
+                    return Json::Null;
+                };
+
+                let definition = &file.1.definitions[target.definition];
+                let mut text = format!("```modu\n{}\n```", signature(definition));
+
+                match definition.kind {
+                    SymbolKind::Function | SymbolKind::Method => {
+                        let arity = definition.params.len();
+                        let plural = if arity == 1 { "" } else { "s" };
+                        text += &format!("\ntakes {} argument{}", arity, plural);
+                    }
+
+                    SymbolKind::Module => {
+                        if let Some(import) = definition.import {
+                            text += &format!("\nimport \"{}\"", file.1.imports[import].path);
+                        }
+                    }
+
+                    _ => {
+                        if let Some(hint) = &definition.hint {
+                            text += &format!("\n{}", hint);
+                        }
+                    }
+                }
+
+                if target.path != document.path {
+                    text += &format!("\n\nfrom {}", target.path.display());
+                }
+
+                text
+            }
+
+            None => {
+                let member = analysis.member_at(offset);
+                let reference = analysis.reference_at(offset);
+
+                // stdlib members and builtins aren't defined anywhere
+                let module = member
+                    .and_then(|m| m.object)
+                    .and_then(|o| analysis.definitions[o].import)
+                    .map(|i| analysis.imports[i].path.clone());
+
+                match (member, reference, module) {
+                    (Some(member), _, Some(module)) => {
+                        let Some(value) = stdlib_module(&module)
+                            .and_then(|members| members.get(&member.name).cloned())
+                        else {
+                            return Json::Null;
+                        };
+
+                        match value {
+                            Value::BuiltinFn(_) => {
+                                format!("```modu\nfn {}\n```\nfrom {}", member.name, module)
+                            }
+                            value => format!(
+                                "```modu\n{} = {}\n```\nfrom {}",
+                                member.name, value, module
+                            ),
+                        }
+                    }
+
+                    (None, Some(reference), _) if builtins().contains(&reference.name) => {
+                        format!("```modu\nfn {}\n```\nbuiltin function", reference.name)
+                    }
+
+                    _ => return Json::Null,
+                }
+            }
+        };
+
+        json!({ "contents": { "kind": "markdown", "value": text } })
+    }
+
+    fn completion(&self, params: &Json) -> Json {
+        let Some((document, offset)) = self.document(params) else {
+            return Json::Null;
+        };
+
+        let text = &document.text;
+        let analysis = &document.analysis;
+
+        let start = text[..offset]
+            .rfind(|c: char| !is_name(c))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        let mut items = Vec::new();
+
+        if start > 0 && text[..start].ends_with('.') {
+            let dot = start - 1;
+            let object_start = text[..dot]
+                .rfind(|c: char| !is_name(c))
+                .map(|i| i + 1)
+                .unwrap_or(0);
+
+            self.member_completion(document, &text[object_start..dot], offset, &mut items);
+            return json!(items);
+        }
+
+        for definition in analysis.visible_at(offset) {
+            items.push(item(
+                &definition.name,
+                completion_kind(definition.kind),
+                signature(definition),
+            ));
+        }
+
+        for import in analysis
+            .imports
+            .iter()
+            .filter(|i| i.alias.as_deref() == Some("*"))
+        {
+            self.module_completion(&document.path, &import.path, &mut items);
+        }
+
+        for name in builtins() {
+            items.push(item(&name, 3, "builtin function"));
+        }
+
+        for keyword in KEYWORDS {
+            items.push(item(keyword, 14, "keyword"));
+        }
+
+        json!(items)
+    }
+
+    // what a module has, from the stdlib or another file
+    fn module_completion(&self, from: &Path, path: &str, items: &mut Vec<Json>) {
+        if let Some(members) = stdlib_module(path) {
+            let mut members: Vec<_> = members.into_iter().collect();
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (name, value) in members {
+                match value {
+                    Value::BuiltinFn(_) => items.push(item(&name, 3, format!("from {}", path))),
+                    value => items.push(item(&name, 21, format!("{} = {}", name, value))),
+                }
+            }
+
+            return;
+        }
+
+        let Some(file) = from
+            .parent()
+            .and_then(|dir| resolve_import(dir, path))
+            .and_then(|file| self.file(&file))
+        else {
+            return;
+        };
+
+        for definition in file.1.definitions.iter().filter(|d| d.global) {
+            items.push(item(
+                &definition.name,
+                completion_kind(definition.kind),
+                signature(definition),
+            ));
+        }
+    }
+
+    fn member_completion(
+        &self,
+        document: &Document,
+        object: &str,
+        offset: usize,
+        items: &mut Vec<Json>,
+    ) {
+        let analysis = &document.analysis;
+
+        let methods = |class: usize, items: &mut Vec<Json>| {
+            for method in analysis.methods(class) {
+                items.push(item(&method.name, 2, signature(method)));
+            }
+        };
+
+        if object == "self" {
+            if let Some(class) = analysis.class_at(offset) {
+                methods(class, items);
+
+                // and whatever gets set on self in its methods
+                let range = &analysis.definitions[class].range;
+                let mut fields: Vec<&str> = analysis
+                    .members
+                    .iter()
+                    .filter(|m| m.object == Some(class) && range.contains(&m.span.start))
+                    .map(|m| m.name.as_str())
+                    .collect();
+
+                fields.sort();
+                fields.dedup();
+
+                for field in fields {
+                    if !analysis.methods(class).iter().any(|m| m.name == field) {
+                        items.push(item(field, 5, "field"));
+                    }
+                }
+            }
+
+            return;
+        }
+
+        let definition = analysis
+            .visible_at(offset)
+            .into_iter()
+            .find(|d| d.name == object);
+
+        match definition {
+            Some(Definition {
+                kind: SymbolKind::Module,
+                import: Some(import),
+                ..
+            }) => self.module_completion(&document.path, &analysis.imports[*import].path, items),
+
+            Some(Definition {
+                hint: Some(hint), ..
+            }) => {
+                match analysis
+                    .global(hint)
+                    .filter(|&c| analysis.definitions[c].kind == SymbolKind::Class)
+                {
+                    Some(class) => methods(class, items),
+                    None => {
+                        for name in natives(hint) {
+                            items.push(item(&name, 2, format!("{} method", hint)));
+                        }
+                    }
+                }
+            }
+
+            // no idea what it is, so anything a value could have
+            _ => {
+                let mut names = Vec::new();
+
+                for type_name in ["string", "array", "object", "int", "float"] {
+                    for name in natives(type_name) {
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+
+                names.sort();
+
+                for name in names {
+                    items.push(item(&name, 2, "method"));
+                }
+            }
+        }
+    }
+
+    fn symbols(&self, params: &Json) -> Json {
+        let Some(document) = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+        else {
+            return Json::Null;
+        };
+
+        let text = &document.text;
+        let analysis = &document.analysis;
+
+        let symbol = |definition: &Definition, children: Vec<Json>| {
+            json!({
+                "name": definition.name,
+                "detail": signature(definition),
+                "kind": symbol_kind(definition.kind),
+                "range": range(text, &definition.range),
+                "selectionRange": range(text, &definition.span),
+                "children": children,
+            })
+        };
+
+        let symbols: Vec<Json> = analysis
+            .definitions
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.global)
+            .map(|(i, definition)| {
+                let children = analysis
+                    .definitions
+                    .iter()
+                    .filter(|d| d.kind == SymbolKind::Method && d.container == Some(i))
+                    .map(|d| symbol(d, Vec::new()))
+                    .collect();
+
+                symbol(definition, children)
+            })
+            .collect();
+
+        json!(symbols)
+    }
+
+    // reparses and sends the diagnostics, keeping the last good analysis on errors
+    fn update(&mut self, uri: &str, text: String) {
+        let path = uri_to_path(uri);
+        let path = path.canonicalize().unwrap_or(path);
+
+        let diagnostics: Vec<Json> =
+            match crate::parser::parse_source(&text, &path.display().to_string()) {
+                Ok(ast) => {
+                    let analysis = Rc::new(analyze(&text, &ast));
+
+                    self.documents.insert(
+                        uri.to_string(),
+                        Document {
+                            path,
+                            text,
+                            analysis,
+                        },
+                    );
+
+                    Vec::new()
+                }
+
+                Err(errors) => {
+                    let diagnostics = errors
+                        .iter()
+                        .map(|e| {
+                            let span = e.span.clone().unwrap_or(0..0);
+
+                            json!({
+                                "range": range(&text, &span),
+                                "severity": match e.severity {
+                                    Severity::Error => 1,
+                                    Severity::Warning => 2,
+                                },
+                                "code": e.code_name(),
+                                "source": "modu",
+                                "message": e.message,
+                            })
+                        })
+                        .collect();
+
+                    let analysis = self
+                        .documents
+                        .get(uri)
+                        .map(|d| d.analysis.clone())
+                        .unwrap_or_default();
+
+                    self.documents.insert(
+                        uri.to_string(),
+                        Document {
+                            path,
+                            text,
+                            analysis,
+                        },
+                    );
+
+                    diagnostics
+                }
+            };
+
+        // other files may import this one
+        self.files.borrow_mut().clear();
+
+        notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        );
+    }
+
+    fn initialize(&mut self, params: &Json) -> Json {
+        let root = params["workspaceFolders"][0]["uri"]
+            .as_str()
+            .or(params["rootUri"].as_str())
+            .map(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+
+        self.root = root.map(|r| r.canonicalize().unwrap_or(r));
+
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "completionProvider": { "triggerCharacters": ["."] },
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "documentSymbolProvider": true,
+            },
+            "serverInfo": { "name": "modu", "version": crate::VERSION },
+        })
+    }
+
+    // None for notifications, which get no answer
+    fn handle(&mut self, method: &str, params: &Json) -> Option<Result<Json, (i64, String)>> {
+        let result = match method {
+            "initialize" => self.initialize(params),
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            }
+
+            "textDocument/completion" => self.completion(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentSymbol" => self.symbols(params),
+
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                let uri = document["uri"].as_str().unwrap_or_default();
+                self.update(
+                    uri,
+                    document["text"].as_str().unwrap_or_default().to_string(),
+                );
+                return None;
+            }
+
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+                // full sync, the last change has all of it
+                if let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.update(uri, text.to_string());
+                }
+
+                return None;
+            }
+
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                self.documents.remove(uri);
+                notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+                return None;
+            }
+
+            method if method.starts_with("$/") || method == "initialized" => return None,
+            method => return Some(Err((-32601, format!("unknown method '{}'", method)))),
+        };
+
+        Some(Ok(result))
+    }
+}
+
+pub fn lsp() -> Result<(), CliError> {
+    let mut server = Server {
+        documents: HashMap::new(),
+        root: None,
+        files: RefCell::new(HashMap::new()),
+        shutdown: false,
+    };
+
+    let mut stdin = std::io::stdin().lock();
+
+    while let Some(message) = read_message(&mut stdin) {
+        let method = message["method"].as_str().unwrap_or_default();
+
+        // exiting without a shutdown first is an error, says the spec
+        if method == "exit" {
+            if server.shutdown {
+                return Ok(());
+            }
+
+            return Err(CliError::Exit(EXIT_FAILURE));
+        }
+
+        let result = server.handle(method, &message["params"]);
+
+        // notifications have no id and get nothing back
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+
+        match result {
+            Some(Ok(result)) => send(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+            Some(Err((code, error))) => send(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": error },
+            })),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
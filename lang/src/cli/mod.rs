@@ -1,8 +1,93 @@
+pub mod ast;
+pub mod check;
+pub mod dap;
+pub mod debug;
+pub mod disasm;
+pub mod fmt;
 pub mod help;
 pub mod init;
 pub mod install;
+pub mod lint;
 pub mod login;
+pub mod lsp;
 pub mod publish;
 pub mod repl;
 pub mod run;
+pub mod snapshot;
+pub mod test;
 pub mod uninstall;
+
+use crate::error::{ErrorKind, ModuError};
+use colored::Colorize;
+
+// what modu exits with when something goes wrong, scripts pick their own with exit(n)
+pub const EXIT_FAILURE: i32 = 1; // an error the script didn't catch, or a check that failed
+pub const EXIT_USAGE: i32 = 2; // bad arguments or a file that isn't there
+pub const EXIT_PARSE: i32 = 3;
+pub const EXIT_COMPILE: i32 = 4;
+pub const EXIT_INTERNAL: i32 = 70; // modu itself panicked
+
+// how a command failed, main prints it to stderr and exits with its code
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Failed(String),
+    // all found in one pass, the first decides the exit code
+    Modu(Vec<ModuError>),
+    Exit(i32), // already reported, or exit(n) in a script
+}
+
+pub fn exit_code(kind: ErrorKind) -> i32 {
+    match kind {
+        ErrorKind::Parse => EXIT_PARSE,
+        ErrorKind::Compile => EXIT_COMPILE,
+        ErrorKind::Io => EXIT_USAGE,
+        ErrorKind::Exit(code) => code as i32,
+        ErrorKind::Runtime | ErrorKind::Budget | ErrorKind::Interrupted => EXIT_FAILURE,
+    }
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Failed(_) => EXIT_FAILURE,
+            CliError::Modu(errors) => errors.first().map_or(EXIT_FAILURE, |e| exit_code(e.kind)),
+            CliError::Exit(code) => *code,
+        }
+    }
+
+    pub fn report(&self) {
+        match self {
+            CliError::Usage(message) | CliError::Failed(message) => {
+                eprintln!("{} {}", "error:".red().bold(), message)
+            }
+
+            CliError::Modu(errors) => {
+                let rendered = ModuError::render_all(errors);
+                eprint!("{}", rendered);
+
+                if !rendered.ends_with('\n') {
+                    eprintln!();
+                }
+            }
+
+            CliError::Exit(_) => {}
+        }
+    }
+}
+
+impl From<Vec<ModuError>> for CliError {
+    fn from(errors: Vec<ModuError>) -> Self {
+        CliError::Modu(errors)
+    }
+}
+
+impl From<ModuError> for CliError {
+    fn from(e: ModuError) -> Self {
+        match e.kind {
+            ErrorKind::Exit(code) => CliError::Exit(code as i32),
+            _ => CliError::Modu(vec![e]),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::io::{Read, Write};
 
+use super::CliError;
 use serde_json::json;
 use toml;
 use zip;
@@ -84,7 +85,7 @@ fn read_dir(dir: &std::path::Path, archive: &mut zip::ZipWriter<std::fs::File>)
     }
 }
 
-pub fn publish() {
+pub fn publish() -> Result<(), CliError> {
     let mut file = std::fs::File::open("project.toml").unwrap();
     let mut contents = String::new();
     file.read_to_string(&mut contents).unwrap();
@@ -103,8 +104,9 @@ pub fn publish() {
     let lib_exists = std::path::Path::new("lib.modu").exists();
 
     if !lib_exists {
-        println!("No lib.modu file found, primary package file must be named lib.modu");
-        return;
+        return Err(CliError::Failed(
+            "no lib.modu file found, primary package file must be named lib.modu".to_string(),
+        ));
     }
 
     std::fs::create_dir_all(".modu").unwrap();
@@ -135,8 +137,7 @@ pub fn publish() {
         .open(path.clone());
 
     if config_file.is_err() {
-        println!("Not logged in, run modu login");
-        return;
+        return Err(CliError::Failed("not logged in, run modu login".to_string()));
     }
 
     let mut config_file_contents = String::new();
@@ -146,8 +147,7 @@ pub fn publish() {
         .unwrap();
 
     if config_file_contents.is_empty() {
-        println!("Not logged in, run modu login");
-        return;
+        return Err(CliError::Failed("not logged in, run modu login".to_string()));
     }
 
     let toml = toml::from_str::<toml::Value>(&config_file_contents).unwrap();
@@ -164,7 +164,7 @@ pub fn publish() {
 
     if input.trim() != "y" {
         println!("Aborted");
-        return;
+        return Ok(());
     }
 
     let mut readme = String::new();
@@ -194,10 +194,9 @@ pub fn publish() {
         .unwrap();
 
     if res.status().as_u16() != 200 {
-        let text = res.text().unwrap();
-
-        println!("Error: {}", text);
-    } else {
-        println!("[2/2] Package uploaded");
+        return Err(CliError::Failed(res.text().unwrap()));
     }
+
+    println!("[2/2] Package uploaded");
+    Ok(())
 }
@@ -1,10 +1,41 @@
-use crate::parser::parse;
+use super::CliError;
+use crate::error::ErrorKind;
+use crate::interpreter::Interpreter;
+use crate::vm::vm::InterruptHandle;
 use colored::Colorize;
 use regex::Regex;
 use rustyline::error::ReadlineError;
 use rustyline::highlight::{CmdKind, Highlighter};
 use rustyline::{Completer, Editor, Helper, Hinter, Validator, history::DefaultHistory};
-use std::collections::HashMap;
+use std::sync::Mutex;
+
+// rustyline turns ctrl-c into an error while typing, this only
+// fires while a line is running and stops just that line
+static RUNNING: Mutex<Option<InterruptHandle>> = Mutex::new(None);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    // never waits, nothing else holds the lock while the handler is installed
+    if let Ok(running) = RUNNING.try_lock()
+        && let Some(handle) = running.as_ref()
+    {
+        handle.interrupt();
+    }
+}
+
+// the handler is only there while the line runs, then whatever was
+// there before it is put back
+fn interruptible<T>(interpreter: &mut Interpreter, run: impl FnOnce(&mut Interpreter) -> T) -> T {
+    *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = Some(interpreter.interrupt_handle());
+    let handler = on_interrupt as *const () as libc::sighandler_t;
+    let previous = unsafe { libc::signal(libc::SIGINT, handler) };
+
+    let result = run(interpreter);
+
+    unsafe { libc::signal(libc::SIGINT, previous) };
+    *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+    result
+}
 
 #[derive(Completer, Helper, Hinter, Validator)]
 pub struct Syntax {
@@ -109,7 +140,7 @@ impl Highlighter for Syntax {
     }
 }
 
-pub fn repl() {
+pub fn repl() -> Result<(), CliError> {
     println!("Modu REPL");
 
     let mut rl: Editor<Syntax, DefaultHistory> = Editor::new().unwrap();
@@ -118,12 +149,7 @@ pub fn repl() {
     let mut open_functions = 0;
     let mut buffer = String::new();
 
-    let mut globals: HashMap<String, crate::vm::value::Value> = HashMap::new();
-    for func in crate::functions::get_functions() {
-        globals.insert(func.name.clone(), crate::vm::value::Value::BuiltinFn(func));
-    }
-
-    let mut persistent_chunks: Vec<crate::vm::chunk::Chunk> = Vec::new();
+    let mut interpreter = Interpreter::new();
 
     loop {
         let prompt = if open_functions > 0 {
@@ -148,41 +174,23 @@ pub fn repl() {
                 buffer.push('\n');
 
                 if open_functions == 0 {
-                    let ast = parse(&buffer, "<repl>");
+                    let result = interruptible(&mut interpreter, |interpreter| {
+                        interpreter.eval_with_name(&buffer, "<repl>")
+                    });
 
-                    if ast.is_err() {
-                        buffer.clear();
-                        continue;
-                    }
+                    match result {
+                        Err(e) if e.kind == ErrorKind::Interrupted => {
+                            println!("{}", "interrupted".dimmed());
+                        }
 
-                    let mut compiler = crate::compiler::compiler::Compiler::new();
-                    compiler.offset = persistent_chunks.len();
+                        // only the exit builtin gets here, not a function someone named exit
+                        Err(e) if matches!(e.kind, ErrorKind::Exit(_)) => return Err(e.into()),
 
-                    if let Err(e) = compiler.compile_program(ast.clone().unwrap()) {
-                        println!("{}: {}", "Compilation error".red(), e);
-                        buffer.clear();
-                        continue;
+                        Err(e) => println!("{}", e.render()),
+                        Ok(_) => {}
                     }
 
-                    let mut all_chunks = persistent_chunks.clone();
-                    all_chunks.extend(compiler.chunks);
-
-                    let mut vm = crate::vm::vm::VM::new(
-                        all_chunks.clone(),
-                        std::path::PathBuf::from("<repl>"),
-                        buffer.clone(),
-                    );
-                    vm.globals = globals.clone();
-
                     buffer.clear();
-
-                    if let Err(e) = vm.run(persistent_chunks.len()) {
-                        println!("{}", e);
-                        continue;
-                    }
-
-                    globals = vm.globals.clone();
-                    persistent_chunks = vm.chunks;
                 }
             }
 
@@ -196,10 +204,9 @@ pub fn repl() {
                 break;
             }
 
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
-            }
+            Err(err) => return Err(CliError::Failed(format!("{:?}", err))),
         }
     }
+
+    Ok(())
 }
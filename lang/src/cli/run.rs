@@ -1,103 +1,408 @@
+use super::{CliError, EXIT_USAGE};
 use crate::parser::parse;
-use colored::Colorize;
+use crate::permissions::Permissions;
+use crate::tracer::Tracer;
+use crate::vm::coverage::Coverage;
+use crate::vm::profile::Profile;
+use std::path::{Path, PathBuf};
 
-pub fn run() {
-    let args = std::env::args().collect::<Vec<String>>();
-
-    let file: String;
-    let file_path: String;
+// flags that take a value, so it isnt mistaken for the file
+const VALUE_FLAGS: [&str; 10] = [
+    "--max-depth",
+    "--max-instructions",
+    "--max-memory",
+    "--timeout",
+    "--trace-fn",
+    "--trace-lines",
+    "--allow",
+    "--allow-read",
+    "--allow-write",
+    "--allow-net",
+];
 
-    if args.len() < 3 || (args[2].as_str().contains("--") && args.len() == 3) {
-        let main_path = std::path::Path::new("main.modu");
-
-        if main_path.exists() {
-            file = std::fs::read_to_string(main_path).unwrap_or_else(|e| {
-                println!("Failed to read main.modu: {}", e);
-                std::process::exit(1);
-            });
-            file_path = main_path.to_str().unwrap().to_string();
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == flag {
+            args.get(i + 1).map(String::as_str)
         } else {
-            println!("Usage: modu run [file]");
-            return;
+            arg.strip_prefix(flag)?.strip_prefix('=')
         }
-    } else {
-        let path = std::path::Path::new(&args[2]);
-        if !path.exists() {
-            println!("{}", format!("File not found: {}", args[2]).red());
-            return;
+    })
+}
+
+fn file_arg(args: &[String]) -> Option<&str> {
+    let mut iter = args.iter().skip(2);
+
+    while let Some(arg) = iter.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            return Some(arg);
         }
+    }
+
+    None
+}
+
+fn limits(args: &[String]) -> Result<crate::vm::vm::Limits, String> {
+    let mut limits = crate::vm::vm::Limits::default();
 
-        file = std::fs::read_to_string(path).unwrap_or_else(|e| {
-            println!("{}", format!("Failed to read file: {}", e).red());
-            std::process::exit(1);
-        });
+    if let Some(depth) = flag_value(args, "--max-depth") {
+        limits.max_frames = depth
+            .parse()
+            .map_err(|_| format!("--max-depth expects a number, got '{}'", depth))?;
 
-        file_path = path.to_str().unwrap().to_string();
+        // every frame needs some room on the stack too
+        limits.max_stack = limits.max_stack.max(limits.max_frames.saturating_mul(256));
     }
 
-    let ast = parse(&file, &file_path);
+    if let Some(count) = flag_value(args, "--max-instructions") {
+        limits.max_instructions = Some(
+            count
+                .parse()
+                .map_err(|_| format!("--max-instructions expects a number, got '{}'", count))?,
+        );
+    }
 
-    if ast.is_err() {
-        return;
+    if let Some(mb) = flag_value(args, "--max-memory") {
+        let mb: usize = mb
+            .parse()
+            .map_err(|_| format!("--max-memory expects a number of MB, got '{}'", mb))?;
+
+        limits.max_memory = Some(mb.saturating_mul(1024 * 1024));
     }
 
-    let mut compiler = crate::compiler::compiler::Compiler::new();
+    if let Some(secs) = flag_value(args, "--timeout") {
+        let timeout = secs
+            .parse()
+            .ok()
+            .and_then(|secs| std::time::Duration::try_from_secs_f64(secs).ok())
+            .ok_or_else(|| format!("--timeout expects a number of seconds, got '{}'", secs))?;
 
-    if let Err(e) = compiler.compile_program(ast.clone().unwrap()) {
-        println!("{}: {}", "Compilation error".red(), e);
-        return;
+        limits.timeout = Some(timeout);
     }
 
-    if args.contains(&"--dump".to_string()) {
-        use std::io::Write;
+    Ok(limits)
+}
 
-        let mut ast_file = std::fs::File::create("dump.ast").unwrap();
-        ast_file
-            .write_all(format!("{:#?}", ast).as_bytes())
-            .unwrap();
+// --coverage writes lcov.info, --coverage=<path> picks where
+pub(super) fn coverage_path(args: &[String]) -> Option<PathBuf> {
+    output_path(args, "--coverage", "lcov.info")
+}
 
-        let mut bytecode_file = std::fs::File::create("dump.bytecode").unwrap();
-        let mut string = String::new();
+fn output_path(args: &[String], flag: &str, default: &str) -> Option<PathBuf> {
+    args.iter().find_map(|arg| match arg.strip_prefix(flag)? {
+        "" => Some(PathBuf::from(default)),
+        path => path.strip_prefix('=').map(PathBuf::from),
+    })
+}
 
-        for (i, chunk) in compiler.chunks.iter().enumerate() {
-            string.push_str(&format!(
-                "=== chunk[{}] \"{}\" ({} locals) ===\n",
-                i, chunk.name, chunk.locals_count
-            ));
+// the table goes to stderr so it doesn't mix with what the script printed
+pub(super) fn report_coverage(coverage: &Coverage, path: &Path) -> Result<(), CliError> {
+    std::fs::write(path, coverage.lcov())
+        .map_err(|e| CliError::Failed(format!("could not write {}: {}", path.display(), e)))?;
+
+    let root = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_default();
+
+    eprint!("\ncoverage:\n{}", coverage.summary(&root));
+    eprintln!("wrote {}", path.display());
+
+    Ok(())
+}
+
+// the table goes to stderr too, the folded stacks are for flamegraph tools
+fn report_profile(profile: &Profile, path: &Path) -> Result<(), CliError> {
+    std::fs::write(path, profile.folded())
+        .map_err(|e| CliError::Failed(format!("could not write {}: {}", path.display(), e)))?;
+
+    let root = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_default();
+
+    eprint!("\nprofile:\n{}", profile.table(&root));
+    eprintln!("wrote {}", path.display());
+
+    Ok(())
+}
+
+// --trace logs to stderr and --trace=<path> to a file, --trace-fn=a,b
+// and --trace-lines=[file:]from-to narrow it down
+fn tracer(args: &[String]) -> Result<Option<Tracer>, String> {
+    let functions = flag_value(args, "--trace-fn");
+    let lines = flag_value(args, "--trace-lines");
+
+    let mut tracer = match output_path(args, "--trace", "") {
+        Some(path) if path.as_os_str().is_empty() => Tracer::new(std::io::stderr()),
+        Some(path) => Tracer::new(
+            std::fs::File::create(&path)
+                .map_err(|e| format!("could not create {}: {}", path.display(), e))?,
+        ),
 
-            for (j, instruction) in chunk.instructions.iter().enumerate() {
-                let span = chunk
-                    .spans
-                    .get(j)
-                    .map(|s| format!("[{}-{}]", s.start, s.end))
-                    .unwrap_or_default();
-                string.push_str(&format!("\t{:04} {}: {:?}\n", j, span, instruction));
+        None => {
+            if functions.is_some() || lines.is_some() {
+                return Err(
+                    "--trace-fn and --trace-lines only do something with --trace".to_string(),
+                );
             }
 
-            if !chunk.constants.is_empty() {
-                string.push_str("\n\tconstants:\n");
+            return Ok(None);
+        }
+    };
+
+    if let Some(functions) = functions {
+        tracer = tracer.functions(functions.split(',').map(String::from).collect());
+    }
+
+    if let Some(lines) = lines {
+        let (file, range) = match lines.rsplit_once(':') {
+            Some((file, range)) => (Some(PathBuf::from(file)), range),
+            None => (None, lines),
+        };
+
+        let (from, to) = range.split_once('-').unwrap_or((range, range));
+        let (Ok(from), Ok(to)) = (from.parse(), to.parse()) else {
+            return Err(format!(
+                "--trace-lines expects a line or a range like 10-20, got '{}'",
+                lines
+            ));
+        };
+
+        tracer = tracer.lines(file, from, to);
+    }
 
-                for (j, constant) in chunk.constants.iter().enumerate() {
-                    string.push_str(&format!("\t\t{:04}: {:?}\n", j, constant));
+    Ok(Some(tracer))
+}
+
+// --allow-read=a,b and --allow-read a,b grant those, --allow-read=* grants everything.
+// there's no bare form, it would quietly grant everything if the value was forgotten
+fn grants<'a>(args: &'a [String], flag: &str) -> Result<Option<Vec<&'a str>>, String> {
+    let mut found: Option<Vec<&str>> = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let list = if arg == flag {
+            match iter.next() {
+                Some(list) if !list.starts_with("--") => list.as_str(),
+                _ => {
+                    let example = match flag {
+                        "--allow" => "os,ffi",
+                        "--allow-net" => "example.com",
+                        _ => "./data",
+                    };
+                    return Err(format!("{} needs a value, like {}={}", flag, flag, example));
                 }
             }
+        } else if let Some(list) = arg.strip_prefix(flag).and_then(|a| a.strip_prefix('=')) {
+            list
+        } else {
+            continue;
+        };
+
+        found
+            .get_or_insert_with(Vec::new)
+            .extend(list.split(',').filter(|s| !s.is_empty()));
+    }
+
+    Ok(found)
+}
+
+fn permissions(args: &[String]) -> Result<Option<Permissions>, String> {
+    let flags = ["--allow", "--allow-read", "--allow-write", "--allow-net"];
+    let sandboxed = args.iter().any(|a| a == "--sandbox");
+
+    if !sandboxed {
+        for flag in flags {
+            if grants(args, flag)?.is_some() {
+                return Err(format!("{} only does something with --sandbox", flag));
+            }
+        }
+
+        return Ok(None);
+    }
+
+    let mut permissions = Permissions::sandbox();
+
+    for module in grants(args, "--allow")?.unwrap_or_default() {
+        if module == "*" {
+            return Err("--allow needs a list of modules, like --allow=os,ffi".to_string());
+        }
+
+        permissions = permissions.allow_module(module);
+    }
+
+    for path in grants(args, "--allow-read")?.unwrap_or_default() {
+        permissions = match path {
+            "*" => permissions.allow_any_read(),
+            path => permissions.allow_read(path),
+        };
+    }
+
+    for path in grants(args, "--allow-write")?.unwrap_or_default() {
+        permissions = match path {
+            "*" => permissions.allow_any_write(),
+            path => permissions.allow_write(path),
+        };
+    }
+
+    for host in grants(args, "--allow-net")?.unwrap_or_default() {
+        permissions = match host {
+            "*" => permissions.allow_any_net(),
+            host => permissions.allow_net(host),
+        };
+    }
+
+    Ok(Some(permissions))
+}
+
+pub fn run() -> Result<(), CliError> {
+    let args = std::env::args().collect::<Vec<String>>();
+
+    let limits = limits(&args).map_err(CliError::Usage)?;
+    let tracer = tracer(&args).map_err(CliError::Usage)?;
+    let permissions = permissions(&args).map_err(CliError::Usage)?;
 
-            if i != compiler.chunks.len() - 1 {
-                string.push('\n');
+    let file_path = match file_arg(&args) {
+        Some(arg) => {
+            if !std::path::Path::new(arg).exists() {
+                return Err(CliError::Usage(format!("file not found: {}", arg)));
             }
+
+            arg.to_string()
+        }
+
+        None if std::path::Path::new("main.modu").exists() => "main.modu".to_string(),
+
+        None => {
+            println!(
+                "Usage: modu run [file] [--max-depth <frames>] [--cache-stats]
+                 [--max-instructions <n>] [--max-memory <MB>] [--timeout <seconds>] [--coverage[=<path>]]
+                 [--profile[=<path>]] [--trace[=<path>]] [--trace-fn=<names>] [--trace-lines=[file:]<from>-<to>]
+                 [--sandbox] [--allow=<modules>] [--allow-read=<paths>] [--allow-write=<paths>] [--allow-net=<hosts>]"
+            );
+            return Err(CliError::Exit(EXIT_USAGE));
         }
+    };
+
+    if args.contains(&"--dump".to_string()) {
+        dump(&file_path);
+    }
+
+    // eval_file stops at the first error, this shows everything wrong with the file
+    if let Ok(source) = std::fs::read_to_string(&file_path) {
+        crate::parser::parse_source(&source, &file_path)?;
+    }
+
+    let mut interpreter = crate::interpreter::Interpreter::with_limits(limits);
+
+    if let Some(permissions) = permissions {
+        interpreter.set_permissions(permissions);
+    }
+
+    if let Some(tracer) = tracer {
+        interpreter.vm().set_hook(tracer);
+    }
+
+    if args.contains(&"--cache-stats".to_string()) {
+        interpreter.vm().enable_cache_stats();
+    }
+
+    let coverage = coverage_path(&args).map(|path| (Coverage::new(), path));
+
+    if let Some((coverage, _)) = &coverage {
+        interpreter.vm().set_coverage(coverage.clone());
+    }
+
+    // --profile writes profile.folded, --profile=<path> picks where
+    let profile =
+        output_path(&args, "--profile", "profile.folded").map(|path| (Profile::new(), path));
+
+    if let Some((profile, _)) = &profile {
+        interpreter.vm().set_profile(profile.clone());
+    }
+
+    let result = interpreter.eval_file(&file_path);
 
-        bytecode_file.write_all(string.as_bytes()).unwrap();
+    if let Some(stats) = interpreter.vm().cache_stats() {
+        eprint!("{}", stats);
     }
 
-    let source_path = std::path::PathBuf::from(&file_path)
-        .canonicalize()
-        .map_err(|_| format!("cannot find file '{}'", file_path))
+    if let Some((coverage, path)) = &coverage {
+        let reported = report_coverage(coverage, path);
+
+        // an error from the script matters more
+        if result.is_ok() {
+            reported?;
+        }
+    }
+
+    if let Some((profile, path)) = &profile {
+        let reported = report_profile(profile, path);
+
+        if result.is_ok() {
+            reported?;
+        }
+    }
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn dump(file_path: &str) {
+    use std::io::Write;
+
+    let Ok(file) = std::fs::read_to_string(file_path) else {
+        return;
+    };
+
+    let Ok(ast) = parse(&file, file_path) else {
+        return;
+    };
+
+    let mut compiler = crate::compiler::compiler::Compiler::new();
+
+    if compiler.compile_program(ast.clone()).is_err() {
+        return;
+    }
+
+    let mut ast_file = std::fs::File::create("dump.ast").unwrap();
+    ast_file
+        .write_all(format!("{:#?}", ast).as_bytes())
         .unwrap();
 
-    let mut vm = crate::vm::vm::VM::new(compiler.chunks, source_path, file);
+    let mut bytecode_file = std::fs::File::create("dump.bytecode").unwrap();
+    let mut string = String::new();
 
-    if let Err(e) = vm.run(0) {
-        println!("{}", e);
+    for (i, chunk) in compiler.chunks.iter().enumerate() {
+        string.push_str(&format!(
+            "=== chunk[{}] \"{}\" ({} locals) ===\n",
+            i, chunk.name, chunk.locals_count
+        ));
+
+        for (j, instruction) in chunk.instructions.iter().enumerate() {
+            let span = chunk
+                .spans
+                .get(j)
+                .map(|s| format!("[{}-{}]", s.start, s.end))
+                .unwrap_or_default();
+            string.push_str(&format!("\t{:04} {}: {:?}\n", j, span, instruction));
+        }
+
+        if !chunk.constants.is_empty() {
+            string.push_str("\n\tconstants:\n");
+
+            for (j, constant) in chunk.constants.iter().enumerate() {
+                string.push_str(&format!("\t\t{:04}: {:?}\n", j, constant));
+            }
+        }
+
+        if i != compiler.chunks.len() - 1 {
+            string.push('\n');
+        }
     }
+
+    bytecode_file.write_all(string.as_bytes()).unwrap();
 }
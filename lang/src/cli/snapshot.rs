@@ -0,0 +1,287 @@
+// modu test --snapshot runs every script under tests/snapshots and compares
+// what it printed and how it exited to the .snap file next to it, --update
+// writes them instead. things that change between runs are masked first:
+//
+//   [test]
+//   snapshots = "tests/snapshots"
+//
+//   [test.masks]
+//   "took \\d+ms" = "took [time]"
+
+use super::{CliError, EXIT_FAILURE};
+use colored::Colorize;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+struct Config {
+    dir: PathBuf,
+    masks: Vec<(Regex, String)>,
+}
+
+// timestamps from std/time and ids from std/uuid, on for everyone
+const DEFAULT_MASKS: [(&str, &str); 3] = [
+    (
+        r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?",
+        "[timestamp]",
+    ),
+    (
+        r"[A-Z][a-z]{2}, \d{1,2} [A-Z][a-z]{2} \d{4} \d{2}:\d{2}:\d{2} [+-]\d{4}",
+        "[timestamp]",
+    ),
+    (
+        r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+        "[uuid]",
+    ),
+];
+
+fn config() -> Result<Config, String> {
+    let mut config = Config {
+        dir: PathBuf::from("tests/snapshots"),
+        masks: Vec::new(),
+    };
+
+    let project = match std::fs::read_to_string("project.toml") {
+        Ok(contents) => toml::from_str(&contents).map_err(|e| format!("project.toml: {}", e))?,
+        Err(_) => toml::Value::Table(Default::default()),
+    };
+
+    let table = project.get("test");
+
+    if let Some(dir) = table.and_then(|t| t.get("snapshots")) {
+        let dir = dir
+            .as_str()
+            .ok_or("[test] snapshots in project.toml should be a path")?;
+        config.dir = PathBuf::from(dir);
+    }
+
+    if let Some(masks) = table.and_then(|t| t.get("masks")) {
+        let masks = masks
+            .as_table()
+            .ok_or("[test.masks] in project.toml should be a table")?;
+
+        for (pattern, replacement) in masks {
+            let regex = Regex::new(pattern)
+                .map_err(|e| format!("[test.masks] '{}' is not a valid regex: {}", pattern, e))?;
+            let replacement = replacement.as_str().ok_or_else(|| {
+                format!("[test.masks] '{}' should be replaced by a string", pattern)
+            })?;
+
+            config.masks.push((regex, replacement.to_string()));
+        }
+    }
+
+    for (pattern, replacement) in DEFAULT_MASKS {
+        config
+            .masks
+            .push((Regex::new(pattern).unwrap(), replacement.to_string()));
+    }
+
+    Ok(config)
+}
+
+// the snapshot a script would get right now
+fn record(script: &Path, config: &Config) -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("could not find modu: {}", e))?;
+    let output = Command::new(exe)
+        .arg("run")
+        .arg(script)
+        .env("NO_COLOR", "1")
+        .output()
+        .map_err(|e| format!("could not run {}: {}", script.display(), e))?;
+
+    let code = match output.status.code() {
+        Some(code) => code.to_string(),
+        None => "killed".to_string(),
+    };
+
+    let snapshot = format!(
+        "exit code: {}\n--- stdout\n{}--- stderr\n{}",
+        code,
+        with_newline(String::from_utf8_lossy(&output.stdout).into_owned()),
+        with_newline(String::from_utf8_lossy(&output.stderr).into_owned()),
+    );
+
+    Ok(normalize(&snapshot, config))
+}
+
+fn with_newline(mut text: String) -> String {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+
+    text
+}
+
+fn normalize(text: &str, config: &Config) -> String {
+    let ansi = Regex::new(r"\x1b\[[0-9;]*m").unwrap();
+    let mut text = ansi.replace_all(text, "").into_owned();
+
+    // errors show absolute paths, which differ between machines
+    if let Ok(cwd) = std::env::current_dir().and_then(|dir| dir.canonicalize()) {
+        text = text.replace(
+            &format!("{}{}", cwd.display(), std::path::MAIN_SEPARATOR),
+            "",
+        );
+    }
+
+    for (regex, replacement) in &config.masks {
+        text = regex.replace_all(&text, replacement.as_str()).into_owned();
+    }
+
+    text
+}
+
+// a line diff of the stored snapshot against the new one
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // longest common subsequence, filled in from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", old[i]).red().to_string());
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", new[j]).green().to_string());
+            j += 1;
+        }
+    }
+
+    lines
+}
+
+fn snapshot_path(script: &Path) -> PathBuf {
+    script.with_extension("snap")
+}
+
+pub fn snapshot(filters: &[&str], update: bool) -> Result<(), CliError> {
+    let config = config().map_err(CliError::Failed)?;
+
+    if !config.dir.is_dir() {
+        return Err(CliError::Usage(format!(
+            "{} does not exist, put the scripts to snapshot there or set [test] snapshots in project.toml",
+            config.dir.display()
+        )));
+    }
+
+    let mut scripts = Vec::new();
+    super::fmt::collect(&config.dir, &mut scripts);
+    scripts.retain(|script| script.extension().is_some_and(|ext| ext == "modu"));
+
+    let total = scripts.len();
+    scripts.retain(|script| {
+        filters.is_empty()
+            || filters
+                .iter()
+                .any(|f| script.display().to_string().contains(f))
+    });
+    let filtered = total - scripts.len();
+
+    println!(
+        "running {} snapshot{}",
+        scripts.len(),
+        if scripts.len() == 1 { "" } else { "s" }
+    );
+
+    let start = Instant::now();
+    let (mut passed, mut updated) = (0, 0);
+    let mut failures = Vec::new();
+
+    for script in &scripts {
+        let actual = record(script, &config).map_err(CliError::Failed)?;
+        let path = snapshot_path(script);
+        let expected = std::fs::read_to_string(&path).ok();
+
+        let status = match expected {
+            Some(expected) if expected == actual => {
+                passed += 1;
+                "ok".green()
+            }
+
+            _ if update => {
+                std::fs::write(&path, &actual).map_err(|e| {
+                    CliError::Failed(format!("could not write {}: {}", path.display(), e))
+                })?;
+
+                updated += 1;
+                "updated".yellow()
+            }
+
+            Some(expected) => {
+                failures.push((script, diff(&expected, &actual)));
+                "FAILED".red()
+            }
+
+            None => {
+                failures.push((
+                    script,
+                    vec![format!(
+                        "no snapshot at {}, run with --update to create it",
+                        path.display()
+                    )],
+                ));
+                "FAILED".red()
+            }
+        };
+
+        println!("snapshot {} ... {}", script.display(), status);
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+
+        for (script, lines) in &failures {
+            println!("\n---- {} ----", script.display());
+
+            for line in lines {
+                println!("{}", line);
+            }
+        }
+    }
+
+    let result = if failures.is_empty() {
+        "ok".green()
+    } else {
+        "FAILED".red()
+    };
+
+    println!(
+        "\nsnapshot result: {}. {} passed, {} failed, {} updated, {} filtered out ({:.2}s)",
+        result,
+        passed,
+        failures.len(),
+        updated,
+        filtered,
+        start.elapsed().as_secs_f64()
+    );
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        if !update {
+            println!("run modu test --snapshot --update to accept the new output");
+        }
+
+        Err(CliError::Exit(EXIT_FAILURE))
+    }
+}
@@ -0,0 +1,320 @@
+// modu test runs every `fn test_*()` in the *_test.modu files under the
+// current directory, each in a fresh interpreter so tests can't see what
+// another one left behind. a comment right above a test says it should fail:
+//
+//   // expect-error: division by zero
+//   fn test_divide_by_zero() { ... }
+
+use super::{CliError, EXIT_FAILURE};
+use crate::ast::Expr;
+use crate::error::{ErrorKind, ModuError};
+use crate::interpreter::Interpreter;
+use crate::lexer::{Token, lex_with_comments};
+use crate::parser::parse_source;
+use crate::vm::chunk::SourceFile;
+use crate::vm::coverage::Coverage;
+use crate::vm::stdio::OutputBuffer;
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+fn usage() {
+    println!("Usage: modu test [filters] [--snapshot] [--update] [--coverage[=<path>]]");
+    println!("Runs every test_* function in *_test.modu files, filters match part of file::test");
+    println!(
+        "--snapshot compares the output of the scripts in tests/snapshots to their .snap files,"
+    );
+    println!("--update rewrites those that changed");
+    println!("--coverage reports which lines and branches the tests ran and writes lcov.info");
+}
+
+struct Test {
+    file: PathBuf,
+    name: String,
+    expect_error: Option<Option<String>>, // Some(None) is any error
+}
+
+impl Test {
+    fn id(&self) -> String {
+        let file = self.file.strip_prefix(".").unwrap_or(&self.file);
+        format!("{}::{}", file.display(), self.name)
+    }
+}
+
+// `// expect-error` and `// expect-error: text`, by the line they are on
+fn expected_errors(file: &SourceFile) -> HashMap<usize, Option<String>> {
+    let tokens = lex_with_comments(&file.text).unwrap_or_default();
+
+    tokens
+        .iter()
+        .filter(|(token, _)| *token == Token::Comment)
+        .filter_map(|(_, span)| {
+            let text = file.text[span.into_range()].trim_start_matches('/').trim();
+            let rest = text.strip_prefix("expect-error")?;
+
+            let expected = match rest.trim().strip_prefix(':') {
+                Some(expected) => Some(expected.trim().to_string()),
+                None if rest.trim().is_empty() => None,
+                None => return None,
+            };
+
+            Some((file.line(span.start), expected))
+        })
+        .collect()
+}
+
+fn discover(path: &Path, tests: &mut Vec<Test>) -> Result<(), Vec<ModuError>> {
+    let name = path.display().to_string();
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        vec![ModuError::new(
+            ErrorKind::Io,
+            format!("could not read {}: {}", name, e),
+        )]
+    })?;
+
+    let ast = parse_source(&text, &name)?;
+    let file = SourceFile::new(&name, &text);
+    let expected = expected_errors(&file);
+
+    for expr in &ast {
+        let Expr::Function { name, args, .. } = &expr.node else {
+            continue;
+        };
+
+        if !name.starts_with("test_") {
+            continue;
+        }
+
+        if !args.is_empty() {
+            return Err(vec![
+                ModuError::new(
+                    ErrorKind::Compile,
+                    format!(
+                        "test functions take no arguments, {} takes {}",
+                        name,
+                        args.len()
+                    ),
+                )
+                .at(expr.span.into_range(), file.clone()),
+            ]);
+        }
+
+        let line = file.line(expr.span.start);
+
+        tests.push(Test {
+            file: path.to_path_buf(),
+            name: name.clone(),
+            expect_error: line
+                .checked_sub(1)
+                .and_then(|line| expected.get(&line).cloned()),
+        });
+    }
+
+    Ok(())
+}
+
+// what went wrong, None if it passed
+fn run(
+    test: &Test,
+    output: &OutputBuffer,
+    coverage: Option<&Coverage>,
+) -> (Option<String>, Duration) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_stdout(output.clone());
+    interpreter.set_stderr(output.clone());
+
+    if let Some(coverage) = coverage {
+        interpreter.vm().set_coverage(coverage.clone());
+    }
+
+    if let Err(e) = interpreter.eval_file(&test.file) {
+        return (Some(e.render()), Duration::ZERO);
+    }
+
+    let start = Instant::now();
+    let result = interpreter.call(&test.name, Vec::new());
+    let elapsed = start.elapsed();
+
+    let failure = match (result, &test.expect_error) {
+        (Ok(_), None) => None,
+        (Ok(_), Some(_)) => Some("expected an error, but the test passed".to_string()),
+
+        (Err(e), _) if matches!(e.kind, ErrorKind::Exit(_)) => {
+            Some(format!("the test called {}", e.message))
+        }
+
+        (Err(e), None) => Some(e.render()),
+        (Err(e), Some(None)) if e.kind == ErrorKind::Runtime => None,
+        (Err(e), Some(Some(expected))) if e.kind == ErrorKind::Runtime => {
+            if e.message.contains(expected.as_str()) {
+                None
+            } else {
+                Some(format!(
+                    "expected an error containing \"{}\", got:\n{}",
+                    expected,
+                    e.render()
+                ))
+            }
+        }
+
+        // running out of time or memory isn't the error a test expects
+        (Err(e), Some(_)) => Some(e.render()),
+    };
+
+    (failure, elapsed)
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() > 0 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+    }
+}
+
+pub fn test() -> Result<(), CliError> {
+    let args = std::env::args().skip(2).collect::<Vec<String>>();
+
+    let mut filters = Vec::new();
+    let (mut snapshot, mut update) = (false, false);
+
+    for arg in &args {
+        match arg.as_str() {
+            "--snapshot" => snapshot = true,
+            "--update" => update = true,
+            flag if flag == "--coverage" || flag.starts_with("--coverage=") => {}
+
+            "--help" | "-h" => {
+                usage();
+                return Ok(());
+            }
+            flag if flag.starts_with("--") => {
+                usage();
+                return Err(CliError::Usage(format!("unknown flag {}", flag)));
+            }
+
+            filter => filters.push(filter),
+        }
+    }
+
+    if update && !snapshot {
+        return Err(CliError::Usage(
+            "--update only works with --snapshot".to_string(),
+        ));
+    }
+
+    let coverage = super::run::coverage_path(&args).map(|path| (Coverage::new(), path));
+
+    if snapshot && coverage.is_some() {
+        return Err(CliError::Usage(
+            "--coverage doesn't work with --snapshot, use modu run --coverage on the scripts"
+                .to_string(),
+        ));
+    }
+
+    if snapshot {
+        return super::snapshot::snapshot(&filters, update);
+    }
+
+    let mut files = Vec::new();
+    super::fmt::collect(Path::new("."), &mut files);
+    files.retain(|file| {
+        file.file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with("_test.modu"))
+    });
+
+    let mut tests = Vec::new();
+    let mut broken = 0;
+
+    for file in &files {
+        if let Err(errors) = discover(file, &mut tests) {
+            eprint!("{}", ModuError::render_all(&errors));
+            broken += 1;
+        }
+    }
+
+    let total = tests.len();
+    tests.retain(|test| filters.is_empty() || filters.iter().any(|f| test.id().contains(f)));
+    let filtered = total - tests.len();
+
+    println!(
+        "running {} test{} from {} file{}",
+        tests.len(),
+        if tests.len() == 1 { "" } else { "s" },
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+    );
+
+    let start = Instant::now();
+    let mut failures = Vec::new();
+
+    for test in &tests {
+        let output = OutputBuffer::default();
+        let (failure, elapsed) = run(test, &output, coverage.as_ref().map(|(c, _)| c));
+
+        let status = match failure {
+            None => "ok".green(),
+            Some(_) => "FAILED".red(),
+        };
+
+        println!(
+            "test {} ... {} {}",
+            test.id(),
+            status,
+            format!("({})", format_duration(elapsed)).dimmed()
+        );
+
+        if let Some(failure) = failure {
+            failures.push((test.id(), output.take(), failure));
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+
+        for (id, output, failure) in &failures {
+            println!("\n---- {} ----", id);
+
+            if !output.is_empty() {
+                print!("{}", output);
+            }
+
+            println!("{}", failure.trim_end());
+        }
+    }
+
+    let passed = tests.len() - failures.len();
+    let result = if failures.is_empty() && broken == 0 {
+        "ok".green()
+    } else {
+        "FAILED".red()
+    };
+
+    println!(
+        "\ntest result: {}. {} passed, {} failed, {} filtered out ({})",
+        result,
+        passed,
+        failures.len(),
+        filtered,
+        format_duration(start.elapsed())
+    );
+
+    if broken > 0 {
+        println!(
+            "{} file{} could not be loaded",
+            broken,
+            if broken == 1 { "" } else { "s" }
+        );
+    }
+
+    if let Some((coverage, path)) = &coverage {
+        super::run::report_coverage(coverage, path)?;
+    }
+
+    if failures.is_empty() && broken == 0 {
+        Ok(())
+    } else {
+        Err(CliError::Exit(EXIT_FAILURE))
+    }
+}
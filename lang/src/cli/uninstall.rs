@@ -1,19 +1,24 @@
-pub fn uninstall() {
+use super::{CliError, EXIT_USAGE};
+
+pub fn uninstall() -> Result<(), CliError> {
     let args = std::env::args().collect::<Vec<String>>();
 
     if args.len() < 3 {
         println!("Usage: modu uninstall <name>");
-        return;
+        return Err(CliError::Exit(EXIT_USAGE));
     }
 
     let name = &(args[2].clone().split("@").collect::<Vec<&str>>()[0].to_string());
 
     if !std::fs::exists(".modu/packages/".to_string() + name).unwrap() {
-        println!("Package {} is not installed", name);
-        return;
+        return Err(CliError::Failed(format!(
+            "package {} is not installed",
+            name
+        )));
     }
 
     std::fs::remove_dir_all(".modu/packages/".to_string() + name).unwrap();
 
     println!("Package {} uninstalled", name);
+    Ok(())
 }
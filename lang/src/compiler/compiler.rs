@@ -1,9 +1,10 @@
 use chumsky::span::SimpleSpan;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use super::scope::{ScopeStack, Variable};
 use crate::ast::{AssignOp, Expr, SpannedExpr};
-use crate::vm::chunk::Chunk;
+use crate::vm::chunk::{Chunk, SourceFile};
 use crate::vm::instruction::Instruction;
 use crate::vm::value::Value;
 
@@ -36,10 +37,18 @@ impl Compiler {
         }
     }
 
+    // statements that leave nothing on the stack, so nothing is popped after
+    // them. const and class store their value like let does, popping after them
+    // took whatever was under it, a local in a function or a loop's iterator
     fn is_void(expr: &Expr) -> bool {
         matches!(
             expr,
-            Expr::Let { .. } | Expr::Assign { .. } | Expr::Function { .. } | Expr::Import { .. }
+            Expr::Let { .. }
+                | Expr::Const { .. }
+                | Expr::Assign { .. }
+                | Expr::Function { .. }
+                | Expr::Class { .. }
+                | Expr::Import { .. }
         )
     }
 
@@ -90,16 +99,36 @@ impl Compiler {
     // compiling shit
 
     pub fn compile_program(&mut self, ast: Vec<SpannedExpr>) -> Result<(), String> {
-        for expr in ast {
+        self.compile_top_level(ast, false)
+    }
+
+    // same as compile_program, but the value of the last expression
+    // is left on the stack so the caller can get it back
+    pub fn compile_eval(&mut self, ast: Vec<SpannedExpr>) -> Result<(), String> {
+        self.compile_top_level(ast, true)
+    }
+
+    pub fn attach_source(&mut self, source: Arc<SourceFile>) {
+        for chunk in &mut self.chunks {
+            chunk.source = Some(source.clone());
+        }
+    }
+
+    fn compile_top_level(&mut self, ast: Vec<SpannedExpr>, keep_last: bool) -> Result<(), String> {
+        let last = ast.len().saturating_sub(1);
+
+        for (i, expr) in ast.into_iter().enumerate() {
             let span = expr.span;
-            self.compile_expr(expr.clone())?;
+            let leaves_nothing = Self::is_void(&expr.node);
+            self.compile_expr(expr)?;
 
-            if !Self::is_void(&expr.node) {
+            if !leaves_nothing && (!keep_last || i != last) {
                 self.emit(Instruction::Pop, span);
             }
         }
 
         self.chunks[self.current_chunk].locals_count = self.scope.max_slot;
+        self.chunks[self.current_chunk].local_names = self.scope.names.clone();
 
         Ok(())
     }
@@ -500,8 +529,9 @@ impl Compiler {
                 self.compile_expr(*body.clone())?;
                 self.emit(Instruction::Return, span);
 
-                let locals_count = self.scope.exit_function(saved);
+                let (locals_count, local_names) = self.scope.exit_function(saved);
                 self.chunks[local_index].locals_count = locals_count;
+                self.chunks[local_index].local_names = local_names;
 
                 self.current_chunk = saved_chunk;
 
@@ -646,6 +676,15 @@ impl Compiler {
 
             Expr::Return(v) => {
                 self.compile_expr(*v.clone())?;
+
+                // `return f(...)` doesnt need the current frame anymore
+                if matches!(v.node, Expr::Call { .. })
+                    && let Some(last) = self.chunks[self.current_chunk].instructions.last_mut()
+                    && let Instruction::Call(argc) = *last
+                {
+                    *last = Instruction::TailCall(argc);
+                }
+
                 self.emit(Instruction::Return, span);
             }
 
@@ -728,12 +767,13 @@ impl Compiler {
                         body,
                     } = &f.node
                     {
-                        let chunk_id = self.chunks.len() + self.offset;
+                        let local_index = self.chunks.len();
+                        let chunk_id = local_index + self.offset;
                         self.chunks
                             .push(Chunk::new(&format!("{}::{}", name, method_name)));
 
                         let saved_chunk = self.current_chunk;
-                        self.current_chunk = chunk_id;
+                        self.current_chunk = local_index;
 
                         let saved_scope = self.scope.enter_function();
                         self.scope.define_local("self");
@@ -752,8 +792,9 @@ impl Compiler {
 
                         self.emit(Instruction::Return, span);
 
-                        let locals_count = self.scope.exit_function(saved_scope);
-                        self.chunks[chunk_id].locals_count = locals_count;
+                        let (locals_count, local_names) = self.scope.exit_function(saved_scope);
+                        self.chunks[local_index].locals_count = locals_count;
+                        self.chunks[local_index].local_names = local_names;
                         self.current_chunk = saved_chunk;
 
                         methods_map.insert(
@@ -770,6 +811,7 @@ impl Compiler {
 
                 let class_value = Value::Class {
                     name: name.clone(),
+                    class_id: crate::vm::cache::next_class_id(),
                     methods: methods_map,
                     parent_methods: HashMap::new(),
                 };
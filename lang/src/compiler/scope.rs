@@ -8,9 +8,18 @@ pub struct ScopeStack {
     scopes: Vec<Scope>,
     next_slot: usize,
     pub max_slot: usize,
+    pub names: Vec<String>, // by slot, slots aren't reused within a function
     function_depth: usize,
 }
 
+// what enter_function hands back to exit_function
+pub struct SavedScopes {
+    next_slot: usize,
+    max_slot: usize,
+    names: Vec<String>,
+    scopes: Vec<Scope>,
+}
+
 pub enum Variable {
     Local(usize),
     Global(String),
@@ -28,6 +37,7 @@ impl ScopeStack {
             scopes: Vec::new(),
             next_slot: 0,
             max_slot: 0,
+            names: Vec::new(),
             function_depth: 0,
         }
     }
@@ -36,30 +46,35 @@ impl ScopeStack {
         self.function_depth > 0
     }
 
-    pub fn enter_function(&mut self) -> (usize, usize, Vec<Scope>) {
+    pub fn enter_function(&mut self) -> SavedScopes {
         self.function_depth += 1;
 
-        let saved_scopes = std::mem::take(&mut self.scopes);
-        let saved_next = self.next_slot;
-        let saved_max = self.max_slot;
+        let saved = SavedScopes {
+            next_slot: self.next_slot,
+            max_slot: self.max_slot,
+            names: std::mem::take(&mut self.names),
+            scopes: std::mem::take(&mut self.scopes),
+        };
 
         self.next_slot = 0;
         self.max_slot = 0;
         self.push_scope();
 
-        (saved_next, saved_max, saved_scopes)
+        saved
     }
 
-    pub fn exit_function(&mut self, saved: (usize, usize, Vec<Scope>)) -> usize {
+    // the locals count and the names of the slots of the function
+    pub fn exit_function(&mut self, saved: SavedScopes) -> (usize, Vec<String>) {
         let locals_count = self.max_slot;
+        let names = std::mem::replace(&mut self.names, saved.names);
 
         self.function_depth -= 1;
 
-        self.next_slot = saved.0;
-        self.max_slot = saved.1;
-        self.scopes = saved.2;
+        self.next_slot = saved.next_slot;
+        self.max_slot = saved.max_slot;
+        self.scopes = saved.scopes;
 
-        locals_count
+        (locals_count, names)
     }
 
     pub fn resolve(&self, name: &str) -> Variable {
@@ -80,6 +95,8 @@ impl ScopeStack {
             self.max_slot = self.next_slot;
         }
 
+        self.names.push(name.to_string());
+
         if let Some(scope) = self.scopes.last_mut() {
             scope.locals.insert(name.to_string(), (slot, false));
         }
@@ -95,6 +112,8 @@ impl ScopeStack {
             self.max_slot = self.next_slot;
         }
 
+        self.names.push(name.to_string());
+
         if let Some(scope) = self.scopes.last_mut() {
             scope.locals.insert(name.to_string(), (slot, true));
         }
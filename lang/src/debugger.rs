@@ -0,0 +1,216 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{ErrorKind, ModuError};
+use crate::vm::debug::Hook;
+use crate::vm::vm::VM;
+
+// line breakpoints and stepping on top of a vm hook, what happens while
+// the program is stopped is up to a frontend like the one `modu debug` has.
+// VM::evaluate works on copies of the variables, but native values like
+// files and the vm's stdio are shared with the program
+//
+//   let mut modu = Interpreter::new();
+//   modu.vm().set_hook(Debugger::new(frontend));
+//   modu.eval_file("main.modu")?;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    Continue,
+    StepIn,   // to the next line, going into calls
+    StepOver, // to the next line in this function or the ones calling it
+    StepOut,  // to the function that called this one
+    Stop,     // ends the program with an error
+}
+
+#[derive(Debug, Clone)]
+pub enum Reason {
+    Entry,
+    Breakpoint,
+    Step,
+    Pause,            // the frontend asked for it from poll()
+    Error(ModuError), // nothing caught it, the program ends after this
+}
+
+pub trait Frontend: Send {
+    fn paused(&mut self, vm: &VM, reason: &Reason, breakpoints: &mut Breakpoints) -> Resume;
+
+    // on every new line while the program runs, true stops it right there
+    fn poll(&mut self, _vm: &VM, _breakpoints: &mut Breakpoints) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    lines: Vec<(PathBuf, usize)>,
+}
+
+impl Breakpoints {
+    // the file is matched against the end of the path, so utils.modu
+    // or lib/utils.modu both work. false if it was already there
+    pub fn add(&mut self, file: impl Into<PathBuf>, line: usize) -> bool {
+        let breakpoint = (file.into(), line);
+
+        if self.lines.contains(&breakpoint) {
+            return false;
+        }
+
+        self.lines.push(breakpoint);
+        true
+    }
+
+    pub fn remove(&mut self, file: &Path, line: usize) -> bool {
+        let before = self.lines.len();
+        self.lines.retain(|(f, l)| !(f == file && *l == line));
+        self.lines.len() != before
+    }
+
+    pub fn clear(&mut self, file: &Path) {
+        self.lines.retain(|(f, _)| f != file);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, usize)> {
+        self.lines
+            .iter()
+            .map(|(file, line)| (file.as_path(), *line))
+    }
+
+    pub fn hit(&self, path: &Path, line: usize) -> bool {
+        self.lines
+            .iter()
+            .any(|(file, l)| *l == line && path.ends_with(file))
+    }
+}
+
+pub struct Debugger<F: Frontend> {
+    frontend: F,
+    breakpoints: Breakpoints,
+    break_on_error: bool,
+    started: bool,
+    resume: Resume,
+    resumed_at: usize, // depth it was resumed at, for stepping over and out
+    lines: Vec<Option<(usize, usize, usize)>>, // chunk, line and ip each frame was last at
+}
+
+impl<F: Frontend> Debugger<F> {
+    // stops on the first line, like it was stepped into
+    pub fn new(frontend: F) -> Self {
+        Self {
+            frontend,
+            breakpoints: Breakpoints::default(),
+            break_on_error: true,
+            started: false,
+            resume: Resume::StepIn,
+            resumed_at: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn with_breakpoints(mut self, breakpoints: Breakpoints) -> Self {
+        self.breakpoints = breakpoints;
+        self
+    }
+
+    pub fn stop_on_entry(mut self, stop: bool) -> Self {
+        self.resume = if stop {
+            Resume::StepIn
+        } else {
+            Resume::Continue
+        };
+        self
+    }
+
+    pub fn break_on_error(mut self, enabled: bool) -> Self {
+        self.break_on_error = enabled;
+        self
+    }
+
+    // true when this instruction starts a line the frame wasn't on yet,
+    // jumping back to the start of a loop counts as a new line too
+    fn new_line(&mut self, depth: usize, here: (usize, usize, usize)) -> bool {
+        self.lines.truncate(depth);
+        self.lines.resize(depth, None);
+
+        let new_line = match self.lines[depth - 1] {
+            Some((chunk, line, ip)) => chunk != here.0 || line != here.1 || here.2 < ip,
+            None => true,
+        };
+
+        self.lines[depth - 1] = Some(here);
+        new_line
+    }
+
+    fn pause(&mut self, vm: &VM, reason: Reason) -> Result<(), ModuError> {
+        let resume = self.frontend.paused(vm, &reason, &mut self.breakpoints);
+
+        self.resume = resume;
+        self.resumed_at = vm.depth();
+
+        if resume == Resume::Stop {
+            return Err(ModuError::new(
+                ErrorKind::Interrupted,
+                "stopped by the debugger",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: Frontend> Hook for Debugger<F> {
+    fn instruction(&mut self, vm: &VM) -> Result<(), ModuError> {
+        let Some((chunk_id, ip)) = vm.position() else {
+            return Ok(());
+        };
+
+        // code that was made up on the spot, like the calls Interpreter::call does
+        let chunk = &vm.chunks[chunk_id];
+        let Some(source) = &chunk.source else {
+            return Ok(());
+        };
+
+        let Some(span) = chunk.spans.get(ip) else {
+            return Ok(());
+        };
+
+        let line = source.line(span.start);
+        let depth = vm.depth();
+
+        if !self.new_line(depth, (chunk_id, line, ip)) {
+            return Ok(());
+        }
+
+        let entry = !std::mem::replace(&mut self.started, true);
+
+        let reason = if entry && self.resume == Resume::StepIn {
+            Some(Reason::Entry)
+        } else if self.breakpoints.hit(&source.path, line) {
+            Some(Reason::Breakpoint)
+        } else if self.frontend.poll(vm, &mut self.breakpoints) {
+            Some(Reason::Pause)
+        } else {
+            let stepped = match self.resume {
+                Resume::StepIn => true,
+                Resume::StepOver => depth <= self.resumed_at,
+                Resume::StepOut => depth < self.resumed_at,
+                Resume::Continue | Resume::Stop => false,
+            };
+
+            stepped.then_some(Reason::Step)
+        };
+
+        match reason {
+            Some(reason) => self.pause(vm, reason),
+            None => Ok(()),
+        }
+    }
+
+    fn uncaught(&mut self, vm: &VM, error: &ModuError) {
+        // stopping it on purpose isn't something to break on
+        if self.break_on_error && !matches!(error.kind, ErrorKind::Interrupted | ErrorKind::Exit(_))
+        {
+            self.frontend
+                .paused(vm, &Reason::Error(error.clone()), &mut self.breakpoints);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use ariadne::{Color, Label, Report, ReportKind, Source};
+use colored::Colorize;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::vm::chunk::SourceFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Parse,
+    Compile,
+    Runtime,
+    Io,
+    Budget,      // ran out of instructions, time or memory, can't be caught
+    Interrupted, // stopped through an InterruptHandle, can't be caught either
+    Exit(u8),    // the script called exit(), whoever runs it decides what that means
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// codes for what gets found before anything runs, shown as E0001 and so on:
+//   E0001  a character that isn't part of the language
+//   E0002  a number literal that doesn't fit or can't be read
+//   E0003  something other than what the grammar allows there
+//   E0004  return outside of a function
+//   E0005  break or continue outside of a loop
+//   E0006  something the compiler can't do, like assigning to a constant
+//   E0007  an import of a module that isn't there
+#[derive(Debug, Clone)]
+pub struct ModuError {
+    pub kind: ErrorKind,
+    pub severity: Severity,
+    pub code: Option<u16>,
+    pub message: String,
+    pub label: Option<String>,
+    pub help: Option<String>,
+    pub span: Option<Range<usize>>,
+    pub source: Option<Arc<SourceFile>>,
+}
+
+impl ModuError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            label: None,
+            help: None,
+            span: None,
+            source: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_code(mut self, code: u16) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn code_name(&self) -> Option<String> {
+        self.code.map(|code| format!("E{:04}", code))
+    }
+
+    // the source is kept around so the error can be rendered later
+    pub fn at(mut self, span: Range<usize>, source: Arc<SourceFile>) -> Self {
+        self.span = Some(span);
+        self.source = Some(source);
+        self
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.source.as_ref().map(|s| s.path.as_path())
+    }
+
+    // 1-based line and column of the start of the span
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let span = self.span.as_ref()?;
+        let source = self.source.as_ref()?;
+
+        let before = &source.text[..span.start.min(source.text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+        Some((line, column))
+    }
+
+    // everything found in one pass, like parse_source returns it, one after another
+    pub fn render_all(errors: &[ModuError]) -> String {
+        errors.iter().map(ModuError::render).collect()
+    }
+
+    // pretty report with the offending code, like the cli prints it
+    pub fn render(&self) -> String {
+        let (Some(span), Some(source)) = (&self.span, &self.source) else {
+            return match self.kind {
+                ErrorKind::Compile => format!("{}: {}", "Compilation error".red(), self.message),
+                _ => self.message.clone(),
+            };
+        };
+
+        let file = source.path.to_string_lossy();
+        let file = file.as_ref();
+
+        let (kind, color) = match self.severity {
+            Severity::Error => (ReportKind::Error, Color::Red),
+            Severity::Warning => (ReportKind::Warning, Color::Yellow),
+        };
+
+        let mut label = Label::new((file, span.clone())).with_color(color);
+        if let Some(message) = &self.label {
+            label = label.with_message(message);
+        }
+
+        let mut report = Report::build(kind, (file, span.clone()))
+            .with_message(&self.message)
+            .with_label(label);
+
+        if let Some(code) = self.code_name() {
+            report = report.with_code(code);
+        }
+
+        if let Some(help) = &self.help {
+            report = report.with_help(help);
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+        report
+            .finish()
+            .write((file, Source::from(source.text.as_str())), &mut buf)
+            .ok();
+
+        String::from_utf8_lossy(&buf).to_string()
+    }
+}
+
+impl From<String> for ModuError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorKind::Runtime, message)
+    }
+}
+
+impl std::fmt::Display for ModuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.path(), self.location()) {
+            (Some(path), Some((line, column))) => {
+                write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    self.message
+                )
+            }
+
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ModuError {}
@@ -0,0 +1,178 @@
+// native extensions, shared libraries that hand modu a module of rust functions
+//
+//   use modu::Value;
+//   use modu::extension::Module;
+//
+//   fn init(module: &mut Module) {
+//       module.function("double", |args| match args.as_slice() {
+//           [Value::Int(n)] => Ok(Value::Int(n * 2)),
+//           _ => Err("double() takes an int".to_string()),
+//       });
+//   }
+//
+//   modu::extension!(init);
+//
+// build it as a cdylib with the same modu version and rustc as the interpreter
+// that loads it, then either `import "native:libfoo";` or point `native` in a
+// package's project.toml at it
+//
+// this is not a stable abi. init is a plain rust fn and Module and Value cross
+// over as rust types, whose layout only holds for one rustc and one build of
+// modu. the abi, modu and rustc versions are checked before init is called, but
+// that's all that can be checked, loading an extension runs its code with no
+// sandbox and a library that lies about its versions is undefined behavior.
+// only load extensions you built or trust
+
+use std::collections::HashMap;
+use std::ffi::{CStr, c_char};
+use std::path::Path;
+
+use crate::vm::value::{BuiltinFn, Value};
+
+// bumped whenever Declaration changes
+pub const ABI_VERSION: u32 = 2;
+
+pub const MODU_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("version has a nul in it"),
+    };
+
+// set by build.rs. an rlib only links with the rustc that built it, so this is
+// also the rustc the extension was compiled with
+pub const RUSTC_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("MODU_RUSTC_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("rustc version has a nul in it"),
+    };
+
+// what extension!() exports. only the first three fields are c types, they're
+// checked before init is touched so a mismatched build fails cleanly instead
+// of calling a rust fn across compilers
+#[repr(C)]
+pub struct Declaration {
+    pub abi_version: u32,
+    pub modu_version: *const c_char,
+    pub rustc_version: *const c_char,
+    pub init: fn(&mut Module),
+}
+
+unsafe impl Sync for Declaration {}
+
+#[macro_export]
+macro_rules! extension {
+    ($init:expr) => {
+        #[unsafe(no_mangle)]
+        pub static MODU_EXTENSION: $crate::extension::Declaration =
+            $crate::extension::Declaration {
+                abi_version: $crate::extension::ABI_VERSION,
+                modu_version: $crate::extension::MODU_VERSION.as_ptr(),
+                rustc_version: $crate::extension::RUSTC_VERSION.as_ptr(),
+                init: $init,
+            };
+    };
+}
+
+// what scripts get when they import the extension
+#[derive(Default)]
+pub struct Module {
+    members: HashMap<String, Value>,
+}
+
+impl Module {
+    pub fn function(
+        &mut self,
+        name: &str,
+        func: impl Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    ) {
+        self.value(name, Value::BuiltinFn(BuiltinFn::new(name, func)));
+    }
+
+    pub fn value(&mut self, name: &str, value: Value) {
+        self.members.insert(name.to_string(), value);
+    }
+}
+
+// unsafe in all but name, see the top of this file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(path: &Path) -> Result<Value, String> {
+    let lib = unsafe { libloading::Library::new(path) }
+        .map_err(|e| format!("cannot load native extension '{}': {}", path.display(), e))?;
+
+    let declaration = unsafe {
+        match lib.get::<*const Declaration>(b"MODU_EXTENSION\0") {
+            Ok(symbol) => &**symbol,
+            Err(_) => {
+                return Err(format!(
+                    "'{}' is not a modu extension (it has no MODU_EXTENSION, see modu::extension!)",
+                    path.display()
+                ));
+            }
+        }
+    };
+
+    if declaration.abi_version != ABI_VERSION {
+        return Err(format!(
+            "'{}' was built for a different extension abi ({}, this is {})",
+            path.display(),
+            declaration.abi_version,
+            ABI_VERSION
+        ));
+    }
+
+    let version = unsafe { CStr::from_ptr(declaration.modu_version) };
+
+    if version != MODU_VERSION {
+        return Err(format!(
+            "'{}' was built against modu {}, this is modu {}",
+            path.display(),
+            version.to_string_lossy(),
+            crate::VERSION
+        ));
+    }
+
+    let rustc = unsafe { CStr::from_ptr(declaration.rustc_version) };
+
+    if rustc != RUSTC_VERSION {
+        return Err(format!(
+            "'{}' was built with {}, this modu was built with {}",
+            path.display(),
+            rustc.to_string_lossy(),
+            RUSTC_VERSION.to_string_lossy()
+        ));
+    }
+
+    let mut module = Module::default();
+    (declaration.init)(&mut module);
+
+    // never unloaded. the functions init made are closures whose code lives in
+    // the library, and they're plain values, they get copied into globals, other
+    // modules and other interpreters, or handed back to the host by eval, all of
+    // which can outlive the vm that imported them. nothing tracks when the last
+    // one is gone so the library has to stay mapped for the rest of the process
+    std::mem::forget(lib);
+
+    Ok(Value::Object(module.members))
+}
+
+// "libfoo" becomes libfoo.so, libfoo.dylib or libfoo.dll
+pub fn file_name(name: &str) -> String {
+    if Path::new(name).extension().is_some() {
+        name.to_string()
+    } else {
+        format!("{}{}", name, std::env::consts::DLL_SUFFIX)
+    }
+}
+
+// what the module is called when imported without an alias, libfoo is foo
+pub fn default_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("extension");
+
+    stem.strip_prefix(std::env::consts::DLL_PREFIX)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(stem)
+        .to_string()
+}
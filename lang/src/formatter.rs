@@ -0,0 +1,440 @@
+use crate::error::{ErrorKind, ModuError};
+use crate::lexer::{Span, Token, lex_with_comments};
+use crate::parser::parse_source;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub indent: usize, // spaces per level
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { indent: 4 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Group {
+    Block,
+    Object,
+    Paren,
+    Bracket,
+}
+
+struct Open {
+    group: Group,
+    broken: bool, // one item per line, kept from how it was written
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, Span)>,
+    options: &'a Options,
+    out: String,
+    stack: Vec<Open>,
+    line_start: bool,    // nothing written on the current line yet
+    pending: bool,       // a newline is owed before the next token
+    prev: Option<Token>, // last token that wasn't a comment
+    unary: bool,         // prev was a unary minus
+    opened: bool,        // the last thing written opened a block or group
+}
+
+fn is_comment(token: &Token) -> bool {
+    matches!(token, Token::Comment | Token::MultiLineComment)
+}
+
+// tokens an expression can end with
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Identifier(_)
+            | Token::Int(_)
+            | Token::Float(_)
+            | Token::String(_)
+            | Token::Bool(_)
+            | Token::Null
+            | Token::Super
+            | Token::RParen
+            | Token::RBracket
+            | Token::RBrace
+    )
+}
+
+// a { after an operand or keyword like else opens a block, anywhere else its an object
+fn opens_block(prev: Option<&Token>) -> bool {
+    match prev {
+        None => true,
+        Some(token) => {
+            ends_operand(token)
+                || matches!(
+                    token,
+                    Token::Semicolon
+                        | Token::LBrace
+                        | Token::Else
+                        | Token::Loop
+                        | Token::Try
+                        | Token::Catch
+                )
+        }
+    }
+}
+
+impl<'a> Printer<'a> {
+    fn text(&self, i: usize) -> &'a str {
+        &self.source[self.tokens[i].1.into_range()]
+    }
+
+    // newlines in the original between token i and the one before it
+    fn gap(&self, i: usize) -> usize {
+        if i == 0 || i >= self.tokens.len() {
+            return 0;
+        }
+
+        let start = self.tokens[i - 1].1.end;
+        let end = self.tokens[i].1.start;
+
+        self.source[start..end].matches('\n').count()
+    }
+
+    fn next_code(&self, i: usize) -> Option<&Token> {
+        self.tokens[i + 1..]
+            .iter()
+            .map(|(token, _)| token)
+            .find(|token| !is_comment(token))
+    }
+
+    // a // comment ends its line, so a group with one directly inside cant stay on one
+    fn has_line_comment(&self, i: usize) -> bool {
+        let mut depth = 0;
+
+        for (token, _) in &self.tokens[i + 1..] {
+            match token {
+                Token::LBrace | Token::LParen | Token::LBracket => depth += 1,
+                Token::RBrace | Token::RParen | Token::RBracket if depth == 0 => return false,
+                Token::RBrace | Token::RParen | Token::RBracket => depth -= 1,
+                Token::Comment if depth == 0 => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    fn level(&self) -> usize {
+        self.stack
+            .iter()
+            .filter(|open| open.group == Group::Block || open.broken)
+            .count()
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            let width = self.level() * self.options.indent;
+            self.out.push_str(&" ".repeat(width));
+            self.line_start = false;
+        }
+
+        self.out.push_str(text);
+        self.opened = false;
+    }
+
+    fn space(&mut self) {
+        if !self.line_start && !self.out.is_empty() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        self.pending = false;
+
+        if self.line_start {
+            return;
+        }
+
+        let trimmed = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+        self.line_start = true;
+    }
+
+    // at most one, and never right after something opens
+    fn blank_line(&mut self) {
+        self.newline();
+
+        if self.opened || self.out.is_empty() || self.out.ends_with("\n\n") {
+            return;
+        }
+
+        self.out.push('\n');
+    }
+
+    fn space_before(&self, token: &Token) -> bool {
+        let Some(prev) = &self.prev else {
+            return false;
+        };
+
+        if matches!(
+            token,
+            Token::Comma
+                | Token::Semicolon
+                | Token::Dot
+                | Token::Colon
+                | Token::RParen
+                | Token::RBracket
+                | Token::Range
+                | Token::InclusiveRange
+        ) {
+            return false;
+        }
+
+        match prev {
+            Token::LParen
+            | Token::LBracket
+            | Token::Dot
+            | Token::Range
+            | Token::InclusiveRange
+            | Token::Not
+            | Token::BitNot => false,
+            Token::Minus if self.unary => false,
+
+            // calls and indexing
+            _ if matches!(token, Token::LParen | Token::LBracket) => !matches!(
+                prev,
+                Token::Identifier(_)
+                    | Token::String(_)
+                    | Token::Super
+                    | Token::RParen
+                    | Token::RBracket
+            ),
+
+            _ => true,
+        }
+    }
+
+    fn open(&mut self, i: usize, group: Group) {
+        let broken = self
+            .tokens
+            .get(i + 1)
+            .is_some_and(|(next, _)| is_comment(next) || self.gap(i + 1) > 0)
+            || self.has_line_comment(i);
+
+        self.stack.push(Open { group, broken });
+
+        if broken {
+            self.pending = true;
+            self.opened = true;
+        } else if group == Group::Object {
+            self.write(" ");
+        }
+    }
+
+    fn close(&mut self) {
+        let Some(open) = self.stack.pop() else {
+            return;
+        };
+
+        if open.group == Group::Block || open.broken {
+            self.newline();
+        } else if open.group == Group::Object {
+            self.space();
+        }
+    }
+
+    // after a block a new line starts, unless its carried on like } else {
+    fn after_block(&mut self, i: usize) {
+        self.pending = !matches!(
+            self.next_code(i),
+            Some(
+                Token::Else
+                    | Token::ElseIf
+                    | Token::Catch
+                    | Token::RParen
+                    | Token::RBracket
+                    | Token::Comma
+                    | Token::Semicolon
+                    | Token::Dot
+            )
+        );
+    }
+
+    fn comment(&mut self, i: usize) {
+        let text = self.text(i);
+        let lines = self.gap(i);
+
+        if lines == 0 && i > 0 {
+            self.pending = false;
+            self.space();
+        } else {
+            self.newline();
+
+            if lines > 1 {
+                self.blank_line();
+            }
+        }
+
+        self.write(text);
+
+        if self.tokens[i].0 == Token::Comment || self.gap(i + 1) > 0 {
+            self.pending = true;
+        }
+    }
+
+    fn print(mut self) -> String {
+        let mut i = 0;
+
+        while i < self.tokens.len() {
+            let token = self.tokens[i].0.clone();
+
+            if is_comment(&token) {
+                self.comment(i);
+                i += 1;
+                continue;
+            }
+
+            if self.pending {
+                self.newline();
+
+                if self.gap(i) > 1 && token != Token::RBrace {
+                    self.blank_line();
+                }
+            }
+
+            let unary = token == Token::Minus
+                && (self.line_start || !self.prev.as_ref().is_some_and(ends_operand));
+
+            if self.space_before(&token) {
+                self.space();
+            }
+
+            match token {
+                Token::LBrace if opens_block(self.prev.as_ref()) => {
+                    self.space();
+                    self.write("{");
+
+                    if self
+                        .tokens
+                        .get(i + 1)
+                        .is_some_and(|(t, _)| *t == Token::RBrace)
+                    {
+                        self.write("}");
+                        i += 1;
+                        self.after_block(i);
+                    } else {
+                        self.stack.push(Open {
+                            group: Group::Block,
+                            broken: true,
+                        });
+                        self.pending = true;
+                        self.opened = true;
+                    }
+                }
+
+                Token::LBrace => {
+                    self.write("{");
+
+                    if self
+                        .tokens
+                        .get(i + 1)
+                        .is_some_and(|(t, _)| *t == Token::RBrace)
+                    {
+                        self.write("}");
+                        i += 1;
+                    } else {
+                        self.open(i, Group::Object);
+                    }
+                }
+
+                Token::LParen | Token::LBracket => {
+                    self.write(self.text(i));
+
+                    let group = if token == Token::LParen {
+                        Group::Paren
+                    } else {
+                        Group::Bracket
+                    };
+
+                    self.open(i, group);
+                }
+
+                Token::RBrace => {
+                    let block = self
+                        .stack
+                        .last()
+                        .is_some_and(|open| open.group == Group::Block);
+
+                    self.close();
+                    self.write("}");
+
+                    if block {
+                        self.after_block(i);
+                    }
+                }
+
+                Token::RParen | Token::RBracket => {
+                    self.close();
+                    self.write(self.text(i));
+                }
+
+                Token::Comma => {
+                    self.write(",");
+
+                    if self.stack.last().is_some_and(|open| open.broken) {
+                        self.pending = true;
+                    }
+                }
+
+                Token::Semicolon => {
+                    self.write(";");
+                    self.pending = true;
+                }
+
+                _ => self.write(self.text(i)),
+            }
+
+            self.prev = Some(self.tokens[i].0.clone());
+            self.unary = unary;
+            i += 1;
+        }
+
+        let trimmed = self.out.trim_end().len();
+        self.out.truncate(trimmed);
+
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+
+        self.out
+    }
+}
+
+// only code that parses gets formatted, and the result has to parse to the same program
+pub fn format(source: &str, filename: &str, options: &Options) -> Result<String, Vec<ModuError>> {
+    let ast = parse_source(source, filename)?;
+
+    let printer = Printer {
+        source,
+        // it parsed, so it lexes
+        tokens: lex_with_comments(source).unwrap_or_default(),
+        options,
+        out: String::new(),
+        stack: Vec::new(),
+        line_start: true,
+        pending: false,
+        prev: None,
+        unary: false,
+        opened: false,
+    };
+
+    let formatted = printer.print();
+
+    match parse_source(&formatted, filename) {
+        Ok(new_ast) if new_ast == ast => Ok(formatted),
+        _ => Err(vec![
+            ModuError::new(
+                ErrorKind::Parse,
+                format!("Formatting {} would change what it does", filename),
+            )
+            .with_help("This is a bug in the formatter, please report it"),
+        ]),
+    }
+}
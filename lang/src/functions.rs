@@ -1,87 +1,61 @@
+use crate::vm::stdio::Stdio;
 use crate::vm::value::{BuiltinFn, Value};
+use std::sync::{Arc, Mutex};
 
-#[cfg(target_arch = "wasm32")]
-unsafe extern "C" {
-    fn _modu_print(ptr: *const u8, len: usize);
+fn builtin(name: &str, func: fn(Vec<Value>) -> Result<Value, String>) -> BuiltinFn {
+    BuiltinFn::new(name, func)
 }
 
-#[cfg(target_arch = "wasm32")]
-unsafe extern "C" {
-    fn _modu_input(ptr: *const u8, len: usize, out_len: *mut usize) -> *mut u8;
-}
+// where exit() leaves its status for the vm to pick up once the call returns.
+// only the exit builtin a vm was made with holds its signal, so no other
+// function can end the script whatever it returns
+#[derive(Clone, Default)]
+pub struct ExitSignal(Arc<Mutex<Option<i64>>>);
 
-fn builtin(name: &str, func: fn(Vec<Value>) -> Result<Value, String>) -> BuiltinFn {
-    BuiltinFn {
-        name: name.to_string(),
-        func,
+impl ExitSignal {
+    fn set(&self, code: i64) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(code);
+    }
+
+    pub(crate) fn take(&self) -> Option<i64> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
     }
 }
 
-pub fn get_functions() -> Vec<BuiltinFn> {
+// print and input go through the vm's stdio, exit signals the vm
+pub fn get_functions(stdio: &Stdio, signal: &ExitSignal) -> Vec<BuiltinFn> {
+    let print_stdio = stdio.clone();
+    let input_stdio = stdio.clone();
+    let signal = signal.clone();
+
     vec![
-        builtin("print", print),
-        builtin("input", input),
+        BuiltinFn::new("print", move |args| print(&print_stdio, args)),
+        BuiltinFn::new("input", move |args| input(&input_stdio, args)),
         builtin("int", int),
         builtin("float", float),
         builtin("str", str),
         builtin("bool", bool),
         builtin("type", r#type),
-        builtin("exit", exit),
+        BuiltinFn::new("exit", move |args| exit(&signal, args)),
         builtin("error", error),
         builtin("assert", assert),
     ]
 }
 
-fn print(args: Vec<Value>) -> Result<Value, String> {
+fn print(stdio: &Stdio, args: Vec<Value>) -> Result<Value, String> {
     let output = args.iter().map(|v| format!("{}", v)).collect::<String>();
-
-    #[cfg(target_arch = "wasm32")]
-    {
-        let text = format!("{}\n", output);
-        unsafe { _modu_print(text.as_ptr(), text.len()) };
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    println!("{}", output);
+    stdio.write(&format!("{}\n", output))?;
 
     Ok(Value::Null)
 }
 
-fn input(args: Vec<Value>) -> Result<Value, String> {
-    for arg in args.clone() {
-        print!("{}", arg);
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        use std::io::{self, Write};
-        io::stdout().flush().unwrap();
+fn input(stdio: &Stdio, args: Vec<Value>) -> Result<Value, String> {
+    let prompt = args.iter().map(|v| format!("{}", v)).collect::<String>();
+    stdio.write(&prompt)?;
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+    let input = stdio.read_line()?.unwrap_or_default();
 
-        Ok(Value::String(input.trim_end().to_string()))
-    }
-
-    #[cfg(target_arch = "wasm32")]
-    {
-        let prompt = args.iter().map(|v| format!("{}", v)).collect::<String>();
-        let input;
-
-        unsafe {
-            let mut out_len: usize = 0;
-            let ptr = _modu_input(prompt.as_ptr(), prompt.len(), &mut out_len);
-
-            if ptr.is_null() {
-                return Err("input failed".to_string());
-            }
-
-            let bytes = std::slice::from_raw_parts(ptr, out_len);
-            input = String::from_utf8_lossy(bytes).to_string();
-        }
-
-        Ok(Value::String(input.trim_end().to_string()))
-    }
+    Ok(Value::String(input.trim_end().to_string()))
 }
 
 fn int(args: Vec<Value>) -> Result<Value, String> {
@@ -159,12 +133,16 @@ fn r#type(args: Vec<Value>) -> Result<Value, String> {
     Ok(Value::String(args[0].type_name().to_string()))
 }
 
-fn exit(args: Vec<Value>) -> Result<Value, String> {
+fn exit(signal: &ExitSignal, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
-        std::process::exit(0);
+        signal.set(0);
+        Ok(Value::Null)
     } else if args.len() == 1 {
         match &args[0] {
-            Value::Int(i) => std::process::exit(*i as i32),
+            Value::Int(i) => {
+                signal.set(*i);
+                Ok(Value::Null)
+            }
             _ => {
                 Err(format!(
                     "exit() argument must be an int, got {}",
@@ -0,0 +1,318 @@
+use serde_json::{Map, Value as Json, json};
+
+use crate::ast::SpannedExpr;
+use crate::compiler::compiler::Compiler;
+use crate::error::{ErrorKind, ModuError};
+use crate::parser::parse_source;
+use crate::vm::chunk::{Chunk, SourceFile};
+use crate::vm::instruction::Instruction;
+use crate::vm::value::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// what modu disasm and modu ast print, a file's syntax tree and bytecode
+// without running any of it. the json forms are for tools like the playground
+//
+//   let chunks = inspect::compile(code, "main.modu")?;
+//   print!("{}", inspect::disassemble(&chunks));
+
+// every error in the file, like modu check finds them
+pub fn parse(code: &str, path: &str) -> Result<Vec<SpannedExpr>, Vec<ModuError>> {
+    parse_source(code, path)
+}
+
+// chunk ids are indexes into what comes back, main is 0
+pub fn compile(code: &str, path: &str) -> Result<Vec<Chunk>, Vec<ModuError>> {
+    let ast = parse(code, path)?;
+
+    let mut compiler = Compiler::new();
+    compiler
+        .compile_program(ast)
+        .map_err(|e| vec![ModuError::new(ErrorKind::Compile, e)])?;
+    compiler.attach_source(SourceFile::new(path, code));
+
+    Ok(compiler.chunks)
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Jump(target)
+        | Instruction::JumpIfFalse(target)
+        | Instruction::SetupTry(target) => Some(*target),
+        _ => None,
+    }
+}
+
+fn constant(value: &Value, chunks: &[Chunk]) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        Value::Function { chunk_id, arity } => match chunks.get(*chunk_id) {
+            Some(chunk) => format!("fn {}/{} (chunk {})", chunk.name, arity, chunk_id),
+            None => value.to_string(),
+        },
+        value => value.to_string(),
+    }
+}
+
+// what an instruction refers to, spelled out
+fn annotation(chunk: &Chunk, chunks: &[Chunk], instruction: &Instruction) -> Option<String> {
+    let local = |slot: &usize| {
+        chunk
+            .local_names
+            .get(*slot)
+            .cloned()
+            .unwrap_or_else(|| format!("slot {}", slot))
+    };
+
+    match instruction {
+        Instruction::Push(index) => chunk.constants.get(*index).map(|c| constant(c, chunks)),
+        Instruction::LoadLocal(slot) | Instruction::StoreLocal(slot) => Some(local(slot)),
+        Instruction::CallMethod {
+            target_local: Some(slot),
+            ..
+        } => Some(format!("on {}", local(slot))),
+        Instruction::IterNext {
+            slot_iter,
+            slot_var,
+            ..
+        } => Some(format!("{} in {}", local(slot_var), local(slot_iter))),
+        instruction => jump_target(instruction).map(|target| format!("-> {:04}", target)),
+    }
+}
+
+fn line(chunk: &Chunk, ip: usize) -> Option<usize> {
+    match (&chunk.source, chunk.spans.get(ip)) {
+        (Some(source), Some(span)) => Some(source.line(span.start)),
+        _ => None,
+    }
+}
+
+fn file_name(source: &SourceFile) -> String {
+    source
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn disassemble(chunks: &[Chunk]) -> String {
+    let mut out = String::new();
+
+    for (id, chunk) in chunks.iter().enumerate() {
+        if id > 0 {
+            out.push('\n');
+        }
+
+        out.push_str(&format!("chunk {}: {}", id, chunk.name));
+        if let (Some(source), Some(line)) = (&chunk.source, line(chunk, 0)) {
+            out.push_str(&format!(" ({}:{})", file_name(source), line));
+        }
+        if !chunk.local_names.is_empty() {
+            out.push_str(&format!(", locals: {}", chunk.local_names.join(", ")));
+        }
+        out.push('\n');
+
+        let targets: HashSet<usize> = chunk.instructions.iter().filter_map(jump_target).collect();
+        let mut last_line = None;
+
+        for (ip, instruction) in chunk.instructions.iter().enumerate() {
+            // the source line whenever it changes, so the bytecode reads along with it
+            let line = line(chunk, ip);
+            if line != last_line
+                && let (Some(source), Some(line)) = (&chunk.source, line)
+            {
+                let text = source.line_text(line).unwrap_or_default().trim();
+                out.push_str(&format!("{:>6} | {}\n", line, text));
+            }
+            last_line = line;
+
+            let marker = if targets.contains(&ip) { ">>" } else { "  " };
+            let text = format!("{:?}", instruction);
+
+            match annotation(chunk, chunks, instruction) {
+                Some(note) => {
+                    out.push_str(&format!("  {:04} {} {:<32} ; {}\n", ip, marker, text, note))
+                }
+                None => out.push_str(&format!("  {:04} {} {}\n", ip, marker, text)),
+            }
+        }
+    }
+
+    out
+}
+
+pub fn disassemble_json(chunks: &[Chunk]) -> Json {
+    let chunks: Vec<Json> = chunks
+        .iter()
+        .enumerate()
+        .map(|(id, chunk)| {
+            let targets: HashSet<usize> =
+                chunk.instructions.iter().filter_map(jump_target).collect();
+
+            let instructions: Vec<Json> = chunk
+                .instructions
+                .iter()
+                .enumerate()
+                .map(|(ip, instruction)| {
+                    let text = format!("{:?}", instruction);
+                    let op = text.split(['(', ' ']).next().unwrap_or_default();
+                    let span = chunk.spans.get(ip).map(|span| [span.start, span.end]);
+
+                    json!({
+                        "ip": ip,
+                        "op": op,
+                        "text": text,
+                        "line": line(chunk, ip),
+                        "span": span,
+                        "annotation": annotation(chunk, chunks, instruction),
+                        "target": jump_target(instruction),
+                        "jump_target": targets.contains(&ip),
+                    })
+                })
+                .collect();
+
+            json!({
+                "id": id,
+                "name": chunk.name,
+                "file": chunk.source.as_ref().map(|s| s.path.display().to_string()),
+                "line": line(chunk, 0),
+                "locals": chunk.local_names,
+                "constants": chunk.constants.iter().map(|c| constant(c, chunks)).collect::<Vec<_>>(),
+                "instructions": instructions,
+            })
+        })
+        .collect();
+
+    json!({ "chunks": chunks })
+}
+
+fn spanned(map: &Map<String, Json>) -> bool {
+    map.len() == 2 && map.contains_key("node") && map.get("span").is_some_and(Json::is_array)
+}
+
+// serde gives {"span": .., "node": {"Let": {..}}}, this flattens every node
+// into {"kind": "Let", "line", "column", "span", ..fields}. tuple variants
+// like Add keep their operands in "children", newtypes like Int in "value"
+// and an if has "branches"
+fn node(value: Json, source: &SourceFile) -> Json {
+    match value {
+        Json::Object(mut map) if spanned(&map) => {
+            let span = map.remove("span").unwrap_or_default();
+            let inner = map.remove("node").unwrap_or_default();
+            let start = span[0].as_u64().unwrap_or(0) as usize;
+
+            let (kind, fields) = match inner {
+                Json::String(kind) => (kind, None),
+                Json::Object(variant) => match variant.into_iter().next() {
+                    Some((kind, fields)) => (kind, Some(fields)),
+                    None => (String::new(), None),
+                },
+                other => (String::new(), Some(other)),
+            };
+
+            let mut out = Map::new();
+            out.insert("kind".to_string(), Json::String(kind.clone()));
+            out.insert("line".to_string(), json!(source.line(start)));
+            out.insert("column".to_string(), json!(source.column(start)));
+            out.insert("span".to_string(), span);
+
+            match fields {
+                Some(Json::Object(fields)) if !spanned(&fields) => {
+                    for (name, field) in fields {
+                        out.insert(name, node(field, source));
+                    }
+                }
+                // [[condition, body], ..], with a null condition for else
+                Some(Json::Array(branches)) if kind == "If" => {
+                    let branches = branches
+                        .into_iter()
+                        .map(|branch| {
+                            let [condition, body] = <[Json; 2]>::try_from(
+                                branch.as_array().cloned().unwrap_or_default(),
+                            )
+                            .unwrap_or_default();
+                            json!({
+                                "condition": node(condition, source),
+                                "body": node(body, source),
+                            })
+                        })
+                        .collect();
+                    out.insert("branches".to_string(), Json::Array(branches));
+                }
+                Some(Json::Array(items)) => {
+                    let items = items.into_iter().map(|item| node(item, source)).collect();
+                    out.insert("children".to_string(), Json::Array(items));
+                }
+                Some(value) => {
+                    out.insert("value".to_string(), node(value, source));
+                }
+                None => {}
+            }
+
+            Json::Object(out)
+        }
+        Json::Array(items) => Json::Array(items.into_iter().map(|i| node(i, source)).collect()),
+        Json::Object(map) => Json::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, node(value, source)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+pub fn ast_json(ast: &[SpannedExpr], source: &Arc<SourceFile>) -> Json {
+    node(serde_json::to_value(ast).unwrap_or_default(), source)
+}
+
+fn is_node(value: &Json) -> bool {
+    value.get("kind").is_some() && value.get("span").is_some()
+}
+
+fn render(value: &Json, label: Option<&str>, depth: usize, out: &mut String) {
+    let label = label.map(|l| format!("{}: ", l)).unwrap_or_default();
+    let indent = "  ".repeat(depth);
+
+    match value {
+        Json::Object(map) if is_node(value) => {
+            let mut line = format!("{}{}{}", indent, label, map["kind"].as_str().unwrap_or("?"));
+            let mut nested = Vec::new();
+
+            for (key, field) in map {
+                match (key.as_str(), field) {
+                    ("kind" | "line" | "column" | "span", _) => {}
+                    (_, Json::Object(_) | Json::Array(_)) => nested.push((key, field)),
+                    (_, field) => line.push_str(&format!(" {}={}", key, field)),
+                }
+            }
+
+            let at = format!("{}:{}", map["line"], map["column"]);
+            out.push_str(&format!("{:>9} {}\n", at, line));
+
+            for (key, field) in nested {
+                render(field, Some(key), depth + 1, out);
+            }
+        }
+        Json::Array(items) => {
+            for item in items {
+                render(item, label.strip_suffix(": "), depth, out);
+            }
+        }
+        Json::Object(map) => {
+            out.push_str(&format!("{:>9} {}{}\n", "", indent, label.trim_end()));
+
+            for (key, field) in map {
+                render(field, Some(key), depth + 1, out);
+            }
+        }
+        scalar => out.push_str(&format!("{:>9} {}{}{}\n", "", indent, label, scalar)),
+    }
+}
+
+// one node per line with where it starts, children indented under it
+pub fn ast_text(ast: &[SpannedExpr], source: &Arc<SourceFile>) -> String {
+    let mut out = String::new();
+    render(&ast_json(ast, source), None, 0, &mut out);
+    out
+}
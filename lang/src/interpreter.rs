@@ -0,0 +1,188 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::compiler::compiler::Compiler;
+use crate::error::{ErrorKind, ModuError};
+use crate::permissions::Permissions;
+use crate::vm::chunk::SourceFile;
+use crate::vm::convert::{ConvertError, from_value_at, to_value};
+use crate::vm::value::{BuiltinFn, Value};
+use crate::vm::vm::{InterruptHandle, Limits, VM};
+
+// the way to run modu code from rust, globals stick around between evals
+//
+//   let mut modu = Interpreter::new();
+//   modu.eval("fn add(a, b) { return a + b; }")?;
+//   let sum = modu.call("add", vec![Value::Int(1), Value::Int(2)])?;
+pub struct Interpreter {
+    vm: VM,
+    import_dir: Option<PathBuf>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_limits(Limits::default())
+    }
+
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            vm: VM::with_limits(Vec::new(), PathBuf::from("<eval>"), String::new(), limits),
+            import_dir: None,
+        }
+    }
+
+    // set this before running anything, modules that were already
+    // imported keep what they were imported with
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.vm.set_permissions(permissions);
+    }
+
+    // where print() goes, like an OutputBuffer to capture it
+    pub fn set_stdout(&mut self, writer: impl Write + Send + 'static) {
+        self.vm.stdio().set_stdout(writer);
+    }
+
+    pub fn set_stderr(&mut self, writer: impl Write + Send + 'static) {
+        self.vm.stdio().set_stderr(writer);
+    }
+
+    // where input() reads lines from
+    pub fn set_stdin(&mut self, reader: impl BufRead + Send + 'static) {
+        self.vm.stdio().set_stdin(reader);
+    }
+
+    // stops the eval or call that is running, from another thread or a signal handler
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.vm.interrupt_handle()
+    }
+
+    // where relative imports in eval'd code resolve from, the current directory
+    // when this isn't set. eval_file always imports next to the file
+    pub fn set_import_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.import_dir = Some(dir.into());
+    }
+
+    // returns the value of the last expression, or null. what each eval compiles
+    // stays loaded for as long as the interpreter, since functions and classes it
+    // defined can be called later, so a long running repl grows with its input
+    pub fn eval(&mut self, source: &str) -> Result<Value, ModuError> {
+        self.eval_with_name(source, "<eval>")
+    }
+
+    // the name is what errors show as the file
+    pub fn eval_with_name(&mut self, source: &str, name: &str) -> Result<Value, ModuError> {
+        let import_dir = self
+            .import_dir
+            .clone()
+            .or_else(|| std::env::current_dir().ok());
+
+        self.eval_source(source, PathBuf::from(name), import_dir)
+    }
+
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, ModuError> {
+        let path = path.as_ref();
+
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            ModuError::new(
+                ErrorKind::Io,
+                format!("cannot read '{}': {}", path.display(), e),
+            )
+        })?;
+
+        // imports resolve relative to the file, so it has to be absolute
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        self.eval_source(&source, path, None)
+    }
+
+    fn eval_source(
+        &mut self,
+        source: &str,
+        path: PathBuf,
+        import_dir: Option<PathBuf>,
+    ) -> Result<Value, ModuError> {
+        let name = path.display().to_string();
+
+        // one error per eval, parse_source has every error if they're all wanted
+        let ast =
+            crate::parser::parse_source(source, &name).map_err(|mut errors| errors.remove(0))?;
+
+        let mut compiler = Compiler::new();
+        compiler.offset = self.vm.chunks.len();
+        compiler
+            .compile_eval(ast)
+            .map_err(|e| ModuError::new(ErrorKind::Compile, e))?;
+
+        compiler.attach_source(SourceFile::new(&path, source));
+
+        let start = self.vm.load(compiler.chunks);
+        self.vm.set_source(path, source.to_string());
+        self.vm.set_import_dir(import_dir);
+        self.vm.run_value(start)
+    }
+
+    // calls a global function (or class, or builtin) by name
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, ModuError> {
+        let callee = self.get_global(name).ok_or_else(|| {
+            ModuError::new(ErrorKind::Runtime, format!("undefined function '{}'", name))
+        })?;
+
+        self.call_value(callee, args)
+    }
+
+    // for callables that aren't globals, like a function returned from eval
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, ModuError> {
+        self.vm.call(callee, args)
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.vm.globals.get(name).cloned()
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.vm.globals.insert(name.to_string(), value);
+    }
+
+    // reads a global into any deserializable rust type,
+    // errors name the path inside it, like `config.servers[2].port`
+    pub fn get_global_as<T: DeserializeOwned>(&self, name: &str) -> Result<T, ConvertError> {
+        let value = self.get_global(name).ok_or_else(|| ConvertError {
+            path: Some(name.to_string()),
+            message: "no such global".to_string(),
+        })?;
+
+        from_value_at(value, name)
+    }
+
+    pub fn set_global_from<T: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        let value = to_value(value)?;
+        self.set_global(name, value);
+        Ok(())
+    }
+
+    // makes a rust function or closure callable from modu,
+    // errors become catchable modu errors
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        func: impl Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    ) {
+        self.set_global(name, Value::BuiltinFn(BuiltinFn::new(name, func)));
+    }
+
+    pub fn vm(&mut self) -> &mut VM {
+        &mut self.vm
+    }
+}
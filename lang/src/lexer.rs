@@ -236,20 +236,43 @@ pub enum Token {
     #[regex(r"[ \t\n\f\r]+", logos::skip)]
     Whitespace,
 
-    #[regex(r"//[^\n]*", logos::skip, allow_greedy = true)]
+    // kept so the formatter can put them back, lex() drops them
+    #[regex(r"//[^\n]*", allow_greedy = true)]
     Comment,
 
-    #[regex(r"/\*([^*]|\*+[^*/])*\*+/", logos::skip, allow_greedy = true)]
+    #[regex(r"/\*([^*]|\*+[^*/])*\*+/", allow_greedy = true)]
     MultiLineComment,
 }
 
 pub fn lex(input: &str) -> Result<Vec<(Token, Span)>, (LexingError, Span)> {
+    let mut tokens = lex_with_comments(input)?;
+    tokens.retain(|(token, _)| !matches!(token, Token::Comment | Token::MultiLineComment));
+
+    Ok(tokens)
+}
+
+// every token including comments, for tools that need to give the source back
+pub fn lex_with_comments(input: &str) -> Result<Vec<(Token, Span)>, (LexingError, Span)> {
+    let (tokens, mut errors) = lex_all(input);
+
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors.remove(0))
+    }
+}
+
+pub type Tokens = Vec<(Token, Span)>;
+
+// goes on past what it can't read, so every bad token in a file can be reported
+pub fn lex_all(input: &str) -> (Tokens, Vec<(LexingError, Span)>) {
     let mut lexer = Token::lexer(input);
     let mut tokens: Vec<(Token, Span)> = Vec::new();
+    let mut errors = Vec::new();
 
     while let Some(token) = lexer.next() {
         match token {
-            Err(e) => return Err((e, SimpleSpan::from(lexer.span()))),
+            Err(e) => errors.push((e, SimpleSpan::from(lexer.span()))),
 
             Ok(v) => {
                 tokens.push((v, SimpleSpan::from(lexer.span())));
@@ -257,7 +280,7 @@ pub fn lex(input: &str) -> Result<Vec<(Token, Span)>, (LexingError, Span)> {
         }
     }
 
-    Ok(tokens)
+    (tokens, errors)
 }
 
 impl std::fmt::Display for LexingError {
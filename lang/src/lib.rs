@@ -1,7 +1,16 @@
+pub mod analysis;
 pub mod ast;
+pub mod debugger;
+pub mod error;
+pub mod extension;
+pub mod formatter;
 pub mod functions;
+pub mod inspect;
 pub mod lexer;
+pub mod linter;
 pub mod parser;
+pub mod permissions;
+pub mod tracer;
 pub mod validator;
 
 pub mod natives;
@@ -10,29 +19,18 @@ pub mod stdlib;
 pub mod compiler;
 pub mod vm;
 
+pub mod interpreter;
+pub use error::{ErrorKind, ModuError};
+pub use interpreter::Interpreter;
+pub use permissions::Permissions;
+pub use vm::convert::{ConvertError, from_value, to_value};
+pub use vm::stdio::{OutputBuffer, Stdio};
+pub use vm::value::Value;
+pub use vm::vm::{InterruptHandle, Limits};
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod capi;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod cli;
-
-#[cfg(target_arch = "wasm32")]
-pub struct WasmWriter;
-
-#[cfg(target_arch = "wasm32")]
-impl std::io::Write for WasmWriter {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        unsafe extern "C" {
-            fn _modu_print(ptr: *const u8, len: usize);
-        }
-
-        unsafe {
-            _modu_print(buf.as_ptr(), buf.len());
-        }
-
-        Ok(buf.len())
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
-    }
-}
@@ -0,0 +1,646 @@
+// warnings about code that runs but probably doesn't do what was meant, on
+// top of the analysis the language server uses. rules can be turned off in
+// project.toml:
+//
+//   [lint]
+//   unused_parameter = false
+//
+// or in the file itself, with `// lint-disable rule` for the whole file and
+// `// lint-ignore rule` for the line it's on, or the next one when it's on
+// its own line. leaving out the rule means all of them
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use ariadne::{Color, Label, Report, ReportKind, Source};
+
+use crate::analysis::{Analysis, Definition, SymbolKind, analyze};
+use crate::ast::{Expr, SpannedExpr};
+use crate::lexer::{Token, lex_with_comments};
+use crate::vm::chunk::SourceFile;
+
+pub const RULES: [(&str, &str); 9] = [
+    ("unused_variable", "a local variable that is never read"),
+    ("unused_import", "an import that is never used"),
+    (
+        "unused_parameter",
+        "a function parameter that is never read",
+    ),
+    (
+        "shadowed_local",
+        "a local that hides another variable with the same name",
+    ),
+    (
+        "undefined_assignment",
+        "assigning to a name that was never declared with let",
+    ),
+    (
+        "typo",
+        "a name that isn't defined but looks like one that is",
+    ),
+    ("unreachable_code", "code after return, break or continue"),
+    (
+        "constant_condition",
+        "an if or while condition that never changes",
+    ),
+    (
+        "wrong_arity",
+        "calling a function or class with the wrong number of arguments",
+    ),
+];
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub rule: &'static str,
+    pub message: String,
+    pub help: Option<String>,
+    pub span: Range<usize>,
+}
+
+impl Lint {
+    pub fn render(&self, file: &SourceFile) -> String {
+        let path = file.path.to_string_lossy();
+        let path = path.as_ref();
+
+        let mut report = Report::build(ReportKind::Warning, (path, self.span.clone()))
+            .with_message(format!("{} [{}]", self.message, self.rule))
+            .with_label(Label::new((path, self.span.clone())).with_color(Color::Yellow));
+
+        if let Some(help) = &self.help {
+            report = report.with_help(help);
+        }
+
+        let mut buf: Vec<u8> = Vec::new();
+        report
+            .finish()
+            .write((path, Source::from(file.text.as_str())), &mut buf)
+            .ok();
+
+        String::from_utf8_lossy(&buf).to_string()
+    }
+}
+
+// which rules are on, everything is unless turned off
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    rules: HashMap<String, bool>,
+}
+
+impl Config {
+    // the [lint] table of a project.toml
+    pub fn from_toml(project: &toml::Value) -> Result<Self, String> {
+        let mut config = Config::default();
+
+        let Some(table) = project.get("lint") else {
+            return Ok(config);
+        };
+
+        let table = table
+            .as_table()
+            .ok_or("[lint] in project.toml should be a table")?;
+
+        for (rule, enabled) in table {
+            let enabled = enabled
+                .as_bool()
+                .ok_or_else(|| format!("lint rule '{}' should be true or false", rule))?;
+
+            config.set(rule, enabled)?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn set(&mut self, rule: &str, enabled: bool) -> Result<(), String> {
+        if !RULES.iter().any(|(name, _)| *name == rule) {
+            return Err(format!("Unknown lint rule '{}'", rule));
+        }
+
+        self.rules.insert(rule.to_string(), enabled);
+        Ok(())
+    }
+
+    pub fn enabled(&self, rule: &str) -> bool {
+        self.rules.get(rule).copied().unwrap_or(true)
+    }
+}
+
+pub fn lint(file: &SourceFile, ast: &[SpannedExpr], config: &Config) -> Vec<Lint> {
+    let analysis = analyze(&file.text, ast);
+
+    let mut checker = Checker {
+        analysis: &analysis,
+        calls: analysis
+            .references
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.span.start, i))
+            .collect(),
+        lints: Vec::new(),
+    };
+
+    checker.definitions(file);
+    checker.names();
+
+    for expr in ast {
+        checker.expr(expr);
+    }
+
+    let mut lints = checker.lints;
+    let comments = Comments::new(file);
+
+    lints.retain(|lint| config.enabled(lint.rule) && !comments.ignores(file, lint));
+    lints.sort_by_key(|lint| lint.span.start);
+    lints
+}
+
+// `// lint-disable` and `// lint-ignore` in the file, "*" is every rule
+struct Comments {
+    disabled: HashSet<String>,
+    ignored: HashMap<usize, HashSet<String>>, // by line
+}
+
+fn directive(comment: &str, name: &str) -> Option<HashSet<String>> {
+    let rest = comment.trim_start_matches('/').trim().strip_prefix(name)?;
+
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let mut rules: HashSet<String> = rest
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if rules.is_empty() {
+        rules.insert("*".to_string());
+    }
+
+    Some(rules)
+}
+
+impl Comments {
+    fn new(file: &SourceFile) -> Self {
+        let mut comments = Comments {
+            disabled: HashSet::new(),
+            ignored: HashMap::new(),
+        };
+
+        let tokens = lex_with_comments(&file.text).unwrap_or_default();
+
+        for (i, (token, span)) in tokens.iter().enumerate() {
+            if *token != Token::Comment {
+                continue;
+            }
+
+            let text = &file.text[span.into_range()];
+
+            if let Some(rules) = directive(text, "lint-disable") {
+                comments.disabled.extend(rules);
+                continue;
+            }
+
+            let Some(rules) = directive(text, "lint-ignore") else {
+                continue;
+            };
+
+            // trailing comments are about their own line
+            let line = file.line(span.start);
+            let trailing = i > 0 && file.line(tokens[i - 1].1.end) == line;
+            let line = if trailing { line } else { line + 1 };
+
+            comments.ignored.entry(line).or_default().extend(rules);
+        }
+
+        comments
+    }
+
+    fn ignores(&self, file: &SourceFile, lint: &Lint) -> bool {
+        let matches = |rules: &HashSet<String>| rules.contains("*") || rules.contains(lint.rule);
+
+        matches(&self.disabled)
+            || self
+                .ignored
+                .get(&file.line(lint.span.start))
+                .is_some_and(matches)
+    }
+}
+
+struct Checker<'a> {
+    analysis: &'a Analysis,
+    calls: HashMap<usize, usize>, // reference by where it starts
+    lints: Vec<Lint>,
+}
+
+fn kind_name(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Variable => "variable",
+        SymbolKind::Constant => "constant",
+        SymbolKind::Function => "function",
+        SymbolKind::Parameter => "parameter",
+        SymbolKind::Class => "class",
+        SymbolKind::Method => "method",
+        SymbolKind::Module => "import",
+    }
+}
+
+// true, 1 == 2, !null.. conditions that come out the same every time
+fn constant(expr: &SpannedExpr) -> bool {
+    match &expr.node {
+        Expr::Int(_) | Expr::Float(_) | Expr::String(_) | Expr::Bool(_) | Expr::Null => true,
+        Expr::Not(value) | Expr::Neg(value) => constant(value),
+        Expr::Equal(a, b)
+        | Expr::NotEqual(a, b)
+        | Expr::LessThan(a, b)
+        | Expr::LessThanOrEqual(a, b)
+        | Expr::GreaterThan(a, b)
+        | Expr::GreaterThanOrEqual(a, b)
+        | Expr::And(a, b)
+        | Expr::Or(a, b) => constant(a) && constant(b),
+        _ => false,
+    }
+}
+
+fn children(expr: &Expr) -> Vec<&SpannedExpr> {
+    match expr {
+        Expr::Return(value)
+        | Expr::Neg(value)
+        | Expr::BitNot(value)
+        | Expr::Not(value)
+        | Expr::Let { value, .. }
+        | Expr::Const { value, .. } => vec![value],
+
+        Expr::Add(a, b)
+        | Expr::Sub(a, b)
+        | Expr::Mul(a, b)
+        | Expr::Div(a, b)
+        | Expr::Mod(a, b)
+        | Expr::Pow(a, b)
+        | Expr::BitAnd(a, b)
+        | Expr::BitOr(a, b)
+        | Expr::BitXor(a, b)
+        | Expr::BitShl(a, b)
+        | Expr::BitShr(a, b)
+        | Expr::Equal(a, b)
+        | Expr::NotEqual(a, b)
+        | Expr::LessThan(a, b)
+        | Expr::LessThanOrEqual(a, b)
+        | Expr::GreaterThan(a, b)
+        | Expr::GreaterThanOrEqual(a, b)
+        | Expr::In(a, b)
+        | Expr::NotIn(a, b)
+        | Expr::And(a, b)
+        | Expr::Or(a, b)
+        | Expr::Range { start: a, end: b }
+        | Expr::InclusiveRange { start: a, end: b }
+        | Expr::Assign {
+            target: a,
+            value: b,
+            ..
+        }
+        | Expr::IndexAccess {
+            object: a,
+            index: b,
+        }
+        | Expr::WhileLoop {
+            condition: a,
+            body: b,
+        }
+        | Expr::ForLoop {
+            iterator_range: a,
+            body: b,
+            ..
+        }
+        | Expr::Try {
+            try_block: a,
+            catch_block: b,
+            ..
+        } => vec![a, b],
+
+        Expr::Call { callee, args } => std::iter::once(&**callee).chain(args).collect(),
+        Expr::PropertyAccess { object, .. } => vec![object],
+        Expr::Function { body, .. } | Expr::InfiniteLoop { body } => vec![body],
+        Expr::Block(exprs) | Expr::Array(exprs) | Expr::Class { methods: exprs, .. } => {
+            exprs.iter().collect()
+        }
+        Expr::Object { properties } => properties.values().collect(),
+        Expr::If(branches) => branches
+            .iter()
+            .flat_map(|(condition, block)| condition.iter().chain(std::iter::once(block)))
+            .collect(),
+
+        Expr::Int(_)
+        | Expr::Float(_)
+        | Expr::String(_)
+        | Expr::Identifier(_)
+        | Expr::Bool(_)
+        | Expr::Null
+        | Expr::Break
+        | Expr::Continue
+        | Expr::Import { .. } => Vec::new(),
+    }
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, rule: &'static str, span: Range<usize>, message: String) {
+        self.lints.push(Lint {
+            rule,
+            message,
+            help: None,
+            span,
+        });
+    }
+
+    fn help(&mut self, help: impl Into<String>) {
+        if let Some(lint) = self.lints.last_mut() {
+            lint.help = Some(help.into());
+        }
+    }
+
+    fn read(&self, definition: usize) -> bool {
+        self.analysis
+            .references
+            .iter()
+            .any(|r| r.definition == Some(definition) && !r.write)
+    }
+
+    // unused and shadowed things
+    fn definitions(&mut self, file: &SourceFile) {
+        let analysis = self.analysis;
+
+        for (i, definition) in analysis.definitions.iter().enumerate() {
+            if definition.name.starts_with('_') {
+                continue;
+            }
+
+            match definition.kind {
+                // globals can be used by whatever imports this file
+                SymbolKind::Variable | SymbolKind::Constant if !definition.global => {
+                    if !self.read(i) {
+                        self.warn(
+                            "unused_variable",
+                            definition.span.clone(),
+                            format!("`{}` is never read", definition.name),
+                        );
+                        self.help(format!(
+                            "remove it, or call it `_{}` if that's on purpose",
+                            definition.name
+                        ));
+                    }
+
+                    if let Some(shadowed) = self.shadowed(i) {
+                        self.warn(
+                            "shadowed_local",
+                            definition.span.clone(),
+                            format!(
+                                "`{}` hides the {} from line {}",
+                                definition.name,
+                                kind_name(shadowed.kind),
+                                file.line(shadowed.span.start)
+                            ),
+                        );
+                        self.help("give it another name so it's clear which one is meant");
+                    }
+                }
+
+                SymbolKind::Parameter if !self.read(i) => {
+                    self.warn(
+                        "unused_parameter",
+                        definition.span.clone(),
+                        format!("parameter `{}` is never used", definition.name),
+                    );
+                    self.help(format!(
+                        "call it `_{}` if it has to be there",
+                        definition.name
+                    ));
+                }
+
+                SymbolKind::Module if !self.read(i) => {
+                    self.warn(
+                        "unused_import",
+                        definition.span.clone(),
+                        format!("`{}` is imported but never used", definition.name),
+                    );
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    fn shadowed(&self, local: usize) -> Option<&Definition> {
+        let definition = &self.analysis.definitions[local];
+
+        self.analysis
+            .definitions
+            .iter()
+            .enumerate()
+            .find_map(|(i, other)| {
+                let hides = i != local
+                    && other.name == definition.name
+                    && other.kind != SymbolKind::Method
+                    && (other.global
+                        || other.container == definition.container
+                            && other.span.start < definition.span.start
+                            && other.visible.contains(&definition.span.start));
+
+                hides.then_some(other)
+            })
+    }
+
+    // assignments that make a global out of nothing, and names that look like typos
+    fn names(&mut self) {
+        let analysis = self.analysis;
+
+        // a star import can bring in anything
+        if analysis
+            .imports
+            .iter()
+            .any(|i| i.alias.as_deref() == Some("*"))
+        {
+            return;
+        }
+
+        let stdio = crate::Stdio::default();
+        let builtins: Vec<String> = crate::functions::get_functions(&stdio, &Default::default())
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+
+        let mut assigned = HashSet::new();
+
+        for reference in &analysis.references {
+            if reference.definition.is_some() || !reference.write {
+                continue;
+            }
+
+            // the first one creates it, the vm doesn't mind
+            if assigned.insert(reference.name.clone()) {
+                self.warn(
+                    "undefined_assignment",
+                    reference.span.clone(),
+                    format!("`{}` is assigned but was never declared", reference.name),
+                );
+                self.help(format!(
+                    "use `let {} = ...` to make a new variable",
+                    reference.name
+                ));
+            }
+        }
+
+        for reference in &analysis.references {
+            let name = &reference.name;
+
+            if reference.definition.is_some()
+                || reference.write
+                || name == "self"
+                || assigned.contains(name)
+                || builtins.contains(name)
+            {
+                continue;
+            }
+
+            let known = analysis
+                .visible_at(reference.span.start)
+                .into_iter()
+                .map(|d| &d.name)
+                .chain(&builtins)
+                .map(|known| (known, strsim::jaro_winkler(name, known)))
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((known, score)) = known
+                && score > 0.8
+            {
+                self.warn(
+                    "typo",
+                    reference.span.clone(),
+                    format!("`{}` is not defined", name),
+                );
+                self.help(format!("did you mean `{}`?", known));
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &SpannedExpr) {
+        match &expr.node {
+            Expr::Block(statements) => self.unreachable(statements),
+
+            Expr::If(branches) => {
+                for (condition, _) in branches {
+                    if let Some(condition) = condition.as_ref().filter(|c| constant(c)) {
+                        self.warn(
+                            "constant_condition",
+                            condition.span.into_range(),
+                            "this condition is always the same".to_string(),
+                        );
+                    }
+                }
+            }
+
+            Expr::WhileLoop { condition, .. } if constant(condition) => {
+                self.warn(
+                    "constant_condition",
+                    condition.span.into_range(),
+                    "this condition is always the same".to_string(),
+                );
+
+                if matches!(condition.node, Expr::Bool(true)) {
+                    self.help("use `loop { ... }` for a loop that only stops at break");
+                }
+            }
+
+            Expr::Call { callee, args } => self.arity(callee, args.len()),
+
+            _ => {}
+        }
+
+        for child in children(&expr.node) {
+            self.expr(child);
+        }
+    }
+
+    fn unreachable(&mut self, statements: &[SpannedExpr]) {
+        let Some(end) = statements
+            .iter()
+            .position(|s| matches!(s.node, Expr::Return(_) | Expr::Break | Expr::Continue))
+        else {
+            return;
+        };
+
+        if let (Some(first), Some(last)) = (statements.get(end + 1), statements.last()) {
+            let keyword = match statements[end].node {
+                Expr::Return(_) => "return",
+                Expr::Break => "break",
+                _ => "continue",
+            };
+
+            self.warn(
+                "unreachable_code",
+                first.span.start..last.span.end,
+                format!("this never runs, it comes after {}", keyword),
+            );
+        }
+    }
+
+    fn arity(&mut self, callee: &SpannedExpr, given: usize) {
+        let Expr::Identifier(name) = &callee.node else {
+            return;
+        };
+
+        let Some(definition) = self
+            .calls
+            .get(&callee.span.start)
+            .and_then(|&r| self.analysis.references[r].definition)
+        else {
+            return;
+        };
+
+        let target = &self.analysis.definitions[definition];
+
+        // defined again further down, so it depends on when the call runs
+        let redefined = self
+            .analysis
+            .definitions
+            .iter()
+            .filter(|d| d.global && d.name == target.name)
+            .count()
+            > 1;
+
+        if redefined {
+            return;
+        }
+
+        let (expected, what) = match target.kind {
+            SymbolKind::Function => (target.params.len(), format!("`{}`", name)),
+
+            SymbolKind::Class => {
+                let methods = self.analysis.methods(definition);
+
+                match methods.iter().find(|m| m.name == "init") {
+                    Some(init) => (init.params.len(), format!("`{}.init`", name)),
+
+                    // the init could be in a class from another file
+                    None if target.parent.is_some() => return,
+                    None => (0, format!("`{}`, which has no init method,", name)),
+                }
+            }
+
+            _ => return,
+        };
+
+        if expected != given {
+            self.warn(
+                "wrong_arity",
+                callee.span.into_range(),
+                format!(
+                    "{} takes {} argument{} but is given {}",
+                    what,
+                    expected,
+                    if expected == 1 { "" } else { "s" },
+                    given
+                ),
+            );
+        }
+    }
+}
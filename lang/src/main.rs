@@ -1,18 +1,7 @@
 use colored::Colorize;
 use std::panic::{AssertUnwindSafe, catch_unwind};
 
-mod ast;
-mod functions;
-mod lexer;
-mod parser;
-mod validator;
-
-mod cli;
-mod natives;
-mod stdlib;
-
-mod compiler;
-mod vm;
+use modu::cli;
 
 fn main() {
     //std::panic::set_hook(Box::new(|_| {}));
@@ -22,14 +11,41 @@ fn main() {
     if args.len() < 2 {
         println!(
             "Commands:
-    run       <file>   - Run a Modu file
+    run       <file>   - Run a Modu file (--max-depth <n> to change the call depth limit,
+                         --sandbox with --allow-read=<paths>, --allow-write=<paths>,
+                         --allow-net=<hosts> and --allow=<modules> to limit what it can do,
+                         --max-instructions <n>, --max-memory <MB> and --timeout <seconds> to stop it,
+                         --coverage[=<path>] to report what ran and write it as lcov,
+                         --profile[=<path>] to time every function and write folded stacks,
+                         --trace[=<path>] to log every instruction, --trace-fn=<names> and
+                         --trace-lines=[file:]<from>-<to> to only log some)
     repl               - Start the Modu REPL
+    debug     <file>   - Step through a Modu file with breakpoints (--no-break-on-error to
+                         not stop on errors nothing caught)
+    dap                - Start a Debug Adapter Protocol server on stdio for editors
+    lsp                - Start a language server on stdio for editors
+    fmt       [paths]  - Format Modu files in place (--check to only report unformatted files,
+                         --indent <n> for the number of spaces, [fmt] indent in project.toml)
+    lint      [paths]  - Warn about likely mistakes (--format json, --allow <rule> and
+                         --deny <rule>, rules can be set under [lint] in project.toml)
+    check     [paths]  - Find errors in Modu files and what they import without running them
+                         (--format json)
+    disasm    <file>   - Print the bytecode of a Modu file without running it, with jump
+                         targets, constants, local names and source lines (--format json)
+    ast       <file>   - Print the syntax tree of a Modu file without running it (--format json)
+    test      [filters] - Run the test_* functions in *_test.modu files, each on its own
+                         (std/test has assertions, // expect-error above a test that should fail)
+                         (--snapshot to compare scripts in tests/snapshots to their .snap files,
+                         --update to rewrite them, --coverage[=<path>] like run)
     help      <stdlib> - Show documentation for a standard library module
     init               - Initialize a new Modu package
     login              - Login with Modu Packages
     publish            - Publish a Modu package
     install   <name>   - Install a Modu package
-    uninstall <name>   - Uninstall a Modu package"
+    uninstall <name>   - Uninstall a Modu package
+
+Exit codes: 1 for an error the script didn't catch or a failed check, 2 for bad usage,
+3 for parse errors, 4 for compile errors, 70 if modu itself crashed, exit(n) picks n"
         );
         return;
     }
@@ -39,6 +55,15 @@ fn main() {
     let result = catch_unwind(AssertUnwindSafe(|| match action.as_str() {
         "run" => cli::run::run(),
         "repl" => cli::repl::repl(),
+        "debug" => cli::debug::debug(),
+        "dap" => cli::dap::dap(),
+        "lsp" => cli::lsp::lsp(),
+        "fmt" => cli::fmt::fmt(),
+        "lint" => cli::lint::lint(),
+        "check" => cli::check::check(),
+        "disasm" => cli::disasm::disasm(),
+        "ast" => cli::ast::ast(),
+        "test" => cli::test::test(),
         "help" => cli::help::help(),
         "login" => cli::login::login(),
         "init" => cli::init::init(),
@@ -47,13 +72,17 @@ fn main() {
         "uninstall" => cli::uninstall::uninstall(),
         "--version" | "-v" | "version" => {
             println!("Modu v{}", env!("CARGO_PKG_VERSION"));
+            Ok(())
         }
 
-        action => {
-            println!("Unknown command: {}", action);
-        }
+        action => Err(cli::CliError::Usage(format!("unknown command: {}", action))),
     }));
 
+    if let Ok(Err(e)) = &result {
+        e.report();
+        std::process::exit(e.exit_code());
+    }
+
     if let Err(panic) = result {
         let msg = panic
             .downcast_ref::<&str>()
@@ -77,5 +106,7 @@ fn main() {
         }
 
         eprintln!("  └─ Please report this issue at https://github.com/cyteon/modu/issues");
+
+        std::process::exit(cli::EXIT_INTERNAL);
     }
 }
@@ -1,30 +1,20 @@
 use crate::{
     ast::{AssignOp, Expr, SpannedExpr},
-    lexer::{Span, Token, lex},
+    error::{ErrorKind, ModuError},
+    lexer::{LexingError, Span, Token, lex_all},
+    vm::chunk::SourceFile,
 };
-use ariadne::{Color, Label, Report, ReportKind, Source};
 use chumsky::prelude::*;
 use std::collections::HashMap;
 
+type ParseExtra<'src> = extra::Err<Rich<'src, (Token, Span), Span>>;
+
 enum Postfix {
     Property(String, Span),
     Call(Vec<SpannedExpr>, Span),
     Index(SpannedExpr),
 }
 
-fn report_error(report: Report<'_, (&str, std::ops::Range<usize>)>, source_name: &str, code: &str) {
-    #[cfg(target_arch = "wasm32")]
-    {
-        let mut writer = crate::WasmWriter;
-        let _ = report.write((source_name, Source::from(code)), &mut writer);
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let _ = report.eprint((source_name, Source::from(code)));
-    }
-}
-
 fn parser<'src>() -> impl Parser<
     'src,
     &'src [(Token, Span)],
@@ -784,98 +774,171 @@ fn parser<'src>() -> impl Parser<
             expr_stmt,
             try_catch_stmt,
         ))
-        .boxed()
         .labelled("statement")
+        .recover_with(via_parser(skip_statement()))
+        .boxed()
+    });
+
+    // a } with no block to close would stop everything after it from being looked at
+    let stray_brace = select! { (Token::RBrace, span) => span }.validate(|span, _, emitter| {
+        emitter.emit(Rich::custom(span, "There is no block for this '}' to close"));
+
+        SpannedExpr {
+            node: Expr::Null,
+            span,
+        }
     });
 
-    stmt.repeated()
+    choice((stmt, stray_brace))
+        .repeated()
         .collect::<Vec<_>>()
         .then_ignore(end())
         .labelled("program")
 }
 
+// what's left of a statement that didn't parse, up to and including the next
+// `;` or a whole {...}, but never the } of the block it's in. it stands in
+// as null so the parser can go on and find more errors
+fn skip_statement<'src>()
+-> impl Parser<'src, &'src [(Token, Span)], SpannedExpr, ParseExtra<'src>> + Clone {
+    let braces = recursive(|braces| {
+        let inside = any()
+            .filter(|(token, _): &(Token, Span)| !matches!(token, Token::LBrace | Token::RBrace))
+            .ignored();
+
+        select! { (Token::LBrace, _) => () }
+            .then(choice((braces, inside)).repeated())
+            .then(select! { (Token::RBrace, _) => () }.or_not())
+            .ignored()
+    });
+
+    let plain = any()
+        .filter(|(token, _): &(Token, Span)| {
+            !matches!(token, Token::LBrace | Token::RBrace | Token::Semicolon)
+        })
+        .ignored();
+
+    let semicolon = select! { (Token::Semicolon, _) => () };
+
+    choice((
+        plain
+            .repeated()
+            .then(choice((
+                semicolon,
+                braces.then(semicolon.or_not()).ignored(),
+            )))
+            .ignored(),
+        plain.repeated().at_least(1).ignored(),
+    ))
+    .map_with(|_, e| SpannedExpr {
+        node: Expr::Null,
+        span: e.span(),
+    })
+}
+
 pub fn parse(input: &str, filename: &str) -> Result<Vec<SpannedExpr>, ()> {
-    let tokens = match lex(input) {
-        Ok(toks) => toks,
-        Err(e) => {
-            let report = Report::build(ReportKind::Error, (filename, e.1.into_range()))
-                .with_message(format!("Lexing error: {:?}", e.0))
-                .with_label(
-                    Label::new((filename, e.1.into_range()))
-                        .with_color(Color::Red)
-                        .with_message(format!("{}", e.0)),
-                )
-                .finish();
-
-            report_error(report, filename, input);
-
-            return Err(());
-        }
-    };
-
-    match parser().parse(&tokens).into_result() {
-        Ok(ast) => {
-            if let Err(()) = crate::validator::validate_ast(&ast, filename, input) {
-                Err(())
-            } else {
-                Ok(ast)
-            }
+    parse_source(input, filename).map_err(|errors| {
+        for err in errors {
+            eprint!("{}", err.render());
         }
+    })
+}
 
-        Err(e) => {
-            for err in e {
-                let span = err.span();
-
-                match err.reason() {
-                    chumsky::error::RichReason::ExpectedFound { expected, found } => {
-                        let (found_str, error_span) = match found {
-                            Some(chumsky::util::MaybeRef::Val((tok, tok_span))) => {
-                                (format!("{:?}", tok), *tok_span)
-                            }
-
-                            Some(chumsky::util::MaybeRef::Ref((tok, tok_span))) => {
-                                (format!("{:?}", tok), *tok_span)
-                            }
-
-                            None => (
-                                "end of input".to_string(),
-                                Span::from(input.len() - 1..input.len() - 1),
-                            ),
-                        };
-
-                        let report =
-                            Report::build(ReportKind::Error, (filename, error_span.into_range()))
-                                .with_message(format!(
-                                    "I expected {:?}, but found {}",
-                                    expected, found_str
-                                ))
-                                .with_label(
-                                    Label::new((filename, error_span.into_range()))
-                                        .with_color(Color::Red)
-                                        .with_message(format!("expected {:?}", expected)),
-                                )
-                                .finish();
-
-                        report_error(report, filename, input);
-                    }
+// like parse, but hands the errors back instead of printing them. the parser
+// picks up again after a statement it couldn't make sense of, so this is
+// every lexing, parse and validation error in the file, in order
+pub fn parse_source(input: &str, filename: &str) -> Result<Vec<SpannedExpr>, Vec<ModuError>> {
+    let file = SourceFile::new(filename, input);
+
+    let (mut tokens, lex_errors) = lex_all(input);
+    tokens.retain(|(token, _)| !matches!(token, Token::Comment | Token::MultiLineComment));
+
+    let mut errors: Vec<ModuError> = lex_errors
+        .iter()
+        .map(|(e, span)| {
+            let code = match e {
+                LexingError::UnexpectedToken => 1,
+                _ => 2,
+            };
+
+            ModuError::new(ErrorKind::Parse, format!("Lexing error: {:?}", e))
+                .with_label(format!("{}", e))
+                .with_code(code)
+                .at(span.into_range(), file.clone())
+        })
+        .collect();
+
+    // the parser trips right after a bad token, that doesn't need its own error
+    let after_bad: Vec<usize> = lex_errors
+        .iter()
+        .filter_map(|(_, bad)| tokens.iter().find(|(_, s)| s.start >= bad.end))
+        .map(|(_, s)| s.start)
+        .collect();
+
+    let (ast, parse_errors) = parser().parse(&tokens).into_output_errors();
+
+    errors.extend(
+        parse_errors
+            .into_iter()
+            .map(|err| parse_error(err, input, &file))
+            .filter(|e| !e.span.as_ref().is_some_and(|s| after_bad.contains(&s.start))),
+    );
+
+    if let Some(ast) = &ast
+        && let Err(invalid) = crate::validator::validate_ast(ast)
+    {
+        errors.extend(invalid.into_iter().map(|err| {
+            ModuError::new(ErrorKind::Parse, &err.message)
+                .with_label(&err.message)
+                .with_code(err.code)
+                .at(err.span.into_range(), file.clone())
+        }));
+    }
 
-                    _ => {
-                        let report =
-                            Report::build(ReportKind::Error, (filename, span.into_range()))
-                                .with_message(format!("{:?}", err.reason()))
-                                .with_label(
-                                    Label::new((filename, span.into_range()))
-                                        .with_color(Color::Red)
-                                        .with_message("error occurred here"),
-                                )
-                                .finish();
-
-                        report_error(report, filename, input);
-                    }
+    if errors.is_empty() {
+        return Ok(ast.unwrap_or_default());
+    }
+
+    errors.sort_by_key(|e| e.span.as_ref().map(|s| s.start));
+    Err(errors)
+}
+
+fn parse_error(
+    err: Rich<'_, (Token, Span), Span>,
+    input: &str,
+    file: &std::sync::Arc<SourceFile>,
+) -> ModuError {
+    match err.reason() {
+        chumsky::error::RichReason::ExpectedFound { expected, found } => {
+            let (found_str, error_span) = match found {
+                Some(chumsky::util::MaybeRef::Val((tok, tok_span))) => {
+                    (format!("{:?}", tok), *tok_span)
                 }
-            }
 
-            Err(())
+                Some(chumsky::util::MaybeRef::Ref((tok, tok_span))) => {
+                    (format!("{:?}", tok), *tok_span)
+                }
+
+                None => (
+                    "end of input".to_string(),
+                    Span::from(input.len().saturating_sub(1)..input.len().saturating_sub(1)),
+                ),
+            };
+
+            ModuError::new(
+                ErrorKind::Parse,
+                format!("I expected {:?}, but found {}", expected, found_str),
+            )
+            .with_label(format!("expected {:?}", expected))
+            .with_code(3)
+            .at(error_span.into_range(), file.clone())
+        }
+
+        chumsky::error::RichReason::Custom(message) => {
+            ModuError::new(ErrorKind::Parse, message)
+                .with_label(message)
+                .with_code(3)
+                .at(err.span().into_range(), file.clone())
         }
     }
 }
@@ -0,0 +1,219 @@
+use std::path::{Component, Path, PathBuf};
+
+// what a script is allowed to touch. the default allows everything,
+// a sandbox starts with nothing and gets capabilities granted one by one
+//
+//   let permissions = Permissions::sandbox()
+//       .allow_read("./data")
+//       .allow_net("localhost");
+#[derive(Debug, Clone)]
+pub struct Permissions {
+    sandboxed: bool,
+    modules: Vec<String>,
+    read: Grant<PathBuf>,
+    write: Grant<PathBuf>,
+    net: Grant<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Grant<T> {
+    Any,
+    Only(Vec<T>),
+}
+
+// modules that can do anything, so they are all or nothing
+const UNCHECKED_MODULES: [&str; 2] = ["os", "ffi"];
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            sandboxed: false,
+            modules: Vec::new(),
+            read: Grant::Any,
+            write: Grant::Any,
+            net: Grant::Any,
+        }
+    }
+}
+
+impl Permissions {
+    pub fn sandbox() -> Self {
+        Self {
+            sandboxed: true,
+            modules: Vec::new(),
+            read: Grant::Only(Vec::new()),
+            write: Grant::Only(Vec::new()),
+            net: Grant::Only(Vec::new()),
+        }
+    }
+
+    pub fn is_sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    // grants a whole stdlib module, like "os" or "fs", or "native" for extensions
+    pub fn allow_module(mut self, name: &str) -> Self {
+        match name {
+            "fs" => {
+                self.read = Grant::Any;
+                self.write = Grant::Any;
+            }
+
+            "http" => self.net = Grant::Any,
+
+            _ => self.modules.push(name.to_string()),
+        }
+
+        self
+    }
+
+    // a directory grants everything inside it
+    pub fn allow_read(mut self, path: impl AsRef<Path>) -> Self {
+        if let Grant::Only(paths) = &mut self.read {
+            paths.push(resolve(path.as_ref()));
+        }
+
+        self
+    }
+
+    pub fn allow_any_read(mut self) -> Self {
+        self.read = Grant::Any;
+        self
+    }
+
+    pub fn allow_write(mut self, path: impl AsRef<Path>) -> Self {
+        if let Grant::Only(paths) = &mut self.write {
+            paths.push(resolve(path.as_ref()));
+        }
+
+        self
+    }
+
+    pub fn allow_any_write(mut self) -> Self {
+        self.write = Grant::Any;
+        self
+    }
+
+    // "example.com" allows any port, "localhost:8080" only that one
+    pub fn allow_net(mut self, host: &str) -> Self {
+        if let Grant::Only(hosts) = &mut self.net {
+            hosts.push(host.to_lowercase());
+        }
+
+        self
+    }
+
+    pub fn allow_any_net(mut self) -> Self {
+        self.net = Grant::Any;
+        self
+    }
+
+    pub fn check_module(&self, name: &str) -> Result<(), String> {
+        if !self.sandboxed
+            || !UNCHECKED_MODULES.contains(&name)
+            || self.modules.iter().any(|m| m == name)
+        {
+            return Ok(());
+        }
+
+        Err(format!(
+            "permission denied: std/{} is not available in the sandbox (allow it with --allow={})",
+            name, name
+        ))
+    }
+
+    // native extensions can do anything, same as ffi
+    pub fn check_native(&self) -> Result<(), String> {
+        if !self.sandboxed || self.modules.iter().any(|m| m == "native") {
+            return Ok(());
+        }
+
+        Err(
+            "permission denied: native extensions are not available in the sandbox (allow them with --allow=native)"
+                .to_string(),
+        )
+    }
+
+    pub fn check_read(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        check_path(&self.read, path.as_ref(), "read", "--allow-read")
+    }
+
+    pub fn check_write(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        check_path(&self.write, path.as_ref(), "write", "--allow-write")
+    }
+
+    pub fn check_net(&self, host: &str, port: Option<u16>) -> Result<(), String> {
+        let hosts = match &self.net {
+            Grant::Any => return Ok(()),
+            Grant::Only(hosts) => hosts,
+        };
+
+        let host = host.to_lowercase();
+        let with_port = port.map(|port| format!("{}:{}", host, port));
+
+        if hosts
+            .iter()
+            .any(|h| *h == host || Some(h) == with_port.as_ref())
+        {
+            return Ok(());
+        }
+
+        Err(format!(
+            "permission denied: network access to '{}' is not allowed (allow it with --allow-net={})",
+            with_port.unwrap_or_else(|| host.clone()),
+            host
+        ))
+    }
+}
+
+fn check_path(grant: &Grant<PathBuf>, path: &Path, action: &str, flag: &str) -> Result<(), String> {
+    let paths = match grant {
+        Grant::Any => return Ok(()),
+        Grant::Only(paths) => paths,
+    };
+
+    let resolved = resolve(path);
+
+    // an unresolved .. could still climb out of an allowed directory
+    let escapes = resolved.components().any(|c| c == Component::ParentDir);
+
+    if !escapes && paths.iter().any(|allowed| resolved.starts_with(allowed)) {
+        return Ok(());
+    }
+
+    Err(format!(
+        "permission denied: cannot {} '{}' (allow it with {}={})",
+        action,
+        path.display(),
+        flag,
+        path.display()
+    ))
+}
+
+// absolute path with symlinks followed, the part that doesnt exist yet
+// (like a file about to be created) is appended as is
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest.iter().rev().fold(canonical, |p, c| p.join(c));
+        }
+
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+
+            _ => return absolute,
+        }
+    }
+}
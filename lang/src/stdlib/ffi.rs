@@ -1,21 +1,23 @@
-use crate::vm::value::{BuiltinFn, Value};
+use super::Context;
+use crate::vm::value::{BuiltinFn, NativeObject, Value};
 use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+fn bind(
+    name: &str,
+    context: &Context,
+    func: fn(&Context, Vec<Value>) -> Result<Value, String>,
+) -> Value {
+    let context = context.clone();
+    Value::BuiltinFn(BuiltinFn::new(name, move |args| func(&context, args)))
+}
 
-pub fn object() -> Value {
+pub fn object(context: Context) -> Value {
     let mut methods = HashMap::new();
 
-    methods.insert(
-        "load".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("load", load)),
-    );
-    methods.insert(
-        "define".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("define", define)),
-    );
-    methods.insert(
-        "unload".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("unload", unload)),
-    );
+    methods.insert("load".to_string(), bind("load", &context, load));
+    methods.insert("define".to_string(), bind("define", &context, define));
+    methods.insert("unload".to_string(), bind("unload", &context, unload));
 
     methods.insert(
         "suffix".to_string(),
@@ -37,6 +39,7 @@ pub fn object() -> Value {
 }
 
 pub struct FFILib {
+    pub path: String,
     pub lib: libloading::Library,
     pub funcs: HashMap<String, FFISig>,
 }
@@ -46,11 +49,41 @@ pub struct FFISig {
     pub ret_type: String,
 }
 
-thread_local! {
-    pub static LIBS: std::cell::RefCell<Vec<Option<FFILib>>> = const { std::cell::RefCell::new(Vec::new()) };
+// None once unloaded, the library is also unloaded when the handle is dropped
+type LibHandle = Mutex<Option<FFILib>>;
+
+fn lock(handle: &LibHandle) -> MutexGuard<'_, Option<FFILib>> {
+    handle.lock().unwrap_or_else(|e| e.into_inner())
 }
 
-fn load(args: Vec<Value>) -> Result<Value, String> {
+fn lib_handle<'a>(
+    context: &Context,
+    func: &str,
+    value: &'a Value,
+) -> Result<&'a LibHandle, String> {
+    let (object, handle) = match value {
+        Value::Native(object) => object.downcast_ref::<LibHandle>().map(|h| (object, h)),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        format!(
+            "ffi.{}() first argument must be a library handle, got {}",
+            func,
+            value.type_name()
+        )
+    })?;
+
+    if !object.is_usable_by(context.owner) {
+        return Err(format!(
+            "ffi.{}() library belongs to another interpreter",
+            func
+        ));
+    }
+
+    Ok(handle)
+}
+
+fn load(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "ffi.load() takes exactly one argument ({} given)",
@@ -73,19 +106,33 @@ fn load(args: Vec<Value>) -> Result<Value, String> {
             .map_err(|e| format!("ffi.load() failed to load library: {}", e))?
     };
 
-    let idx = LIBS.with(|libs| {
-        let mut libs = libs.borrow_mut();
-        libs.push(Some(FFILib {
-            lib,
-            funcs: HashMap::new(),
-        }));
-        libs.len() - 1
-    });
-
-    Ok(Value::FFILib(idx))
+    let handle: LibHandle = Mutex::new(Some(FFILib {
+        path: path.clone(),
+        lib,
+        funcs: HashMap::new(),
+    }));
+
+    // calls through lib.add() are checked by the vm, it knows about owners
+    let object = NativeObject::new("ffi_lib", handle)
+        .with_owner(context.owner)
+        .with_display(|handle: &LibHandle| match &*lock(handle) {
+            Some(lib) => format!("<ffi lib {}>", lib.path),
+            None => "<unloaded ffi lib>".to_string(),
+        })
+        // lib.add(1, 2) calls the function defined as "add"
+        .with_getter(|object, name| {
+            let object = object.clone();
+            let func_name = name.to_string();
+
+            Some(Value::BuiltinFn(BuiltinFn::new(name, move |args| {
+                call_ffi(&object, &func_name, args)
+            })))
+        });
+
+    Ok(Value::Native(object))
 }
 
-fn define(args: Vec<Value>) -> Result<Value, String> {
+fn define(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 4 {
         return Err(format!(
             "ffi.define() takes exactly four arguments ({} given)",
@@ -93,15 +140,7 @@ fn define(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let idx = match &args[0] {
-        Value::FFILib(i) => *i,
-        _ => {
-            return Err(format!(
-                "ffi.define() first argument must be a library handle, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
+    let handle = lib_handle(context, "define", &args[0])?;
 
     let name = match &args[1] {
         Value::String(s) => s,
@@ -150,30 +189,28 @@ fn define(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    LIBS.with(|libs| {
-        let mut libs = libs.borrow_mut();
-        let lib = libs[idx]
-            .as_mut()
-            .ok_or_else(|| format!("invalid library handle: {}", idx))?;
+    let mut handle = lock(handle);
+    let lib = handle
+        .as_mut()
+        .ok_or_else(|| "ffi.define() library has been unloaded".to_string())?;
 
-        unsafe {
-            lib.lib
-                .get::<unsafe extern "C" fn()>(name.as_bytes())
-                .map_err(|e| format!("ffi.define() failed to find symbol '{}': {}", name, e))?;
-        }
+    unsafe {
+        lib.lib
+            .get::<unsafe extern "C" fn()>(name.as_bytes())
+            .map_err(|e| format!("ffi.define() failed to find symbol '{}': {}", name, e))?;
+    }
 
-        lib.funcs.insert(
-            name.clone(),
-            FFISig {
-                arg_types,
-                ret_type,
-            },
-        );
-        Ok(Value::Null)
-    })
+    lib.funcs.insert(
+        name.clone(),
+        FFISig {
+            arg_types,
+            ret_type,
+        },
+    );
+    Ok(Value::Null)
 }
 
-fn unload(args: Vec<Value>) -> Result<Value, String> {
+fn unload(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "ffi.unload() takes exactly one argument ({} given)",
@@ -181,25 +218,10 @@ fn unload(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let lib_index = match &args[0] {
-        Value::FFILib(i) => *i,
-        _ => {
-            return Err(format!(
-                "ffi.unload() argument must be a library handle, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
-
-    LIBS.with(|libs| {
-        let mut libs = libs.borrow_mut();
-        if lib_index >= libs.len() {
-            return Err(format!("invalid library handle: {}", lib_index));
-        }
+    let handle = lib_handle(context, "unload", &args[0])?;
+    lock(handle).take();
 
-        libs[lib_index] = None;
-        Ok(Value::Null)
-    })
+    Ok(Value::Null)
 }
 
 fn validate_type(t: &str) -> Result<(), String> {
@@ -212,86 +234,85 @@ fn validate_type(t: &str) -> Result<(), String> {
     }
 }
 
-pub fn call_ffi(idx: usize, name: &str, args: Vec<Value>) -> Result<Value, String> {
-    LIBS.with(|libs| {
-        let libs = libs.borrow();
-        let lib = libs
-            .get(idx)
-            .ok_or_else(|| format!("invalid library handle: {}", idx))?
-            .as_ref()
-            .ok_or_else(|| format!("library at index {} has been unloaded", idx))?;
-
-        let sig = lib
-            .funcs
-            .get(name)
-            .ok_or_else(|| format!("function '{}' is not defined in library {}", name, idx))?;
-
-        if args.len() != sig.arg_types.len() {
-            return Err(format!(
-                "function '{}' expects {} arguments, got {}",
-                name,
-                sig.arg_types.len(),
-                args.len()
-            ));
-        }
+fn call_ffi(object: &NativeObject, name: &str, args: Vec<Value>) -> Result<Value, String> {
+    let handle = lock(
+        object
+            .downcast_ref::<LibHandle>()
+            .ok_or_else(|| "invalid library handle".to_string())?,
+    );
 
-        let ffi_args: Vec<FFIArg> = args
-            .iter()
-            .zip(&sig.arg_types)
-            .map(|(v, t)| to_ffi_arg(v, t))
-            .collect::<Result<_, _>>()?;
+    let lib = handle
+        .as_ref()
+        .ok_or_else(|| "library has been unloaded".to_string())?;
 
-        let ffi_arg_types: Vec<libffi::middle::Type> = sig
-            .arg_types
-            .iter()
-            .map(|t| modu_to_ffi_type(t))
-            .collect::<Result<_, _>>()?;
-
-        let ffi_ret_type = modu_to_ffi_type(&sig.ret_type)?;
-        let cif = libffi::middle::Cif::new(ffi_arg_types, ffi_ret_type);
-
-        let func_ptr = unsafe {
-            lib.lib
-                .get::<unsafe extern "C" fn()>(name.as_bytes())
-                .map_err(|e| format!("failed to get symbol '{}': {}", name, e))?
-        };
-
-        let code_ptr = libffi::middle::CodePtr::from_fun(*func_ptr);
-
-        let c_args: Vec<libffi::middle::Arg> = ffi_args.iter().map(|arg| arg.as_arg()).collect();
-
-        unsafe {
-            match sig.ret_type.as_str() {
-                "i64" => Ok(Value::Int(cif.call::<i64>(code_ptr, &c_args))),
-                "i32" => Ok(Value::Int(cif.call::<i32>(code_ptr, &c_args) as i64)),
-                "f64" => Ok(Value::Float(cif.call::<f64>(code_ptr, &c_args))),
-                "f32" => Ok(Value::Float(cif.call::<f32>(code_ptr, &c_args) as f64)),
-                "bool" => Ok(Value::Bool(cif.call::<i32>(code_ptr, &c_args) != 0)),
-
-                "string" => {
-                    let ptr = cif.call::<*const std::os::raw::c_char>(code_ptr, &c_args);
-                    if ptr.is_null() {
-                        Ok(Value::Null)
-                    } else {
-                        let c_str = std::ffi::CStr::from_ptr(ptr);
-                        c_str
-                            .to_str()
-                            .map(|s| Value::String(s.to_string()))
-                            .map_err(|e| {
-                                format!("failed to convert C string to Rust string: {}", e)
-                            })
-                    }
-                }
+    let sig = lib
+        .funcs
+        .get(name)
+        .ok_or_else(|| format!("function '{}' is not defined in library {}", name, lib.path))?;
 
-                "void" => {
-                    cif.call::<()>(code_ptr, &c_args);
+    if args.len() != sig.arg_types.len() {
+        return Err(format!(
+            "function '{}' expects {} arguments, got {}",
+            name,
+            sig.arg_types.len(),
+            args.len()
+        ));
+    }
+
+    let ffi_args: Vec<FFIArg> = args
+        .iter()
+        .zip(&sig.arg_types)
+        .map(|(v, t)| to_ffi_arg(v, t))
+        .collect::<Result<_, _>>()?;
+
+    let ffi_arg_types: Vec<libffi::middle::Type> = sig
+        .arg_types
+        .iter()
+        .map(|t| modu_to_ffi_type(t))
+        .collect::<Result<_, _>>()?;
+
+    let ffi_ret_type = modu_to_ffi_type(&sig.ret_type)?;
+    let cif = libffi::middle::Cif::new(ffi_arg_types, ffi_ret_type);
+
+    let func_ptr = unsafe {
+        lib.lib
+            .get::<unsafe extern "C" fn()>(name.as_bytes())
+            .map_err(|e| format!("failed to get symbol '{}': {}", name, e))?
+    };
+
+    let code_ptr = libffi::middle::CodePtr::from_fun(*func_ptr);
+
+    let c_args: Vec<libffi::middle::Arg> = ffi_args.iter().map(|arg| arg.as_arg()).collect();
+
+    unsafe {
+        match sig.ret_type.as_str() {
+            "i64" => Ok(Value::Int(cif.call::<i64>(code_ptr, &c_args))),
+            "i32" => Ok(Value::Int(cif.call::<i32>(code_ptr, &c_args) as i64)),
+            "f64" => Ok(Value::Float(cif.call::<f64>(code_ptr, &c_args))),
+            "f32" => Ok(Value::Float(cif.call::<f32>(code_ptr, &c_args) as f64)),
+            "bool" => Ok(Value::Bool(cif.call::<i32>(code_ptr, &c_args) != 0)),
+
+            "string" => {
+                let ptr = cif.call::<*const std::os::raw::c_char>(code_ptr, &c_args);
+                if ptr.is_null() {
                     Ok(Value::Null)
+                } else {
+                    let c_str = std::ffi::CStr::from_ptr(ptr);
+                    c_str
+                        .to_str()
+                        .map(|s| Value::String(s.to_string()))
+                        .map_err(|e| format!("failed to convert C string to Rust string: {}", e))
                 }
+            }
 
-                _ => Err(format!("unsupported return type '{}'", sig.ret_type)),
+            "void" => {
+                cif.call::<()>(code_ptr, &c_args);
+                Ok(Value::Null)
             }
+
+            _ => Err(format!("unsupported return type '{}'", sig.ret_type)),
         }
-    })
+    }
 }
 
 fn modu_to_ffi_type(t: &str) -> Result<libffi::middle::Type, String> {
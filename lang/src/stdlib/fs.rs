@@ -1,51 +1,66 @@
-use crate::vm::value::{BuiltinFn, Value};
+use super::Context;
+use crate::vm::value::{BuiltinFn, NativeObject, Value};
 use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+fn bind(
+    name: &str,
+    context: &Context,
+    func: fn(&Context, Vec<Value>) -> Result<Value, String>,
+) -> Value {
+    let context = context.clone();
+    Value::BuiltinFn(BuiltinFn::new(name, move |args| func(&context, args)))
+}
 
-pub fn object() -> Value {
+pub fn object(context: Context) -> Value {
     let mut methods = HashMap::new();
 
-    methods.insert(
-        "open".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("open", open)),
-    );
-    methods.insert(
-        "read".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("read", read)),
-    );
-    methods.insert(
-        "write".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("write", write)),
-    );
-    methods.insert(
-        "stat".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("stat", stat)),
-    );
-    methods.insert(
-        "close".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("close", close)),
-    );
-    methods.insert(
-        "exists".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("exists", exists)),
-    );
-    methods.insert(
-        "mkdir".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("mkdir", mkdir)),
-    );
-    methods.insert(
-        "rmdir".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("rmdir", rmdir)),
-    );
-    methods.insert(
-        "remove".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("remove", remove)),
-    );
+    methods.insert("open".to_string(), bind("open", &context, open));
+    methods.insert("read".to_string(), bind("read", &context, read));
+    methods.insert("write".to_string(), bind("write", &context, write));
+    methods.insert("stat".to_string(), bind("stat", &context, stat));
+    methods.insert("close".to_string(), bind("close", &context, close));
+    methods.insert("exists".to_string(), bind("exists", &context, exists));
+    methods.insert("mkdir".to_string(), bind("mkdir", &context, mkdir));
+    methods.insert("rmdir".to_string(), bind("rmdir", &context, rmdir));
+    methods.insert("remove".to_string(), bind("remove", &context, remove));
 
     Value::Object(methods)
 }
 
-thread_local! {
-    static FILES: std::cell::RefCell<Vec<Option<(std::fs::File, FileMode)>>> = const { std::cell::RefCell::new(Vec::new()) };
+// the file gets closed when the handle is closed or no longer referenced
+struct FileHandle {
+    path: String,
+    file: Mutex<Option<(std::fs::File, FileMode)>>,
+}
+
+impl FileHandle {
+    fn file(&self) -> MutexGuard<'_, Option<(std::fs::File, FileMode)>> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn handle<'a>(context: &Context, func: &str, value: &'a Value) -> Result<&'a FileHandle, String> {
+    let (object, handle) = match value {
+        Value::Native(object) => object.downcast_ref::<FileHandle>().map(|h| (object, h)),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        format!(
+            "fs.{}() argument must be a file, got {}",
+            func,
+            value.type_name()
+        )
+    })?;
+
+    if !object.is_usable_by(context.owner) {
+        return Err(format!(
+            "fs.{}() file '{}' belongs to another interpreter",
+            func, handle.path
+        ));
+    }
+
+    Ok(handle)
 }
 
 enum FileMode {
@@ -55,7 +70,7 @@ enum FileMode {
     ReadWrite,
 }
 
-fn open(args: Vec<Value>) -> Result<Value, String> {
+fn open(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err(format!(
             "fs.open() takes exactly two arguments ({} given)",
@@ -83,6 +98,16 @@ fn open(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
+    match mode.as_str() {
+        "r" => context.permissions.check_read(path)?,
+        "w" | "a" => context.permissions.check_write(path)?,
+        "rw" => {
+            context.permissions.check_read(path)?;
+            context.permissions.check_write(path)?;
+        }
+        _ => {}
+    }
+
     let (file, mode) = match mode.as_str() {
         "r" => (
             std::fs::File::open(path)
@@ -123,16 +148,24 @@ fn open(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let idx = FILES.with(|files| {
-        let mut files = files.borrow_mut();
-        files.push(Some((file, mode)));
-        files.len() - 1
-    });
+    let handle = FileHandle {
+        path: path.clone(),
+        file: Mutex::new(Some((file, mode))),
+    };
 
-    Ok(Value::Int(idx as i64))
+    Ok(Value::Native(
+        NativeObject::new("file", handle)
+            .with_owner(context.owner)
+            .with_display(|handle: &FileHandle| match &*handle.file() {
+                Some(_) => format!("<file {}>", handle.path),
+                None => format!("<closed file {}>", handle.path),
+            }),
+    ))
 }
 
-fn read(args: Vec<Value>) -> Result<Value, String> {
+fn read(context: &Context, args: Vec<Value>) -> Result<Value, String> {
+    use std::io::Seek;
+
     if args.len() != 1 {
         return Err(format!(
             "fs.read() takes exactly one argument ({} given)",
@@ -140,46 +173,29 @@ fn read(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let idx = match &args[0] {
-        Value::Int(i) => *i as usize,
-        _ => {
-            return Err(format!(
-                "fs.read() argument must be an integer file index, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
+    let handle = handle(context, "read", &args[0])?;
+    let path = &handle.path;
 
-    FILES.with(|files| {
-        use std::io::Seek;
+    let mut entry = handle.file();
+    let (file, mode) = entry
+        .as_mut()
+        .ok_or_else(|| format!("fs.read() file '{}' is closed", path))?;
 
-        let mut files = files.borrow_mut();
-        let file_entry = files
-            .get_mut(idx)
-            .ok_or_else(|| format!("fs.read() invalid file index: {}", idx))?;
-        let (file, mode) = file_entry
-            .as_mut()
-            .ok_or_else(|| format!("fs.read() file at index {} is closed", idx))?;
-
-        if !matches!(mode, FileMode::Read | FileMode::ReadWrite) {
-            return Err(format!(
-                "fs.read() file at index {} is not open for reading",
-                idx
-            ));
-        }
+    if !matches!(mode, FileMode::Read | FileMode::ReadWrite) {
+        return Err(format!("fs.read() file '{}' is not open for reading", path));
+    }
 
-        file.seek(std::io::SeekFrom::Start(0))
-            .map_err(|e| format!("failed to seek in file at index {}: {}", idx, e))?;
+    file.seek(std::io::SeekFrom::Start(0))
+        .map_err(|e| format!("failed to seek in file '{}': {}", path, e))?;
 
-        let mut content = String::new();
-        std::io::Read::read_to_string(file, &mut content)
-            .map_err(|e| format!("failed to read from file at index {}: {}", idx, e))?;
+    let mut content = String::new();
+    std::io::Read::read_to_string(file, &mut content)
+        .map_err(|e| format!("failed to read from file '{}': {}", path, e))?;
 
-        Ok(Value::String(content))
-    })
+    Ok(Value::String(content))
 }
 
-fn write(args: Vec<Value>) -> Result<Value, String> {
+fn write(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 2 {
         return Err(format!(
             "fs.write() takes exactly two arguments ({} given)",
@@ -187,15 +203,8 @@ fn write(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let idx = match &args[0] {
-        Value::Int(i) => *i as usize,
-        _ => {
-            return Err(format!(
-                "fs.write() first argument must be an integer file index, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
+    let handle = handle(context, "write", &args[0])?;
+    let path = &handle.path;
 
     let content = match &args[1] {
         Value::String(s) => s,
@@ -207,37 +216,32 @@ fn write(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    FILES.with(|files| {
-        let mut files = files.borrow_mut();
-        let file_entry = files
-            .get_mut(idx)
-            .ok_or_else(|| format!("fs.write() invalid file index: {}", idx))?;
-        let (file, mode) = file_entry
-            .as_mut()
-            .ok_or_else(|| format!("fs.write() file at index {} is closed", idx))?;
-
-        if !matches!(
-            mode,
-            FileMode::Write | FileMode::Append | FileMode::ReadWrite
-        ) {
-            return Err(format!(
-                "fs.write() file at index {} is not open for writing",
-                idx
-            ));
-        }
+    let mut entry = handle.file();
+    let (file, mode) = entry
+        .as_mut()
+        .ok_or_else(|| format!("fs.write() file '{}' is closed", path))?;
 
-        if matches!(mode, FileMode::Write | FileMode::ReadWrite) {
-            file.set_len(0)
-                .map_err(|e| format!("failed to truncate file at index {}: {}", idx, e))?;
-        }
+    if !matches!(
+        mode,
+        FileMode::Write | FileMode::Append | FileMode::ReadWrite
+    ) {
+        return Err(format!(
+            "fs.write() file '{}' is not open for writing",
+            path
+        ));
+    }
+
+    if matches!(mode, FileMode::Write | FileMode::ReadWrite) {
+        file.set_len(0)
+            .map_err(|e| format!("failed to truncate file '{}': {}", path, e))?;
+    }
 
-        std::io::Write::write_all(file, content.as_bytes())
-            .map_err(|e| format!("failed to write to file at index {}: {}", idx, e))?;
-        Ok(Value::Null)
-    })
+    std::io::Write::write_all(file, content.as_bytes())
+        .map_err(|e| format!("failed to write to file '{}': {}", path, e))?;
+    Ok(Value::Null)
 }
 
-fn stat(args: Vec<Value>) -> Result<Value, String> {
+fn stat(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.stat() takes exactly one argument ({} given)",
@@ -245,41 +249,29 @@ fn stat(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let idx = match &args[0] {
-        Value::Int(i) => *i as usize,
-        _ => {
-            return Err(format!(
-                "fs.stat() argument must be an integer file index, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
-
-    FILES.with(|files| {
-        let mut files = files.borrow_mut();
-        let file_entry = files
-            .get_mut(idx)
-            .ok_or_else(|| format!("fs.stat() invalid file index: {}", idx))?;
-        let (file, _) = file_entry
-            .as_mut()
-            .ok_or_else(|| format!("fs.stat() file at index {} is closed", idx))?;
-
-        let metadata = file
-            .metadata()
-            .map_err(|e| format!("failed to get metadata for file at index {}: {}", idx, e))?;
-        Ok(Value::Object(
-            vec![
-                ("size".to_string(), Value::Int(metadata.len() as i64)),
-                ("is_file".to_string(), Value::Bool(metadata.is_file())),
-                ("is_dir".to_string(), Value::Bool(metadata.is_dir())),
-            ]
-            .into_iter()
-            .collect(),
-        ))
-    })
+    let handle = handle(context, "stat", &args[0])?;
+    let path = &handle.path;
+
+    let entry = handle.file();
+    let (file, _) = entry
+        .as_ref()
+        .ok_or_else(|| format!("fs.stat() file '{}' is closed", path))?;
+
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("failed to get metadata for file '{}': {}", path, e))?;
+    Ok(Value::Object(
+        vec![
+            ("size".to_string(), Value::Int(metadata.len() as i64)),
+            ("is_file".to_string(), Value::Bool(metadata.is_file())),
+            ("is_dir".to_string(), Value::Bool(metadata.is_dir())),
+        ]
+        .into_iter()
+        .collect(),
+    ))
 }
 
-fn close(args: Vec<Value>) -> Result<Value, String> {
+fn close(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.close() takes exactly one argument ({} given)",
@@ -287,27 +279,13 @@ fn close(args: Vec<Value>) -> Result<Value, String> {
         ));
     }
 
-    let idx = match &args[0] {
-        Value::Int(i) => *i as usize,
-        _ => {
-            return Err(format!(
-                "fs.close() argument must be an integer file index, got {}",
-                args[0].type_name()
-            ));
-        }
-    };
+    let handle = handle(context, "close", &args[0])?;
+    handle.file().take();
 
-    FILES.with(|files| {
-        let mut files = files.borrow_mut();
-        let file_entry = files
-            .get_mut(idx)
-            .ok_or_else(|| format!("fs.close() invalid file index: {}", idx))?;
-        *file_entry = None;
-        Ok(Value::Null)
-    })
+    Ok(Value::Null)
 }
 
-fn exists(args: Vec<Value>) -> Result<Value, String> {
+fn exists(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.exists() takes exactly one argument ({} given)",
@@ -325,10 +303,12 @@ fn exists(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
+    context.permissions.check_read(path)?;
+
     Ok(Value::Bool(std::path::Path::new(path).exists()))
 }
 
-fn mkdir(args: Vec<Value>) -> Result<Value, String> {
+fn mkdir(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.mkdir() takes exactly one argument ({} given)",
@@ -346,12 +326,14 @@ fn mkdir(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
+    context.permissions.check_write(path)?;
+
     std::fs::create_dir(path)
         .map_err(|e| format!("failed to create directory '{}': {}", path, e))?;
     Ok(Value::Null)
 }
 
-fn rmdir(args: Vec<Value>) -> Result<Value, String> {
+fn rmdir(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.rmdir() takes exactly one argument ({} given)",
@@ -369,12 +351,14 @@ fn rmdir(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
+    context.permissions.check_write(path)?;
+
     std::fs::remove_dir(path)
         .map_err(|e| format!("failed to remove directory '{}': {}", path, e))?;
     Ok(Value::Null)
 }
 
-fn remove(args: Vec<Value>) -> Result<Value, String> {
+fn remove(context: &Context, args: Vec<Value>) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
             "fs.remove() takes exactly one argument ({} given)",
@@ -392,6 +376,8 @@ fn remove(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
+    context.permissions.check_write(path)?;
+
     std::fs::remove_file(path).map_err(|e| format!("failed to remove file '{}': {}", path, e))?;
     Ok(Value::Null)
 }
@@ -1,54 +1,83 @@
+use super::Context;
+use crate::permissions::Permissions;
+use crate::vm::convert::to_value;
 use crate::vm::value::{BuiltinFn, Value};
 use std::collections::HashMap;
 
-pub fn object() -> Value {
+fn method(
+    name: &str,
+    context: &Context,
+    func: fn(&Permissions, Vec<Value>) -> Result<Value, String>,
+) -> Value {
+    let context = context.clone();
+    Value::BuiltinFn(BuiltinFn::new(name, move |args| {
+        func(&context.permissions, args)
+    }))
+}
+
+pub fn object(context: Context) -> Value {
     let mut methods = HashMap::new();
 
-    methods.insert(
-        "get".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("get", get)),
-    );
-    methods.insert(
-        "post".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("post", post)),
-    );
-    methods.insert(
-        "put".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("put", put)),
-    );
-    methods.insert(
-        "patch".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("patch", patch)),
-    );
-    methods.insert(
-        "delete".to_string(),
-        Value::BuiltinFn(BuiltinFn::new("delete", delete)),
-    );
+    methods.insert("get".to_string(), method("get", &context, get));
+    methods.insert("post".to_string(), method("post", &context, post));
+    methods.insert("put".to_string(), method("put", &context, put));
+    methods.insert("patch".to_string(), method("patch", &context, patch));
+    methods.insert("delete".to_string(), method("delete", &context, delete));
 
     Value::Object(methods)
 }
 
+#[derive(serde::Serialize)]
+struct Response {
+    status: u16,
+    status_text: String,
+    ok: bool,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+fn check_url(permissions: &Permissions, url: &reqwest::Url) -> Result<(), String> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("permission denied: '{}' has no host", url))?;
+
+    permissions.check_net(host, url.port_or_known_default())
+}
+
+// in a sandbox every redirect has to be allowed too, not just the first url
+fn client(
+    func: &str,
+    permissions: &Permissions,
+    url: &str,
+) -> Result<reqwest::blocking::Client, String> {
+    if !permissions.is_sandboxed() {
+        return Ok(reqwest::blocking::Client::new());
+    }
+
+    let parsed =
+        reqwest::Url::parse(url).map_err(|e| format!("{} invalid url '{}': {}", func, url, e))?;
+    check_url(permissions, &parsed)?;
+
+    let permissions = permissions.clone();
+    let policy = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 {
+            return attempt.error("too many redirects");
+        }
+
+        match check_url(&permissions, attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    });
+
+    reqwest::blocking::Client::builder()
+        .redirect(policy)
+        .build()
+        .map_err(|e| format!("{} failed to create client: {}", func, e))
+}
+
 fn create_response_obj(response: reqwest::blocking::Response) -> Value {
-    let mut properties = HashMap::new();
-
-    properties.insert(
-        "status".to_string(),
-        Value::Int(response.status().as_u16() as i64),
-    );
-    properties.insert(
-        "status_text".to_string(),
-        Value::String(
-            response
-                .status()
-                .canonical_reason()
-                .unwrap_or("")
-                .to_string(),
-        ),
-    );
-    properties.insert(
-        "ok".to_string(),
-        Value::Bool(response.status().is_success()),
-    );
+    let status = response.status();
 
     let headers = response
         .headers()
@@ -58,18 +87,22 @@ fn create_response_obj(response: reqwest::blocking::Response) -> Value {
                 Ok(s) => s.to_string(),
                 Err(_) => format!("{:?}", v),
             };
-            (k.to_string(), Value::String(value_str))
+            (k.to_string(), value_str)
         })
         .collect();
-    properties.insert("headers".to_string(), Value::Object(headers));
 
-    let body = response.text().unwrap_or_default();
-    properties.insert("body".to_string(), Value::String(body));
+    let response = Response {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        ok: status.is_success(),
+        headers,
+        body: response.text().unwrap_or_default(),
+    };
 
-    Value::Object(properties)
+    to_value(&response).unwrap_or(Value::Null)
 }
 
-fn get(args: Vec<Value>) -> Result<Value, String> {
+fn get(permissions: &Permissions, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
         return Err(format!(
             "http.get() needs at least one argument ({} given)",
@@ -92,7 +125,7 @@ fn get(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let client = reqwest::blocking::Client::new();
+    let client = client("http.get()", permissions, url)?;
     let mut request = client.get(url);
 
     if args.len() == 2 {
@@ -129,7 +162,7 @@ fn get(args: Vec<Value>) -> Result<Value, String> {
     Ok(create_response_obj(response))
 }
 
-fn post(args: Vec<Value>) -> Result<Value, String> {
+fn post(permissions: &Permissions, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
         return Err(format!(
             "http.post() needs at least one argument ({} given)",
@@ -152,7 +185,7 @@ fn post(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let client = reqwest::blocking::Client::new();
+    let client = client("http.post()", permissions, url)?;
     let mut request = client.post(url);
 
     if args.len() >= 2 {
@@ -203,7 +236,7 @@ fn post(args: Vec<Value>) -> Result<Value, String> {
     Ok(create_response_obj(response))
 }
 
-fn put(args: Vec<Value>) -> Result<Value, String> {
+fn put(permissions: &Permissions, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
         return Err(format!(
             "http.put() needs at least one argument ({} given)",
@@ -226,7 +259,7 @@ fn put(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let client = reqwest::blocking::Client::new();
+    let client = client("http.put()", permissions, url)?;
     let mut request = client.put(url);
 
     if args.len() >= 2 {
@@ -277,7 +310,7 @@ fn put(args: Vec<Value>) -> Result<Value, String> {
     Ok(create_response_obj(response))
 }
 
-fn patch(args: Vec<Value>) -> Result<Value, String> {
+fn patch(permissions: &Permissions, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
         return Err(format!(
             "http.patch() needs at least one argument ({} given)",
@@ -300,7 +333,7 @@ fn patch(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let client = reqwest::blocking::Client::new();
+    let client = client("http.patch()", permissions, url)?;
     let mut request = client.patch(url);
 
     if args.len() >= 2 {
@@ -351,7 +384,7 @@ fn patch(args: Vec<Value>) -> Result<Value, String> {
     Ok(create_response_obj(response))
 }
 
-fn delete(args: Vec<Value>) -> Result<Value, String> {
+fn delete(permissions: &Permissions, args: Vec<Value>) -> Result<Value, String> {
     if args.is_empty() {
         return Err(format!(
             "http.delete() needs at least one argument ({} given)",
@@ -374,7 +407,7 @@ fn delete(args: Vec<Value>) -> Result<Value, String> {
         }
     };
 
-    let client = reqwest::blocking::Client::new();
+    let client = client("http.delete()", permissions, url)?;
     let mut request = client.delete(url);
 
     if args.len() == 2 {
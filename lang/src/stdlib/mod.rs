@@ -2,6 +2,7 @@ mod crypto;
 mod encoding;
 mod json;
 mod math;
+mod test;
 mod time;
 mod uuid;
 
@@ -17,12 +18,24 @@ mod fs;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod ffi;
 
-pub fn get(name: &str) -> Option<crate::vm::value::Value> {
+use crate::permissions::Permissions;
+use std::sync::Arc;
+
+// what a vm hands the modules it imports
+#[derive(Clone)]
+pub struct Context {
+    pub permissions: Arc<Permissions>, // fs and http check every path and host against these
+    pub owner: usize,                  // id of the vm, handles it makes dont work in other vms
+}
+
+// the caller decides if the module can be imported at all
+pub fn get(name: &str, context: &Context) -> Option<crate::vm::value::Value> {
     match name {
         "crypto" => Some(crypto::object()),
         "encoding" => Some(encoding::object()),
         "json" => Some(json::object()),
         "math" => Some(math::object()),
+        "test" => Some(test::object()),
         "time" => Some(time::object()),
         "uuid" => Some(uuid::object()),
 
@@ -30,14 +43,23 @@ pub fn get(name: &str) -> Option<crate::vm::value::Value> {
         "os" => Some(os::object()),
 
         #[cfg(not(target_arch = "wasm32"))]
-        "http" => Some(http::object()),
+        "http" => Some(http::object(context.clone())),
 
         #[cfg(not(target_arch = "wasm32"))]
-        "fs" => Some(fs::object()),
+        "fs" => Some(fs::object(context.clone())),
 
         #[cfg(not(target_arch = "wasm32"))]
-        "ffi" => Some(ffi::object()),
+        "ffi" => Some(ffi::object(context.clone())),
+
+        _ => None,
+    }
+}
 
+// modu code that gets run when the module is imported, what it defines
+// is added to what get() gave
+pub fn source(name: &str) -> Option<&'static str> {
+    match name {
+        "test" => Some(test::SOURCE),
         _ => None,
     }
 }
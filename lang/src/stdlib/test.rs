@@ -0,0 +1,225 @@
+use crate::vm::value::{BuiltinFn, Value};
+use std::collections::{BTreeSet, HashMap};
+
+// assert_raises is in test.modu, it has to call back into modu code
+pub const SOURCE: &str = include_str!("test.modu");
+
+pub fn object() -> Value {
+    let mut methods = HashMap::new();
+
+    methods.insert(
+        "assert_eq".to_string(),
+        Value::BuiltinFn(BuiltinFn::new("assert_eq", assert_eq)),
+    );
+    methods.insert(
+        "assert_ne".to_string(),
+        Value::BuiltinFn(BuiltinFn::new("assert_ne", assert_ne)),
+    );
+    methods.insert(
+        "assert_approx".to_string(),
+        Value::BuiltinFn(BuiltinFn::new("assert_approx", assert_approx)),
+    );
+    methods.insert(
+        "assert_contains".to_string(),
+        Value::BuiltinFn(BuiltinFn::new("assert_contains", assert_contains)),
+    );
+    methods.insert(
+        "fail".to_string(),
+        Value::BuiltinFn(BuiltinFn::new("fail", fail)),
+    );
+
+    Value::Object(methods)
+}
+
+fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        _ => value.to_string(),
+    }
+}
+
+// the optional last argument is put in front of the message
+fn with_message(args: &[Value], at: usize, message: String) -> String {
+    match args.get(at) {
+        Some(extra) => format!("{}: {}", extra, message),
+        None => message,
+    }
+}
+
+fn check_args(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), String> {
+    if args.len() < min || args.len() > max {
+        return Err(format!(
+            "{}() takes {} to {} arguments ({} given)",
+            name,
+            min,
+            max,
+            args.len()
+        ));
+    }
+
+    Ok(())
+}
+
+// every place two values differ, as "[1].name: expected 2, got 3"
+fn differences(path: &str, actual: &Value, expected: &Value, out: &mut Vec<String>) {
+    match (actual, expected) {
+        (Value::Array(a), Value::Array(e)) => {
+            for i in 0..a.len().max(e.len()) {
+                let at = format!("{}[{}]", path, i);
+
+                match (a.get(i), e.get(i)) {
+                    (Some(a), Some(e)) => differences(&at, a, e, out),
+                    (Some(a), None) => out.push(format!("{}: not expected, got {}", at, show(a))),
+                    (None, Some(e)) => out.push(format!("{}: missing, expected {}", at, show(e))),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        (Value::Object(a), Value::Object(e)) => {
+            let keys: BTreeSet<&String> = a.keys().chain(e.keys()).collect();
+
+            for key in keys {
+                let at = format!("{}.{}", path, key);
+
+                match (a.get(key), e.get(key)) {
+                    (Some(a), Some(e)) => differences(&at, a, e, out),
+                    (Some(a), None) => out.push(format!("{}: not expected, got {}", at, show(a))),
+                    (None, Some(e)) => out.push(format!("{}: missing, expected {}", at, show(e))),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        _ if actual != expected => {
+            let at = if path.is_empty() { "value" } else { path };
+            out.push(format!(
+                "{}: expected {}, got {}",
+                at,
+                show(expected),
+                show(actual)
+            ));
+        }
+
+        _ => {}
+    }
+}
+
+fn assert_eq(args: Vec<Value>) -> Result<Value, String> {
+    check_args("assert_eq", &args, 2, 3)?;
+
+    if args[0] == args[1] {
+        return Ok(Value::Null);
+    }
+
+    let message = match (&args[0], &args[1]) {
+        (Value::Array(_), Value::Array(_)) | (Value::Object(_), Value::Object(_)) => {
+            let mut found = Vec::new();
+            differences("", &args[0], &args[1], &mut found);
+
+            format!("values differ\n  {}", found.join("\n  "))
+        }
+
+        (actual, expected) => format!("expected {}, got {}", show(expected), show(actual)),
+    };
+
+    Err(with_message(&args, 2, message))
+}
+
+fn assert_ne(args: Vec<Value>) -> Result<Value, String> {
+    check_args("assert_ne", &args, 2, 3)?;
+
+    if args[0] != args[1] {
+        return Ok(Value::Null);
+    }
+
+    Err(with_message(
+        &args,
+        2,
+        format!("expected something other than {}", show(&args[1])),
+    ))
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    }
+}
+
+// the tolerance grows with the numbers, 1e-9 by default. it can be left out
+// and the message passed third, a string is never a tolerance
+fn assert_approx(args: Vec<Value>) -> Result<Value, String> {
+    check_args("assert_approx", &args, 2, 4)?;
+
+    let (Some(actual), Some(expected)) = (number(&args[0]), number(&args[1])) else {
+        return Err(format!(
+            "assert_approx() compares numbers, got {} and {}",
+            args[0].type_name(),
+            args[1].type_name()
+        ));
+    };
+
+    let (tolerance, message_at) = match args.get(2) {
+        None => (1e-9, 2),
+        Some(Value::String(_)) if args.len() == 3 => (1e-9, 2),
+        Some(value) => {
+            let tolerance = number(value).ok_or_else(|| {
+                format!(
+                    "assert_approx() tolerance must be a number, got {}",
+                    value.type_name()
+                )
+            })?;
+
+            (tolerance, 3)
+        }
+    };
+
+    if (actual - expected).abs() <= tolerance * actual.abs().max(expected.abs()).max(1.0) {
+        return Ok(Value::Null);
+    }
+
+    Err(with_message(
+        &args,
+        message_at,
+        format!(
+            "expected about {}, got {} (tolerance {})",
+            expected, actual, tolerance
+        ),
+    ))
+}
+
+fn assert_contains(args: Vec<Value>) -> Result<Value, String> {
+    check_args("assert_contains", &args, 2, 3)?;
+
+    let found = match (&args[0], &args[1]) {
+        (Value::String(haystack), Value::String(needle)) => haystack.contains(needle.as_str()),
+        (Value::Array(items), needle) => items.contains(needle),
+        (Value::Object(properties), Value::String(key)) => properties.contains_key(key),
+
+        (haystack, _) => {
+            return Err(format!(
+                "assert_contains() looks in strings, arrays and objects, got {}",
+                haystack.type_name()
+            ));
+        }
+    };
+
+    if found {
+        return Ok(Value::Null);
+    }
+
+    Err(with_message(
+        &args,
+        2,
+        format!("{} does not contain {}", show(&args[0]), show(&args[1])),
+    ))
+}
+
+fn fail(args: Vec<Value>) -> Result<Value, String> {
+    match args.first() {
+        Some(message) => Err(message.to_string()),
+        None => Err("failed".to_string()),
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::error::ModuError;
+use crate::vm::debug::Hook;
+use crate::vm::value::Value;
+use crate::vm::vm::VM;
+
+// logs every instruction as it runs, with where it is and the top of the
+// stack right before it. imports run without hooks, so the top level of a
+// module doesn't show up but calls into it do
+//
+//   let mut modu = Interpreter::new();
+//   modu.vm().set_hook(Tracer::new(std::io::stderr()));
+//   modu.eval_file("main.modu")?;
+
+const STACK_SHOWN: usize = 4;
+const VALUE_WIDTH: usize = 24;
+
+pub struct Tracer {
+    out: Box<dyn Write + Send>,
+    functions: Vec<String>,
+    lines: Option<(Option<PathBuf>, usize, usize)>,
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Box::new(std::io::BufWriter::new(out)),
+            functions: Vec::new(),
+            lines: None,
+        }
+    }
+
+    // only instructions in these functions, "main" is the top level
+    pub fn functions(mut self, functions: Vec<String>) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    // only these lines, in files whose path ends with `file` if there is one
+    pub fn lines(mut self, file: Option<PathBuf>, from: usize, to: usize) -> Self {
+        self.lines = Some((file, from, to));
+        self
+    }
+}
+
+// short enough that a line of the trace stays readable
+fn compact(value: &Value) -> String {
+    let text = match value {
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(items) => format!("[{} items]", items.len()),
+        Value::Object(properties) => format!("{{{} keys}}", properties.len()),
+        value => value.to_string(),
+    };
+
+    if text.chars().count() > VALUE_WIDTH {
+        let cut: String = text.chars().take(VALUE_WIDTH - 1).collect();
+        format!("{}…", cut)
+    } else {
+        text
+    }
+}
+
+impl Hook for Tracer {
+    fn instruction(&mut self, vm: &VM) -> Result<(), ModuError> {
+        let Some((chunk_id, ip)) = vm.position() else {
+            return Ok(());
+        };
+
+        let chunk = &vm.chunks[chunk_id];
+
+        if !self.functions.is_empty() && !self.functions.contains(&chunk.name) {
+            return Ok(());
+        }
+
+        let location = match (&chunk.source, chunk.spans.get(ip)) {
+            (Some(source), Some(span)) => Some((source, source.line(span.start))),
+            _ => None,
+        };
+
+        if let Some((file, from, to)) = &self.lines {
+            let inside = location.is_some_and(|(source, line)| {
+                (*from..=*to).contains(&line)
+                    && file.as_ref().is_none_or(|file| source.path.ends_with(file))
+            });
+
+            if !inside {
+                return Ok(());
+            }
+        }
+
+        let location = match location {
+            Some((source, line)) => {
+                let name = source
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                format!("{}:{}", name, line)
+            }
+            None => "-".to_string(),
+        };
+
+        let stack = vm.stack();
+        let shown = &stack[stack.len().saturating_sub(STACK_SHOWN)..];
+        let mut values: Vec<String> = shown.iter().map(compact).collect();
+        if stack.len() > STACK_SHOWN {
+            values.insert(0, "…".to_string());
+        }
+
+        // a trace that can't be written isn't worth stopping the program for
+        let _ = writeln!(
+            self.out,
+            "{:<16} {:04}  {:<16} {:<32} [{}]",
+            chunk.name,
+            ip,
+            location,
+            format!("{:?}", chunk.instructions[ip]),
+            values.join(", ")
+        );
+
+        Ok(())
+    }
+
+    fn uncaught(&mut self, _vm: &VM, error: &ModuError) {
+        let _ = writeln!(self.out, "uncaught error: {}", error.message);
+        let _ = self.out.flush();
+    }
+}
@@ -1,65 +1,76 @@
 use crate::ast::{Expr, SpannedExpr};
 use crate::lexer::Span;
-use ariadne::{Color, Label, Report, ReportKind, Source};
 
 struct ValidationContext {
     inside_function: usize,
     inside_loop: usize,
+    errors: Vec<ValidationError>,
 }
 
 pub struct ValidationError {
     pub span: Span,
     pub message: String,
+    pub code: u16,
 }
 
-pub fn validate_ast(ast: &[SpannedExpr], filename: &str, source: &str) -> Result<(), ()> {
+impl ValidationContext {
+    fn error(&mut self, span: Span, message: &str, code: u16) {
+        self.errors.push(ValidationError {
+            span,
+            message: message.to_string(),
+            code,
+        });
+    }
+}
+
+// every misplaced statement, not just the first
+pub fn validate_ast(ast: &[SpannedExpr]) -> Result<(), Vec<ValidationError>> {
     let mut context = ValidationContext {
         inside_function: 0,
         inside_loop: 0,
+        errors: Vec::new(),
     };
 
     for expr in ast {
-        if let Err(err) = validate_expr(expr, &mut context) {
-            report_error(&err, filename, source);
-            return Err(());
-        }
+        validate_expr(expr, &mut context);
     }
 
-    Ok(())
+    if context.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(context.errors)
+    }
 }
 
-fn validate_expr(expr: &SpannedExpr, ctx: &mut ValidationContext) -> Result<(), ValidationError> {
+fn validate_expr(expr: &SpannedExpr, ctx: &mut ValidationContext) {
     match &expr.node {
-        Expr::Return(_) => {
-            if ctx.inside_function == 0 {
-                return Err(ValidationError {
-                    span: expr.span,
-                    message: "Return statement not allowed outside of a function".to_string(),
-                });
-            }
+        Expr::Return(_) if ctx.inside_function == 0 => {
+            ctx.error(
+                expr.span,
+                "Return statement not allowed outside of a function",
+                4,
+            );
         }
 
-        Expr::Break => {
-            if ctx.inside_loop == 0 {
-                return Err(ValidationError {
-                    span: expr.span,
-                    message: "Break statement not allowed outside of a loop".to_string(),
-                });
-            }
+        Expr::Break if ctx.inside_loop == 0 => {
+            ctx.error(
+                expr.span,
+                "Break statement not allowed outside of a loop",
+                5,
+            );
         }
 
-        Expr::Continue => {
-            if ctx.inside_loop == 0 {
-                return Err(ValidationError {
-                    span: expr.span,
-                    message: "Continue statement not allowed outside of a loop".to_string(),
-                });
-            }
+        Expr::Continue if ctx.inside_loop == 0 => {
+            ctx.error(
+                expr.span,
+                "Continue statement not allowed outside of a loop",
+                5,
+            );
         }
 
         Expr::Function { body, .. } => {
             ctx.inside_function += 1;
-            validate_expr(body, ctx)?;
+            validate_expr(body, ctx);
             ctx.inside_function -= 1;
         }
 
@@ -67,50 +78,26 @@ fn validate_expr(expr: &SpannedExpr, ctx: &mut ValidationContext) -> Result<(),
         | Expr::ForLoop { body, .. }
         | Expr::InfiniteLoop { body, .. } => {
             ctx.inside_loop += 1;
-            validate_expr(body, ctx)?;
+            validate_expr(body, ctx);
             ctx.inside_loop -= 1;
         }
 
         Expr::If(branches) => {
             for (condition, block) in branches {
                 if let Some(cond) = condition {
-                    validate_expr(cond, ctx)?;
+                    validate_expr(cond, ctx);
                 }
 
-                validate_expr(block, ctx)?;
+                validate_expr(block, ctx);
             }
         }
 
         Expr::Block(body) => {
             for expr in body {
-                validate_expr(expr, ctx)?;
+                validate_expr(expr, ctx);
             }
         }
 
         _ => {}
     }
-
-    Ok(())
-}
-
-fn report_error(err: &ValidationError, source_name: &str, code: &str) {
-    let report = Report::build(ReportKind::Error, (source_name, err.span.into_range()))
-        .with_message(&err.message)
-        .with_label(
-            Label::new((source_name, err.span.into_range()))
-                .with_message(&err.message)
-                .with_color(Color::Red),
-        )
-        .finish();
-
-    #[cfg(target_arch = "wasm32")]
-    {
-        let mut writer = crate::WasmWriter;
-        let _ = report.write((source_name, Source::from(code)), &mut writer);
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    {
-        let _ = report.eprint((source_name, Source::from(code)));
-    }
 }
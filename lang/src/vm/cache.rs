@@ -0,0 +1,186 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::value::{NativeFn, Value};
+
+static NEXT_CLASS_ID: AtomicUsize = AtomicUsize::new(0);
+
+// every class declaration gets its own id, so two classes
+// with the same name never share cache entries
+pub fn next_class_id() -> usize {
+    NEXT_CLASS_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// the builtin types that have native methods. host objects can call
+// themselves anything, so they're never one of these
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NativeType {
+    Object,
+    String,
+    Int,
+    Float,
+    Array,
+}
+
+impl NativeType {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Object(_) => Some(Self::Object),
+            Value::String(_) => Some(Self::String),
+            Value::Int(_) => Some(Self::Int),
+            Value::Float(_) => Some(Self::Float),
+            Value::Array(_) => Some(Self::Array),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum InlineCache {
+    Native {
+        native_type: NativeType,
+        func: NativeFn,
+    },
+
+    Method {
+        class_id: usize,
+        chunk_id: usize,
+        arity: usize,
+        epoch: usize,
+    },
+}
+
+pub enum CacheHit {
+    Native(NativeFn),
+    Method { chunk_id: usize, arity: usize },
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct SiteStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Default)]
+pub struct InlineCaches {
+    slots: Vec<Vec<Option<InlineCache>>>, // [chunk_id][ip]
+    epoch: usize,
+    // (class_id, property) pairs that were assigned on some instance,
+    // so the value can differ between instances of the same class
+    dynamic_props: HashSet<(usize, String)>,
+    stats: Option<HashMap<(usize, usize), SiteStats>>,
+}
+
+impl InlineCaches {
+    pub fn enable_stats(&mut self) {
+        self.stats.get_or_insert_with(HashMap::new);
+    }
+
+    pub fn stats(&self) -> Option<&HashMap<(usize, usize), SiteStats>> {
+        self.stats.as_ref()
+    }
+
+    pub fn lookup(&mut self, site: (usize, usize), target: &Value) -> Option<CacheHit> {
+        let cache = self.slots.get(site.0)?.get(site.1)?.as_ref()?;
+
+        let hit = match (cache, target) {
+            (
+                InlineCache::Method {
+                    class_id,
+                    chunk_id,
+                    arity,
+                    epoch,
+                },
+                Value::Instance {
+                    class_id: target_id,
+                    ..
+                },
+            ) if class_id == target_id && *epoch == self.epoch => Some(CacheHit::Method {
+                chunk_id: *chunk_id,
+                arity: *arity,
+            }),
+
+            (InlineCache::Native { native_type, func }, target)
+                if NativeType::of(target) == Some(*native_type) =>
+            {
+                Some(CacheHit::Native(func.clone()))
+            }
+
+            _ => None,
+        };
+
+        if hit.is_some()
+            && let Some(stats) = &mut self.stats
+        {
+            stats.entry(site).or_default().hits += 1;
+        }
+
+        hit
+    }
+
+    pub fn fill_native(&mut self, site: (usize, usize), native_type: NativeType, func: NativeFn) {
+        self.fill(site, InlineCache::Native { native_type, func });
+    }
+
+    pub fn fill_method(
+        &mut self,
+        site: (usize, usize),
+        name: &str,
+        class_id: usize,
+        chunk_id: usize,
+        arity: usize,
+    ) {
+        if self.is_dynamic(class_id, name) {
+            self.record_miss(site);
+            return;
+        }
+
+        self.fill(
+            site,
+            InlineCache::Method {
+                class_id,
+                chunk_id,
+                arity,
+                epoch: self.epoch,
+            },
+        );
+    }
+
+    fn record_miss(&mut self, site: (usize, usize)) {
+        if let Some(stats) = &mut self.stats {
+            stats.entry(site).or_default().misses += 1;
+        }
+    }
+
+    fn fill(&mut self, site: (usize, usize), cache: InlineCache) {
+        self.record_miss(site);
+
+        if self.slots.len() <= site.0 {
+            self.slots.resize_with(site.0 + 1, Vec::new);
+        }
+
+        let chunk = &mut self.slots[site.0];
+        if chunk.len() <= site.1 {
+            chunk.resize(site.1 + 1, None);
+        }
+
+        chunk[site.1] = Some(cache);
+    }
+
+    pub fn is_dynamic(&self, class_id: usize, name: &str) -> bool {
+        self.dynamic_props.contains(&(class_id, name.to_string()))
+    }
+
+    pub fn mark_dynamic(&mut self, class_id: usize, name: &str) {
+        if self.dynamic_props.insert((class_id, name.to_string())) {
+            // anything cached before this could now be stale
+            self.epoch += 1;
+        }
+    }
+
+    pub fn merge_dynamic(&mut self, other: &InlineCaches) {
+        for (class_id, name) in &other.dynamic_props {
+            self.mark_dynamic(*class_id, name);
+        }
+    }
+}
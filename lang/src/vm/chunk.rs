@@ -1,6 +1,60 @@
 use super::instruction::Instruction;
 use super::value::Value;
 use chumsky::span::SimpleSpan;
+use std::sync::Arc;
+
+// the file a chunk was compiled from, so errors point at the right code
+// even after the chunk got merged into another vm
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: std::path::PathBuf,
+    pub text: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(path: impl Into<std::path::PathBuf>, text: &str) -> Arc<Self> {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Arc::new(Self {
+            path: path.into(),
+            text: text.to_string(),
+            line_starts,
+        })
+    }
+
+    // 1-based line a byte offset is on
+    pub fn line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    // 1-based, in characters
+    pub fn column(&self, offset: usize) -> usize {
+        let start = self.line_starts[self.line(offset) - 1];
+        self.text[start..offset.min(self.text.len())]
+            .chars()
+            .count()
+            + 1
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    // without the line ending
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|&next| next - 1)
+            .unwrap_or(self.text.len());
+
+        Some(self.text[start..end].trim_end_matches('\r'))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Chunk {
@@ -9,6 +63,8 @@ pub struct Chunk {
     pub spans: Vec<SimpleSpan>,
     pub constants: Vec<Value>,
     pub locals_count: usize,
+    pub local_names: Vec<String>, // by slot, for debuggers
+    pub source: Option<Arc<SourceFile>>,
 }
 
 impl Chunk {
@@ -19,6 +75,8 @@ impl Chunk {
             spans: Vec::new(),
             constants: Vec::new(),
             locals_count: 0,
+            local_names: Vec::new(),
+            source: None,
         }
     }
 
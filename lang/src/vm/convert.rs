@@ -0,0 +1,831 @@
+// conversions between modu values and rust types through serde
+//
+//   #[derive(Serialize, Deserialize)]
+//   struct Config { name: String, port: u16 }
+//
+//   let value = to_value(&config)?;
+//   let config: Config = from_value(value)?;
+
+use std::collections::HashMap;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq};
+
+use super::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    pub path: Option<String>, // where in the modu value it went wrong, like servers[2].port
+    pub message: String,
+}
+
+impl ConvertError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            path: None,
+            message: message.into(),
+        }
+    }
+
+    // the innermost path wins, outer values only fill it in if nothing did yet
+    fn at(mut self, path: &str) -> Self {
+        if self.path.is_none() && !path.is_empty() {
+            self.path = Some(path.to_string());
+        }
+
+        self
+    }
+
+    // serializing goes inside out, so segments get added in front
+    fn within(mut self, segment: &str) -> Self {
+        self.path = Some(match self.path.take() {
+            Some(path) if path.starts_with('[') => format!("{}{}", segment, path),
+            Some(path) => format!("{}.{}", segment, path),
+            None => segment.to_string(),
+        });
+
+        self
+    }
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl de::Error for ConvertError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::new(msg.to_string())
+    }
+}
+
+impl ser::Error for ConvertError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Self::new(msg.to_string())
+    }
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ConvertError> {
+    from_value_at(value, "")
+}
+
+// like from_value, but errors are reported relative to `root`, e.g. the global's name
+pub fn from_value_at<T: DeserializeOwned>(value: Value, root: &str) -> Result<T, ConvertError> {
+    T::deserialize(ValueDeserializer {
+        value,
+        path: root.to_string(),
+    })
+}
+
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ConvertError> {
+    value.serialize(ValueSerializer)
+}
+
+// value -> rust
+
+struct ValueDeserializer {
+    value: Value,
+    path: String,
+}
+
+impl ValueDeserializer {
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match &self.value {
+            Value::Null => de::Unexpected::Unit,
+            Value::Bool(b) => de::Unexpected::Bool(*b),
+            Value::Int(n) => de::Unexpected::Signed(*n),
+            Value::Float(n) => de::Unexpected::Float(*n),
+            Value::String(s) => de::Unexpected::Str(s),
+            Value::Array(_) => de::Unexpected::Seq,
+            Value::Object(_) | Value::Instance { .. } => de::Unexpected::Map,
+            other => de::Unexpected::Other(other.type_name()),
+        }
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn sorted(properties: HashMap<String, Value>) -> Vec<(String, Value)> {
+    let mut properties: Vec<_> = properties.into_iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(&b.0));
+    properties
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = ConvertError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        let path = self.path;
+
+        let result = match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Int(n) => visitor.visit_i64(n),
+            Value::Float(n) => visitor.visit_f64(n),
+            Value::String(s) => visitor.visit_string(s),
+
+            Value::Array(items) => visitor.visit_seq(SeqDeserializer {
+                items: items.into_iter().enumerate(),
+                path: path.clone(),
+            }),
+
+            Value::Object(properties) | Value::Instance { properties, .. } => {
+                visitor.visit_map(MapDeserializer {
+                    properties: sorted(properties).into_iter(),
+                    value: None,
+                    path: path.clone(),
+                })
+            }
+
+            Value::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let end = if inclusive { end + 1 } else { end };
+                let items: Vec<Value> = (start..end).map(Value::Int).collect();
+
+                visitor.visit_seq(SeqDeserializer {
+                    items: items.into_iter().enumerate(),
+                    path: path.clone(),
+                })
+            }
+
+            other => Err(ConvertError::new(format!(
+                "cannot convert {} to rust data",
+                other.type_name()
+            ))),
+        };
+
+        result.map_err(|e| e.at(&path))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ConvertError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // "variant" for unit variants, { variant: value } for the rest, same as serde_json
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        let path = self.path.clone();
+
+        let result = match self.value {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+
+            Value::Object(properties) if properties.len() == 1 => {
+                let (variant, value) = properties.into_iter().next().unwrap();
+
+                visitor.visit_enum(EnumDeserializer {
+                    path: child_path(&path, &variant),
+                    variant,
+                    value,
+                })
+            }
+
+            other => {
+                let value = ValueDeserializer {
+                    value: other,
+                    path: String::new(),
+                };
+
+                Err(de::Error::invalid_type(
+                    value.unexpected(),
+                    &"a string or an object with one key",
+                ))
+            }
+        };
+
+        result.map_err(|e| e.at(&path))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct SeqDeserializer {
+    items: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    path: String,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = ConvertError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ConvertError> {
+        match self.items.next() {
+            Some((i, value)) => seed
+                .deserialize(ValueDeserializer {
+                    value,
+                    path: format!("{}[{}]", self.path, i),
+                })
+                .map(Some),
+
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapDeserializer {
+    properties: std::vec::IntoIter<(String, Value)>,
+    value: Option<(String, Value)>,
+    path: String,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = ConvertError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ConvertError> {
+        match self.properties.next() {
+            Some((key, value)) => {
+                let path = child_path(&self.path, &key);
+                self.value = Some((path, value));
+
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ConvertError> {
+        let (path, value) = self
+            .value
+            .take()
+            .ok_or_else(|| ConvertError::new("value requested before key"))?;
+
+        seed.deserialize(ValueDeserializer { value, path })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.properties.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    value: Value,
+    path: String,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = ConvertError;
+    type Variant = ValueDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, ValueDeserializer), ConvertError> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+
+        Ok((
+            variant,
+            ValueDeserializer {
+                value: self.value,
+                path: self.path,
+            },
+        ))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueDeserializer {
+    type Error = ConvertError;
+
+    fn unit_variant(self) -> Result<(), ConvertError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ConvertError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ConvertError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "any value modu can represent")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Int(n))
+    }
+
+    // too big for an int, so it ends up as a float
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(i64::try_from(n).map_or(Value::Float(n as f64), Value::Int))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::Float(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut properties = HashMap::new();
+
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            properties.insert(key, value);
+        }
+
+        Ok(Value::Object(properties))
+    }
+}
+
+// rust -> value
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Int(n) => serializer.serialize_i64(*n),
+            Value::Float(n) => serializer.serialize_f64(*n),
+            Value::String(s) => serializer.serialize_str(s),
+
+            Value::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+
+            // sorted so the output is the same every time
+            Value::Object(properties) | Value::Instance { properties, .. } => {
+                let mut keys: Vec<&String> = properties.keys().collect();
+                keys.sort();
+
+                let mut map = serializer.serialize_map(Some(keys.len()))?;
+                for key in keys {
+                    map.serialize_entry(key, &properties[key])?;
+                }
+                map.end()
+            }
+
+            Value::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let end = if *inclusive { *end + 1 } else { *end };
+                serializer.collect_seq((*start..end).map(Value::Int))
+            }
+
+            other => Err(ser::Error::custom(format!(
+                "cannot convert {} to rust data",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+struct ValueSerializer;
+
+fn too_big(n: impl std::fmt::Display) -> ConvertError {
+    ConvertError::new(format!("integer {} does not fit in an int", n))
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ConvertError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, ConvertError> {
+        i64::try_from(v).map(Value::Int).map_err(|_| too_big(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ConvertError> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ConvertError> {
+        i64::try_from(v).map(Value::Int).map_err(|_| too_big(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, ConvertError> {
+        i64::try_from(v).map(Value::Int).map_err(|_| too_big(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ConvertError> {
+        Ok(Value::Float(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ConvertError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ConvertError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ConvertError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ConvertError> {
+        Ok(Value::Array(
+            v.iter().map(|b| Value::Int(*b as i64)).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ConvertError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ConvertError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ConvertError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ConvertError> {
+        let mut properties = HashMap::new();
+        properties.insert(variant.to_string(), value.serialize(self)?);
+        Ok(Value::Object(properties))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, ConvertError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, ConvertError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<SeqSerializer>, ConvertError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, ConvertError> {
+        Ok(MapSerializer {
+            properties: HashMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, ConvertError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<MapSerializer>, ConvertError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SeqSerializer {
+    items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        let segment = format!("[{}]", self.items.len());
+        self.items
+            .push(to_value(value).map_err(|e| e.within(&segment))?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::Array(self.items))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    properties: HashMap<String, Value>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    // object keys are always strings, so numbers and bools get stringified
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ConvertError> {
+        let key = match to_value(key)? {
+            Value::String(s) => s,
+            Value::Int(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            other => {
+                return Err(ConvertError::new(format!(
+                    "object keys must be strings, got {}",
+                    other.type_name()
+                )));
+            }
+        };
+
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ConvertError::new("value serialized before key"))?;
+
+        let value = to_value(value).map_err(|e| e.within(&key))?;
+        self.properties.insert(key, value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::Object(self.properties))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        let value = to_value(value).map_err(|e| e.within(key))?;
+        self.properties.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Value::Object(self.properties))
+    }
+}
+
+// wraps tuple and struct variants as { variant: ... }
+struct VariantSerializer<T> {
+    variant: &'static str,
+    inner: T,
+}
+
+impl<T> VariantSerializer<T> {
+    fn wrap(variant: &str, value: Value) -> Value {
+        let mut properties = HashMap::new();
+        properties.insert(variant.to_string(), value);
+        Value::Object(properties)
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ConvertError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeSeq::end(self.inner)?,
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Value;
+    type Error = ConvertError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value, ConvertError> {
+        Ok(Self::wrap(
+            self.variant,
+            ser::SerializeStruct::end(self.inner)?,
+        ))
+    }
+}
@@ -0,0 +1,272 @@
+use super::chunk::Chunk;
+use super::instruction::Instruction;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// which lines and branches ran, see VM::set_coverage. a vm counts by chunk
+// and instruction as it goes and adds that up here by file when a run is
+// over, so modules it imported end up under their own files
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    files: Arc<Mutex<BTreeMap<PathBuf, FileCoverage>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileCoverage {
+    pub lines: BTreeMap<usize, u64>, // every line with code on it, and how often it ran
+    // every condition by line and where it starts, [times it was true, times it was false]
+    pub branches: BTreeMap<(usize, usize), [u64; 2]>,
+}
+
+impl FileCoverage {
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits > 0).count()
+    }
+
+    // each way a condition can go is a branch
+    pub fn branches_hit(&self) -> usize {
+        self.branches
+            .values()
+            .map(|counts| counts.iter().filter(|&&n| n > 0).count())
+            .sum()
+    }
+}
+
+fn percent(hit: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+    }
+}
+
+// 3, 5-8, 12
+fn ranges(lines: impl Iterator<Item = usize>) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // only real files, not std modules written in modu or code given as a string
+    pub fn files(&self) -> BTreeMap<PathBuf, FileCoverage> {
+        self.files
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.is_absolute())
+            .map(|(path, file)| (path.clone(), file.clone()))
+            .collect()
+    }
+
+    // the lcov tracefile format, which most coverage tools read
+    pub fn lcov(&self) -> String {
+        let mut out = String::new();
+
+        for (path, file) in self.files() {
+            out.push_str(&format!("TN:\nSF:{}\n", path.display()));
+
+            let mut previous = None;
+            let mut block = 0;
+
+            for (&(line, _), counts) in &file.branches {
+                block = if previous == Some(line) { block + 1 } else { 0 };
+                previous = Some(line);
+
+                let ran = file.lines.get(&line).is_some_and(|&hits| hits > 0);
+
+                for (branch, taken) in counts.iter().enumerate() {
+                    let taken = if ran {
+                        taken.to_string()
+                    } else {
+                        "-".to_string()
+                    };
+                    out.push_str(&format!("BRDA:{},{},{},{}\n", line, block, branch, taken));
+                }
+            }
+
+            out.push_str(&format!(
+                "BRF:{}\nBRH:{}\n",
+                file.branches.len() * 2,
+                file.branches_hit()
+            ));
+
+            for (line, hits) in &file.lines {
+                out.push_str(&format!("DA:{},{}\n", line, hits));
+            }
+
+            out.push_str(&format!(
+                "LF:{}\nLH:{}\nend_of_record\n",
+                file.lines.len(),
+                file.lines_hit()
+            ));
+        }
+
+        out
+    }
+
+    // a table for the terminal, paths relative to root when they're under it
+    pub fn summary(&self, root: &Path) -> String {
+        let files = self.files();
+        let name = |path: &Path| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        let width = files
+            .keys()
+            .map(|path| name(path).len())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+
+        let mut out = format!(
+            "{:<width$}  {:>15}  {:>15}  missed lines\n",
+            "file", "lines", "branches"
+        );
+
+        let (mut lines, mut lines_hit, mut branches, mut branches_hit) = (0, 0, 0, 0);
+
+        for (path, file) in &files {
+            let missed = file
+                .lines
+                .iter()
+                .filter(|(_, hits)| **hits == 0)
+                .map(|(line, _)| *line);
+
+            out.push_str(&format!(
+                "{:<width$}  {:>15}  {:>15}  {}\n",
+                name(path),
+                format!(
+                    "{}/{} {}",
+                    file.lines_hit(),
+                    file.lines.len(),
+                    percent(file.lines_hit(), file.lines.len())
+                ),
+                format!(
+                    "{}/{} {}",
+                    file.branches_hit(),
+                    file.branches.len() * 2,
+                    percent(file.branches_hit(), file.branches.len() * 2)
+                ),
+                ranges(missed)
+            ));
+
+            lines += file.lines.len();
+            lines_hit += file.lines_hit();
+            branches += file.branches.len() * 2;
+            branches_hit += file.branches_hit();
+        }
+
+        out.push_str(&format!(
+            "{:<width$}  {:>15}  {:>15}\n",
+            "total",
+            format!("{}/{} {}", lines_hit, lines, percent(lines_hit, lines)),
+            format!(
+                "{}/{} {}",
+                branches_hit,
+                branches,
+                percent(branches_hit, branches)
+            ),
+        ));
+
+        out
+    }
+}
+
+// what one vm counted since its last run ended
+#[derive(Debug)]
+pub(crate) struct Recorder {
+    coverage: Coverage,
+    instructions: HashMap<(usize, usize), u64>,
+    branches: HashMap<(usize, usize), [u64; 2]>,
+}
+
+impl Recorder {
+    pub fn new(coverage: Coverage) -> Self {
+        Self {
+            coverage,
+            instructions: HashMap::new(),
+            branches: HashMap::new(),
+        }
+    }
+
+    // another vm adding to the same coverage, for imports
+    pub fn fork(&self) -> Self {
+        Self::new(self.coverage.clone())
+    }
+
+    pub fn hit(&mut self, site: (usize, usize)) {
+        *self.instructions.entry(site).or_insert(0) += 1;
+    }
+
+    pub fn branch(&mut self, site: (usize, usize), jumped: bool) {
+        self.branches.entry(site).or_insert([0, 0])[jumped as usize] += 1;
+    }
+
+    // adds the counts to the files they came from, lines that didn't run
+    // count too so they show up as missed
+    pub fn flush(&mut self, chunks: &[Chunk]) {
+        let mut files = self.coverage.files.lock().unwrap();
+
+        for (chunk_id, chunk) in chunks.iter().enumerate() {
+            let Some(source) = &chunk.source else {
+                continue;
+            };
+
+            let file = files.entry(source.path.clone()).or_default();
+            let mut lines: BTreeMap<usize, u64> = BTreeMap::new();
+
+            for (ip, (instruction, span)) in chunk.instructions.iter().zip(&chunk.spans).enumerate()
+            {
+                let line = source.line(span.start);
+                let hits = self.instructions.get(&(chunk_id, ip)).copied().unwrap_or(0);
+
+                // a line ran as often as its busiest instruction
+                let count = lines.entry(line).or_insert(0);
+                *count = (*count).max(hits);
+
+                if let Instruction::JumpIfFalse(_) = instruction {
+                    let counts = self
+                        .branches
+                        .get(&(chunk_id, ip))
+                        .copied()
+                        .unwrap_or([0, 0]);
+                    let total = file.branches.entry((line, span.start)).or_insert([0, 0]);
+                    total[0] += counts[0];
+                    total[1] += counts[1];
+                }
+            }
+
+            for (line, hits) in lines {
+                *file.lines.entry(line).or_insert(0) += hits;
+            }
+        }
+
+        self.instructions.clear();
+        self.branches.clear();
+    }
+}
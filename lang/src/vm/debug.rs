@@ -0,0 +1,39 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::chunk::SourceFile;
+use super::vm::VM;
+use crate::error::ModuError;
+
+// lets tools like the debugger watch a vm run, see VM::set_hook
+pub trait Hook: Send {
+    // right before an instruction runs, it is the current one of the
+    // top frame in vm.frames(). an error stops the run
+    fn instruction(&mut self, _vm: &VM) -> Result<(), ModuError> {
+        Ok(())
+    }
+
+    // an error nothing caught, the frames it happened in are still there
+    fn uncaught(&mut self, _vm: &VM, _error: &ModuError) {}
+}
+
+// a call on the stack, as far as tools are concerned
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub depth: usize, // 0 is the outermost
+    pub chunk_id: usize,
+    pub ip: usize, // the instruction it is at
+    pub name: String,
+    pub span: Range<usize>,
+    pub source: Option<Arc<SourceFile>>,
+}
+
+impl Frame {
+    pub fn line(&self) -> Option<usize> {
+        self.source.as_ref().map(|s| s.line(self.span.start))
+    }
+
+    pub fn path(&self) -> Option<&std::path::Path> {
+        self.source.as_ref().map(|s| s.path.as_path())
+    }
+}
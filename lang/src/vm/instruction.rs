@@ -42,6 +42,7 @@ pub enum Instruction {
     EndTry,
 
     Call(usize),
+    TailCall(usize), // like Call, but reuses the current frame when it can
     CallMethod {
         argc: usize,
         target_local: Option<usize>,
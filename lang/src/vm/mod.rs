@@ -1,4 +1,10 @@
+pub mod cache;
 pub mod chunk;
+pub mod convert;
+pub mod coverage;
+pub mod debug;
 pub mod instruction;
+pub mod profile;
+pub mod stdio;
 pub mod value;
 pub mod vm;
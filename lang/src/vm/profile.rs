@@ -0,0 +1,292 @@
+use super::chunk::Chunk;
+use super::vm::CallFrame;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// where the time goes, see VM::set_profile. the vm keeps a stack of its own
+// in step with its call frames, each function gets what ran while it was on
+// top. builtins like http.get are timed around the call and listed apart
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    data: Arc<Mutex<ProfileData>>,
+}
+
+#[derive(Debug, Default)]
+struct ProfileData {
+    functions: HashMap<(String, String), FunctionStats>,
+    stacks: HashMap<String, Duration>, // "main;load;json.parse" -> time spent in the last one
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FunctionStats {
+    pub name: String,
+    pub location: String, // file:line, or "builtin"
+    pub builtin: bool,
+    pub calls: u64,
+    pub total: Duration, // with what it called
+    pub own: Duration,
+    pub instructions: u64, // with what it called
+    pub own_instructions: u64,
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64() * 1000.0)
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the most time spent in the function itself first
+    pub fn functions(&self) -> Vec<FunctionStats> {
+        let mut functions: Vec<_> = self
+            .data
+            .lock()
+            .unwrap()
+            .functions
+            .values()
+            .cloned()
+            .collect();
+        functions.sort_by(|a, b| b.own.cmp(&a.own).then_with(|| a.name.cmp(&b.name)));
+        functions
+    }
+
+    // one line per call stack with the microseconds spent at its top,
+    // what flamegraph.pl and inferno read
+    pub fn folded(&self) -> String {
+        let data = self.data.lock().unwrap();
+        let mut stacks: Vec<_> = data.stacks.iter().collect();
+        stacks.sort();
+
+        stacks
+            .iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect()
+    }
+
+    // a table for the terminal, paths relative to root when they're under it
+    pub fn table(&self, root: &Path) -> String {
+        let functions = self.functions();
+        let total: Duration = functions.iter().map(|f| f.own).sum();
+
+        let location =
+            |f: &FunctionStats| match f.location.strip_prefix(&format!("{}/", root.display())) {
+                Some(relative) => relative.to_string(),
+                None => f.location.clone(),
+            };
+        let width = functions
+            .iter()
+            .map(|f| f.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(8);
+
+        let mut out = format!(
+            "{:<width$}  {:>8}  {:>10}  {:>10}  {:>6}  {:>12}  {:>12}  location\n",
+            "function", "calls", "total ms", "self ms", "self %", "instructions", "self instr"
+        );
+
+        for f in &functions {
+            let percent = if total.is_zero() {
+                0.0
+            } else {
+                f.own.as_secs_f64() * 100.0 / total.as_secs_f64()
+            };
+
+            out.push_str(&format!(
+                "{:<width$}  {:>8}  {:>10}  {:>10}  {:>5.1}%  {:>12}  {:>12}  {}\n",
+                f.name,
+                f.calls,
+                millis(f.total),
+                millis(f.own),
+                percent,
+                f.instructions,
+                f.own_instructions,
+                location(f)
+            ));
+        }
+
+        out
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    chunk_id: usize,
+    key: (String, String),
+    stack: String,
+    start: Instant,
+    instructions: u64, // the vm's count when it started
+    children: Duration,
+    child_instructions: u64,
+    recursive: bool, // already further down, so its total is counted there
+}
+
+// what one vm is in the middle of
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    profile: Profile,
+    entries: Vec<Entry>,
+    prefix: String, // the stack of the vm that imported this one
+}
+
+fn location(chunk: &Chunk) -> String {
+    match (&chunk.source, chunk.spans.first()) {
+        (Some(source), Some(span)) => {
+            format!("{}:{}", source.path.display(), source.line(span.start))
+        }
+        (Some(source), None) => source.path.display().to_string(),
+        _ => String::new(),
+    }
+}
+
+impl Profiler {
+    pub fn new(profile: Profile) -> Self {
+        Self {
+            profile,
+            entries: Vec::new(),
+            prefix: String::new(),
+        }
+    }
+
+    // for the vm running an import, which shows up under what imported it
+    pub fn fork(&self) -> Self {
+        Self {
+            profile: self.profile.clone(),
+            entries: Vec::new(),
+            prefix: self.stack(),
+        }
+    }
+
+    fn stack(&self) -> String {
+        match self.entries.last() {
+            Some(entry) => entry.stack.clone(),
+            None => self.prefix.clone(),
+        }
+    }
+
+    // catches up with calls and returns since the last instruction,
+    // a tail call replaces the top frame so it's a return and a call
+    pub fn sync(&mut self, frames: &[CallFrame], chunks: &[Chunk], instructions: u64) {
+        let mut keep = self.entries.len().min(frames.len());
+        if keep > 0 && self.entries[keep - 1].chunk_id != frames[keep - 1].chunk_id {
+            keep -= 1;
+        }
+
+        while self.entries.len() > keep {
+            self.pop(instructions);
+        }
+
+        for frame in &frames[self.entries.len()..] {
+            let chunk_id = frame.chunk_id;
+            let chunk = &chunks[chunk_id];
+            // the top of an imported module goes by its file instead of "main"
+            let name = match &chunk.source {
+                Some(source) if self.entries.is_empty() && !self.prefix.is_empty() => source
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| chunk.name.clone()),
+                _ => chunk.name.clone(),
+            };
+
+            let key = (name.clone(), location(chunk));
+            let recursive = self.entries.iter().any(|entry| entry.key == key);
+            let stack = match self.stack() {
+                stack if stack.is_empty() => name,
+                stack => format!("{};{}", stack, name),
+            };
+
+            self.entries.push(Entry {
+                chunk_id,
+                key,
+                stack,
+                start: Instant::now(),
+                instructions,
+                children: Duration::ZERO,
+                child_instructions: 0,
+                recursive,
+            });
+        }
+    }
+
+    fn pop(&mut self, instructions: u64) {
+        let Some(entry) = self.entries.pop() else {
+            return;
+        };
+
+        let elapsed = entry.start.elapsed();
+        let ran = instructions.saturating_sub(entry.instructions);
+        let own = elapsed.saturating_sub(entry.children);
+
+        if let Some(parent) = self.entries.last_mut() {
+            parent.children += elapsed;
+            parent.child_instructions += ran;
+        }
+
+        let mut data = self.profile.data.lock().unwrap();
+        *data.stacks.entry(entry.stack).or_default() += own;
+
+        let stats = data
+            .functions
+            .entry(entry.key.clone())
+            .or_insert_with(|| FunctionStats {
+                name: entry.key.0.clone(),
+                location: entry.key.1.clone(),
+                ..Default::default()
+            });
+
+        stats.calls += 1;
+        stats.own += own;
+        stats.own_instructions += ran.saturating_sub(entry.child_instructions);
+
+        if !entry.recursive {
+            stats.total += elapsed;
+            stats.instructions += ran;
+        }
+    }
+
+    // the run is over, whatever is still on the stack returns now
+    pub fn finish(&mut self, instructions: u64) {
+        while !self.entries.is_empty() {
+            self.pop(instructions);
+        }
+    }
+
+    // time the current function didn't spend itself, like running an import
+    pub fn exclude(&mut self, elapsed: Duration, instructions: u64) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.children += elapsed;
+            entry.child_instructions += instructions;
+        }
+    }
+
+    pub fn builtin(&mut self, name: &str, elapsed: Duration) {
+        self.exclude(elapsed, 0);
+
+        let stack = match self.stack() {
+            stack if stack.is_empty() => name.to_string(),
+            stack => format!("{};{}", stack, name),
+        };
+
+        let mut data = self.profile.data.lock().unwrap();
+        *data.stacks.entry(stack).or_default() += elapsed;
+
+        let stats = data
+            .functions
+            .entry((name.to_string(), "builtin".to_string()))
+            .or_insert_with(|| FunctionStats {
+                name: name.to_string(),
+                location: "builtin".to_string(),
+                builtin: true,
+                ..Default::default()
+            });
+
+        stats.calls += 1;
+        stats.total += elapsed;
+        stats.own += elapsed;
+    }
+}
@@ -0,0 +1,110 @@
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// where a vm's print() and input() go. the handles are shared with the
+// builtins, so swapping one out affects code that already imported things
+//
+//   let output = OutputBuffer::default();
+//   interpreter.set_stdout(output.clone());
+//   interpreter.eval("print(1);")?;
+//   assert_eq!(output.contents(), "1\n");
+#[derive(Clone)]
+pub struct Stdio {
+    stdout: Arc<Mutex<Box<dyn Write + Send>>>,
+    stderr: Arc<Mutex<Box<dyn Write + Send>>>,
+    stdin: Arc<Mutex<Box<dyn BufRead + Send>>>,
+}
+
+// a writer that panicked halfway is still better than no output at all
+fn lock<T: ?Sized>(handle: &Mutex<Box<T>>) -> MutexGuard<'_, Box<T>> {
+    handle.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Self {
+            stdout: Arc::new(Mutex::new(Box::new(std::io::stdout()))),
+            stderr: Arc::new(Mutex::new(Box::new(std::io::stderr()))),
+            stdin: Arc::new(Mutex::new(Box::new(std::io::BufReader::new(
+                std::io::stdin(),
+            )))),
+        }
+    }
+}
+
+impl Stdio {
+    pub fn set_stdout(&self, writer: impl Write + Send + 'static) {
+        *lock(&self.stdout) = Box::new(writer);
+    }
+
+    pub fn set_stderr(&self, writer: impl Write + Send + 'static) {
+        *lock(&self.stderr) = Box::new(writer);
+    }
+
+    pub fn set_stdin(&self, reader: impl BufRead + Send + 'static) {
+        *lock(&self.stdin) = Box::new(reader);
+    }
+
+    pub fn write(&self, text: &str) -> Result<(), String> {
+        let mut stdout = lock(&self.stdout);
+
+        stdout
+            .write_all(text.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|e| format!("failed to write to stdout: {}", e))
+    }
+
+    pub fn write_err(&self, text: &str) -> Result<(), String> {
+        let mut stderr = lock(&self.stderr);
+
+        stderr
+            .write_all(text.as_bytes())
+            .and_then(|_| stderr.flush())
+            .map_err(|e| format!("failed to write to stderr: {}", e))
+    }
+
+    // without the line ending, None once there is nothing left to read
+    pub fn read_line(&self) -> Result<Option<String>, String> {
+        let mut line = String::new();
+
+        let read = lock(&self.stdin)
+            .read_line(&mut line)
+            .map_err(|e| format!("failed to read from stdin: {}", e))?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+// a writer that keeps everything, clones write to the same buffer
+#[derive(Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes()).to_string()
+    }
+
+    // returns what was written so far and empties the buffer
+    pub fn take(&self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut *self.bytes())).to_string()
+    }
+
+    fn bytes(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
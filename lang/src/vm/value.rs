@@ -1,4 +1,6 @@
+use std::any::Any;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Clone, Debug)]
 pub enum Value {
@@ -18,8 +20,7 @@ pub enum Value {
     NativeFn(NativeFn),
     BuiltinFn(BuiltinFn),
 
-    FFILib(usize),
-    FFIFunc(usize, String), // (lib, fn name)
+    Native(NativeObject), // opaque host data, like files or ffi libs
 
     Range {
         start: i64,
@@ -29,12 +30,14 @@ pub enum Value {
 
     Class {
         name: String,
+        class_id: usize,
         methods: HashMap<String, Value>,
         parent_methods: HashMap<String, Value>,
     },
 
     Instance {
         class_name: String,
+        class_id: usize,
         properties: HashMap<String, Value>,
         parent_methods: HashMap<String, Value>,
     },
@@ -46,21 +49,153 @@ pub enum Value {
     },
 }
 
+// host functions and native data have to be thread safe, so a vm can be sent to another thread
+pub type HostFn = dyn Fn(Vec<Value>) -> Result<Value, String> + Send + Sync;
+
 #[derive(Clone)]
 pub struct BuiltinFn {
     pub name: String,
-    pub func: fn(Vec<Value>) -> Result<Value, String>,
+    pub func: Arc<HostFn>,
 }
 
 impl BuiltinFn {
-    pub fn new(name: &str, func: fn(Vec<Value>) -> Result<Value, String>) -> Self {
+    // plain fns work too, closures can capture whatever state they need
+    pub fn new(
+        name: &str,
+        func: impl Fn(Vec<Value>) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Self {
         Self {
             name: name.to_string(),
-            func,
+            func: Arc::new(func),
+        }
+    }
+}
+
+type Display = dyn Fn(&dyn Any) -> String + Send + Sync;
+type Getter = dyn Fn(&NativeObject, &str) -> Option<Value> + Send + Sync;
+type Finalizer = dyn FnOnce(&mut dyn Any) + Send + Sync;
+
+struct NativeInner {
+    type_name: &'static str,
+    owner: Option<usize>,
+    data: Box<dyn Any + Send + Sync>,
+    display: Option<Box<Display>>,
+    getter: Option<Box<Getter>>,
+    finalizer: Option<Box<Finalizer>>,
+}
+
+impl Drop for NativeInner {
+    fn drop(&mut self) {
+        if let Some(finalizer) = self.finalizer.take() {
+            finalizer(self.data.as_mut());
         }
     }
 }
 
+// clones share the same data, the finalizer runs once the last one is dropped
+#[derive(Clone)]
+pub struct NativeObject {
+    inner: Arc<NativeInner>,
+}
+
+impl NativeObject {
+    pub fn new<T: Any + Send + Sync>(type_name: &'static str, data: T) -> Self {
+        Self {
+            inner: Arc::new(NativeInner {
+                type_name,
+                owner: None,
+                data: Box::new(data),
+                display: None,
+                getter: None,
+                finalizer: None,
+            }),
+        }
+    }
+
+    // the with_ methods only work before the object is cloned
+    fn inner_mut(&mut self) -> &mut NativeInner {
+        Arc::get_mut(&mut self.inner).expect("native object was already shared")
+    }
+
+    // ties the object to one vm (see VM::id), other vms refuse to touch it
+    pub fn with_owner(mut self, vm_id: usize) -> Self {
+        self.inner_mut().owner = Some(vm_id);
+        self
+    }
+
+    pub fn with_display<T: Any>(
+        mut self,
+        display: impl Fn(&T) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.inner_mut().display = Some(Box::new(move |data| match data.downcast_ref::<T>() {
+            Some(data) => display(data),
+            None => String::new(),
+        }));
+        self
+    }
+
+    // lets modu code do `obj.name`, gets the object itself so it can be captured
+    pub fn with_getter(
+        mut self,
+        getter: impl Fn(&NativeObject, &str) -> Option<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.inner_mut().getter = Some(Box::new(getter));
+        self
+    }
+
+    pub fn with_finalizer<T: Any>(
+        mut self,
+        finalizer: impl FnOnce(&mut T) + Send + Sync + 'static,
+    ) -> Self {
+        self.inner_mut().finalizer = Some(Box::new(move |data| {
+            if let Some(data) = data.downcast_mut::<T>() {
+                finalizer(data);
+            }
+        }));
+        self
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.inner.type_name
+    }
+
+    pub fn owner(&self) -> Option<usize> {
+        self.inner.owner
+    }
+
+    // objects without an owner can be used anywhere
+    pub fn is_usable_by(&self, vm_id: usize) -> bool {
+        self.inner.owner.is_none_or(|owner| owner == vm_id)
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.inner.data.downcast_ref::<T>()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.inner.getter.as_ref()?(self, name)
+    }
+
+    pub fn ptr_eq(&self, other: &NativeObject) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl std::fmt::Display for NativeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.inner.display {
+            Some(display) => write!(f, "{}", display(self.inner.data.as_ref())),
+            None => write!(f, "<{}>", self.inner.type_name),
+        }
+    }
+}
+
+impl std::fmt::Debug for NativeObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native {}>", self.inner.type_name)
+    }
+}
+
 #[derive(Clone)]
 pub struct NativeFn {
     pub name: String,
@@ -102,10 +237,7 @@ impl PartialEq for Value {
 
             (Value::Array(a), Value::Array(b)) => a == b,
             (Value::Object(a), Value::Object(b)) => a == b,
-            (Value::FFILib(a), Value::FFILib(b)) => a == b,
-            (Value::FFIFunc(lib_a, name_a), Value::FFIFunc(lib_b, name_b)) => {
-                lib_a == lib_b && name_a == name_b
-            }
+            (Value::Native(a), Value::Native(b)) => a.ptr_eq(b),
 
             (
                 Value::Function {
@@ -220,8 +352,7 @@ impl std::fmt::Display for Value {
             Value::Function { chunk_id, arity } => write!(f, "<fn {}:{}>", chunk_id, arity),
             Value::NativeFn(native_fn) => write!(f, "{:?}", native_fn),
             Value::BuiltinFn(builtin_fn) => write!(f, "{:?}", builtin_fn),
-            Value::FFILib(lib_id) => write!(f, "<ffi lib {}>", lib_id),
-            Value::FFIFunc(lib_id, name) => write!(f, "<ffi func {} from lib {}>", name, lib_id),
+            Value::Native(object) => write!(f, "{}", object),
 
             Value::Range {
                 start,
@@ -310,14 +441,43 @@ impl Value {
             Value::NativeFn(_) => "native_fn",
             Value::BuiltinFn(_) => "builtin_fn",
             Value::Range { .. } => "range",
-            Value::FFILib(_) => "ffi_lib",
-            Value::FFIFunc(_, _) => "ffi_function",
+            Value::Native(object) => object.type_name(),
             Value::Class { .. } => "class",
             Value::Instance { .. } => "instance",
             Value::InstanceFn { .. } => "instance_fn",
         }
     }
 
+    // rough bytes used, shared things like host data arent counted
+    pub fn approx_size(&self) -> usize {
+        let map_size = |map: &HashMap<String, Value>| -> usize {
+            map.iter()
+                .map(|(k, v)| k.capacity() + v.approx_size())
+                .sum()
+        };
+
+        std::mem::size_of::<Value>()
+            + match self {
+                Value::String(s) => s.capacity(),
+                Value::Array(items) => items.iter().map(Value::approx_size).sum(),
+                Value::Object(properties) => map_size(properties),
+                Value::Class {
+                    name,
+                    methods,
+                    parent_methods,
+                    ..
+                } => name.capacity() + map_size(methods) + map_size(parent_methods),
+                Value::Instance {
+                    class_name,
+                    properties,
+                    parent_methods,
+                    ..
+                } => class_name.capacity() + map_size(properties) + map_size(parent_methods),
+                Value::InstanceFn { instance, .. } => instance.approx_size(),
+                _ => 0,
+            }
+    }
+
     pub fn add(&self, other: &Value) -> Result<Value, String> {
         match (self, other) {
             (Value::Int(a), Value::Int(b)) => Ok(Value::Int(
@@ -1,12 +1,23 @@
-use ariadne::{Color, Label, Report, ReportKind, Source};
 use chumsky::span::SimpleSpan;
 use colored::Colorize;
 use std::collections::HashMap;
-
-use super::chunk::Chunk;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::cache::{CacheHit, InlineCaches, NativeType};
+use super::coverage::{Coverage, Recorder};
+use super::profile::{Profile, Profiler};
+use super::chunk::{Chunk, SourceFile};
+use super::debug::{Frame, Hook};
 use super::instruction::Instruction;
-use super::value::Value;
+use super::stdio::Stdio;
+use super::value::{BuiltinFn, Value};
+use crate::ast::SpannedExpr;
 use crate::compiler::scope::Variable;
+use crate::error::{ErrorKind, ModuError};
+use crate::functions::ExitSignal;
+use crate::permissions::Permissions;
 
 pub struct ErrorHandler {
     catch_ip: usize,
@@ -15,12 +26,48 @@ pub struct ErrorHandler {
 }
 
 pub struct CallFrame {
-    chunk_id: usize,
+    pub(crate) chunk_id: usize,
     ip: usize,
     base: usize,
     self_target: Option<Variable>,
 }
 
+// the budgets are per run, so every eval or call starts with a fresh one
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_frames: usize,             // how deep calls can nest
+    pub max_stack: usize,              // how many values the stack can hold
+    pub max_instructions: Option<u64>, // how many instructions a run can execute
+    pub timeout: Option<Duration>,     // how long a run can take, not on wasm
+    pub max_memory: Option<usize>,     // roughly how many bytes values can take up, see memory_used
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_frames: FRAMES_MAX,
+            max_stack: STACK_MAX,
+            max_instructions: None,
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
+
+// stops whatever the vm it came from is running, from any thread
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle(Arc<AtomicBool>);
+
+impl InterruptHandle {
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
 pub struct VM {
     pub chunks: Vec<Chunk>,
     stack: Vec<Value>,
@@ -29,10 +76,41 @@ pub struct VM {
     source_path: std::path::PathBuf,
     source: String,
     error_handlers: Vec<ErrorHandler>,
+    limits: Limits,
+    caches: InlineCaches,
+    permissions: Arc<Permissions>,
+    interrupt: InterruptHandle,
+    id: usize,
+    instructions: u64,
+    deadline: Option<Instant>,
+    stdio: Stdio,
+    hook: Option<Box<dyn Hook>>,
+    coverage: Option<Recorder>,
+    profiler: Option<Profiler>,
+    exit: ExitSignal,
+    import_dir: Option<std::path::PathBuf>,
+    running: Arc<AtomicBool>,
+}
+
+// marks a vm as running until the run ends, even if it ends in a panic
+struct Running(Arc<AtomicBool>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
 }
 
-const STACK_MAX: usize = 2048;
-const FRAMES_MAX: usize = 256;
+// room for 256 values in each of the 256 frames. its only a ceiling, the stack
+// starts at 2048 values like it always has and grows when a script needs more
+pub const STACK_MAX: usize = 65536;
+pub const FRAMES_MAX: usize = 256;
+
+static NEXT_VM_ID: AtomicUsize = AtomicUsize::new(0);
+
+// how often the budgets that are slower to check get looked at
+const CHECK_INTERVAL: u64 = 1024;
+const MEMORY_CHECK_INTERVAL: u64 = 16384;
 
 fn find_closest(name: String, options: impl Iterator<Item = String>) -> Option<String> {
     let options: Vec<String> = options.collect();
@@ -49,28 +127,154 @@ fn find_closest(name: String, options: impl Iterator<Item = String>) -> Option<S
     if score > 0.75 { Some(best) } else { None }
 }
 
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f64 {
+    if hits + misses == 0 {
+        0.0
+    } else {
+        hits as f64 * 100.0 / (hits + misses) as f64
+    }
+}
+
+// finds the shortest sequence of calls repeating at the top of the call stack
+fn find_cycle<'a>(names: &[&'a str]) -> Option<(Vec<&'a str>, usize)> {
+    let n = names.len();
+
+    for len in 1..=(n / 2).min(16) {
+        let cycle = &names[n - len..];
+        let mut times = 0;
+
+        while (times + 1) * len <= n && &names[n - (times + 1) * len..n - times * len] == cycle {
+            times += 1;
+        }
+
+        if times >= 2 {
+            return Some((cycle.to_vec(), times));
+        }
+    }
+
+    None
+}
+
 impl VM {
     pub fn new(chunks: Vec<Chunk>, source_path: std::path::PathBuf, source: String) -> Self {
+        Self::with_limits(chunks, source_path, source, Limits::default())
+    }
+
+    pub fn with_limits(
+        chunks: Vec<Chunk>,
+        source_path: std::path::PathBuf,
+        source: String,
+        limits: Limits,
+    ) -> Self {
+        Self::with_stdio(chunks, source_path, source, limits, Stdio::default())
+    }
+
+    fn with_stdio(
+        chunks: Vec<Chunk>,
+        source_path: std::path::PathBuf,
+        source: String,
+        limits: Limits,
+        stdio: Stdio,
+    ) -> Self {
         let mut vm = Self {
             chunks,
-            stack: Vec::with_capacity(STACK_MAX),
-            frames: Vec::with_capacity(FRAMES_MAX),
+            stack: Vec::with_capacity(limits.max_stack.min(2048)),
+            frames: Vec::with_capacity(limits.max_frames.min(256)),
             globals: HashMap::new(),
             source_path,
             source,
             error_handlers: Vec::new(),
+            limits,
+            caches: InlineCaches::default(),
+            permissions: Arc::new(Permissions::default()),
+            interrupt: InterruptHandle::default(),
+            id: NEXT_VM_ID.fetch_add(1, Ordering::Relaxed),
+            instructions: 0,
+            deadline: None,
+            stdio,
+            hook: None,
+            coverage: None,
+            profiler: None,
+            exit: ExitSignal::default(),
+            import_dir: None,
+            running: Arc::default(),
         };
 
-        for func in crate::functions::get_functions() {
+        for func in crate::functions::get_functions(&vm.stdio, &vm.exit) {
             vm.globals.insert(func.name.clone(), Value::BuiltinFn(func));
         }
 
         vm
     }
 
-    pub fn run(&mut self, chunk_id: usize) -> Result<(), String> {
+    // modules imported before this keep the permissions they were imported with
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.permissions = Arc::new(permissions);
+    }
+
+    // handles like files are tied to the vm that made them
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // an interrupt only stops a run that is already going
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    // swap the handles in here to send output somewhere else
+    pub fn stdio(&self) -> &Stdio {
+        &self.stdio
+    }
+
+    pub fn run(&mut self, chunk_id: usize) -> Result<(), ModuError> {
+        self.run_value(chunk_id).map(|_| ())
+    }
+
+    // runs a chunk and returns whatever it left on top of the stack. a host
+    // function can't run code on the vm that called it, that run would
+    // clear the stack and frames the first one is still using
+    pub fn run_value(&mut self, chunk_id: usize) -> Result<Value, ModuError> {
+        if self.running.swap(true, Ordering::Relaxed) {
+            return Err(ModuError::new(
+                ErrorKind::Runtime,
+                "cannot run code while this interpreter is already running it",
+            )
+            .with_help("host functions can't call back into the interpreter that called them"));
+        }
+        let _running = Running(self.running.clone());
+
         let locals_count = self.chunks[chunk_id].locals_count;
 
+        // whatever an earlier run left behind is garbage now
+        self.stack.clear();
+        self.frames.clear();
+        self.error_handlers.clear();
+
+        self.interrupt.take();
+        self.instructions = 0;
+        self.deadline = self.limits.timeout.map(|timeout| Instant::now() + timeout);
+
         self.frames.push(CallFrame {
             chunk_id,
             ip: 0,
@@ -82,11 +286,221 @@ impl VM {
             self.stack.push(Value::Null);
         }
 
-        self.execute()
+        let result = self.execute().map(|_| {
+            if self.stack.len() > locals_count {
+                self.stack.pop().unwrap_or(Value::Null)
+            } else {
+                Value::Null
+            }
+        });
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.flush(&self.chunks);
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.finish(self.instructions);
+        }
+
+        if let Err(e) = &result
+            && let Some(mut hook) = self.hook.take()
+        {
+            hook.uncaught(self, e);
+            self.hook = Some(hook);
+        }
+
+        // so nothing left on the stack outlives the run, like open files
+        self.stack.clear();
+        self.frames.clear();
+
+        result
+    }
+
+    // calls any callable value from outside, by running a tiny chunk that does the call
+    pub fn call(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, ModuError> {
+        let span = SimpleSpan::from(0..0);
+        let argc = args.len();
+
+        let mut chunk = Chunk::new("<call>");
+        for value in std::iter::once(callee).chain(args) {
+            let index = chunk.constants.len();
+            chunk.constants.push(value);
+            chunk.emit(Instruction::Push(index), span);
+        }
+        chunk.emit(Instruction::Call(argc), span);
+
+        let chunk_id = self.chunks.len();
+        self.chunks.push(chunk);
+
+        let result = self.run_value(chunk_id);
+
+        // imports might have added chunks after it, those have to stay
+        if self.chunks.len() == chunk_id + 1 {
+            self.chunks.pop();
+        }
+
+        result
     }
 
-    fn execute(&mut self) -> Result<(), String> {
+    // adds chunks compiled with `offset` set to the current chunk count,
+    // returns the id of the first one
+    pub fn load(&mut self, chunks: Vec<Chunk>) -> usize {
+        let start = self.chunks.len();
+        self.chunks.extend(chunks);
+        start
+    }
+
+    // called as the vm runs, for debuggers and the like. imports
+    // run without it, but calls into what they defined don't
+    pub fn set_hook(&mut self, hook: impl Hook + 'static) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    pub fn take_hook(&mut self) -> Option<Box<dyn Hook>> {
+        self.hook.take()
+    }
+
+    // counts what runs into coverage, imports included
+    pub fn set_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(Recorder::new(coverage));
+    }
+
+    // times every call into profile, imports included
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profiler = Some(Profiler::new(profile));
+    }
+
+    // how many calls deep it is, the top level counts as one
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    // chunk and instruction the top frame is at, cheaper than frames()
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let frame = self.frames.last()?;
+        Some((frame.chunk_id, frame.ip.saturating_sub(1)))
+    }
+
+    // every value on the stack, the top is last
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    // the calls that are running, outermost first
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(depth, frame)| {
+                let chunk = &self.chunks[frame.chunk_id];
+                let ip = frame.ip.saturating_sub(1);
+                let span = chunk.spans.get(ip).copied().unwrap_or(SimpleSpan::from(0..0));
+
+                Frame {
+                    depth,
+                    chunk_id: frame.chunk_id,
+                    ip,
+                    name: chunk.name.clone(),
+                    span: span.start..span.end,
+                    source: chunk.source.clone(),
+                }
+            })
+            .collect()
+    }
+
+    // named locals of a frame, the compiler's own like __iter__ are left out
+    pub fn locals(&self, depth: usize) -> Vec<(String, Value)> {
+        let Some(frame) = self.frames.get(depth) else {
+            return Vec::new();
+        };
+
+        self.chunks[frame.chunk_id]
+            .local_names
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !name.starts_with("__"))
+            .filter_map(|(slot, name)| {
+                let value = self.stack.get(frame.base + slot)?;
+                Some((name.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    // runs code as if it was in a frame. variables, arrays and objects are
+    // copies so assigning to them doesn't stick, but native values like open
+    // files are shared with the program, and so are stdout and stdin
+    pub fn evaluate(&self, depth: usize, code: &str) -> Result<Value, ModuError> {
+        let ast =
+            crate::parser::parse_source(code, "<debug>").map_err(|mut errors| errors.remove(0))?;
+
+        let mut compiler = crate::compiler::compiler::Compiler::new();
+        compiler.offset = self.chunks.len();
+        compiler
+            .compile_eval(ast)
+            .map_err(|e| ModuError::new(ErrorKind::Compile, e))?;
+        compiler.attach_source(SourceFile::new("<debug>", code));
+
+        let mut vm = VM::with_stdio(
+            self.chunks.clone(),
+            self.source_path.clone(),
+            self.source.clone(),
+            self.limits,
+            self.stdio.clone(),
+        );
+        vm.permissions = self.permissions.clone();
+        vm.interrupt = self.interrupt.clone();
+        vm.import_dir = self.import_dir.clone();
+        vm.id = self.id;
+        vm.globals = self.globals.clone();
+        vm.globals.extend(self.locals(depth));
+
+        let start = vm.load(compiler.chunks);
+        vm.run_value(start)
+    }
+
+    // where relative imports resolve from, and what errors show by default
+    pub fn set_source(&mut self, source_path: std::path::PathBuf, source: String) {
+        self.source_path = source_path;
+        self.source = source;
+    }
+
+    // for code that isn't a file, where its relative imports resolve from
+    // instead of next to source_path. None goes back to source_path
+    pub fn set_import_dir(&mut self, dir: Option<std::path::PathBuf>) {
+        self.import_dir = dir;
+    }
+
+    fn execute(&mut self) -> Result<(), ModuError> {
         loop {
+            self.instructions += 1;
+
+            if self
+                .limits
+                .max_instructions
+                .is_some_and(|max| self.instructions > max)
+                || self.instructions.is_multiple_of(CHECK_INTERVAL)
+            {
+                self.check_budgets()?;
+            }
+
+            // calls make sure there's room up front, but one frame can keep pushing on
+            // its own, like a huge array literal, so the last instruction gets blamed
+            if self.stack.len() > self.limits.max_stack {
+                let frame = self.frames.last().unwrap();
+                let span = self.chunks[frame.chunk_id]
+                    .spans
+                    .get(frame.ip.saturating_sub(1))
+                    .cloned()
+                    .unwrap_or(SimpleSpan::from(0..0));
+
+                self.handle_error("stack overflow".to_string(), span)?;
+                continue;
+            }
+
+            if let Some(profiler) = &mut self.profiler {
+                profiler.sync(&self.frames, &self.chunks, self.instructions);
+            }
+
             let frame = self.frames.last_mut().unwrap();
 
             if frame.ip >= self.chunks[frame.chunk_id].instructions.len() {
@@ -104,8 +518,23 @@ impl VM {
                 .cloned()
                 .unwrap_or(SimpleSpan::from(0..0));
 
+            let site = (frame.chunk_id, frame.ip);
             frame.ip += 1;
 
+            if let Some(coverage) = &mut self.coverage {
+                coverage.hit(site);
+            }
+
+            let frame = if let Some(mut hook) = self.hook.take() {
+                let result = hook.instruction(self);
+                self.hook = Some(hook);
+                result?;
+
+                self.frames.last_mut().unwrap()
+            } else {
+                frame
+            };
+
             match instruction {
                 Instruction::Push(i) => {
                     let v = self.chunks[frame.chunk_id].constants[*i].clone();
@@ -132,6 +561,10 @@ impl VM {
                     let b = self.stack.pop().unwrap_or(Value::Null);
                     let a = self.stack.pop().unwrap_or(Value::Null);
 
+                    if let (Value::String(x), Value::String(y)) = (&a, &b) {
+                        self.check_allocation(x.len() + y.len())?;
+                    }
+
                     match a.add(&b) {
                         Ok(v) => self.stack.push(v),
                         Err(e) => {
@@ -158,6 +591,10 @@ impl VM {
                     let b = self.stack.pop().unwrap_or(Value::Null);
                     let a = self.stack.pop().unwrap_or(Value::Null);
 
+                    if let (Value::String(x), Value::Int(n)) = (&a, &b) {
+                        self.check_allocation(x.len().saturating_mul((*n).max(0) as usize))?;
+                    }
+
                     match a.mul(&b) {
                         Ok(v) => self.stack.push(v),
                         Err(e) => {
@@ -357,11 +794,15 @@ impl VM {
                     self.stack.push(Value::Bool(!a.truthy()));
                 }
 
-                Instruction::Call(argc) => {
+                Instruction::Call(argc) | Instruction::TailCall(argc) => {
                     let callee = self.stack[self.stack.len() - 1 - argc].clone();
 
-                    if self.frames.len() >= FRAMES_MAX {
-                        self.handle_error("stack overflow".to_string(), span)?;
+                    let reuse_frame = matches!(instruction, Instruction::TailCall(_))
+                        && matches!(callee, Value::Function { .. })
+                        && self.can_reuse_frame();
+
+                    if !reuse_frame && self.frames.len() >= self.limits.max_frames {
+                        self.recursion_error(span)?;
                         continue;
                     }
 
@@ -371,8 +812,10 @@ impl VM {
                                 self.stack.drain(self.stack.len() - argc..).collect();
                             self.stack.pop();
 
-                            match (func.func)(args) {
+                            let name = |_: &Self| func.name.clone();
+                            match self.call_builtin(&func, args, name, span)? {
                                 Ok(result) => self.stack.push(result),
+
                                 Err(e) => {
                                     // this isnt great lmfao but it works ig
                                     if func.name == "error"
@@ -400,10 +843,32 @@ impl VM {
                                 continue;
                             }
 
-                            let base = self.stack.len() - argc;
-
                             let extra_locals =
                                 self.chunks[chunk_id].locals_count.saturating_sub(*argc);
+
+                            if reuse_frame {
+                                // slide the callee and its args down over the current frame
+                                let base = self.frames.last().unwrap().base;
+                                let callee_slot = self.stack.len() - 1 - argc;
+                                self.stack.drain(base - 1..callee_slot);
+
+                                for _ in 0..extra_locals {
+                                    self.stack.push(Value::Null);
+                                }
+
+                                let frame = self.frames.last_mut().unwrap();
+                                frame.chunk_id = chunk_id;
+                                frame.ip = 0;
+                                continue;
+                            }
+
+                            if self.stack.len() + extra_locals > self.limits.max_stack {
+                                self.handle_error("stack overflow".to_string(), span)?;
+                                continue;
+                            }
+
+                            let base = self.stack.len() - argc;
+
                             for _ in 0..extra_locals {
                                 self.stack.push(Value::Null);
                             }
@@ -418,6 +883,7 @@ impl VM {
 
                         Value::Class {
                             name,
+                            class_id,
                             methods,
                             parent_methods,
                         } => {
@@ -427,6 +893,7 @@ impl VM {
 
                             let instance = Value::Instance {
                                 class_name: name.clone(),
+                                class_id,
                                 properties: methods.clone(),
                                 parent_methods: parent_methods.clone(),
                             };
@@ -498,8 +965,8 @@ impl VM {
                 } => {
                     let callee = self.stack[self.stack.len() - 1 - argc].clone();
 
-                    if self.frames.len() >= FRAMES_MAX {
-                        self.handle_error("stack overflow".to_string(), span)?;
+                    if self.frames.len() >= self.limits.max_frames {
+                        self.recursion_error(span)?;
                         continue;
                     }
 
@@ -575,8 +1042,18 @@ impl VM {
                             let args = self.stack.drain(self.stack.len() - argc..).collect();
                             self.stack.pop();
                             let obj = self.stack.pop().unwrap_or(Value::Null);
+                            let profiled = self.profiler.is_some().then(|| {
+                                let name = format!("{}.{}", obj.type_name(), func.name);
+                                (name, Instant::now())
+                            });
+
+                            let result = (func.func)(obj, args);
+
+                            if let Some((name, started)) = profiled {
+                                self.profile_builtin(&name, started);
+                            }
 
-                            match (func.func)(obj, args) {
+                            match result {
                                 Ok(result) => {
                                     if let Some(replace_self) = result.1 {
                                         if let Some(target) = target_local {
@@ -602,6 +1079,7 @@ impl VM {
 
                         Value::Class {
                             name,
+                            class_id,
                             methods,
                             parent_methods,
                         } => {
@@ -611,6 +1089,7 @@ impl VM {
 
                             let instance = Value::Instance {
                                 class_name: name.clone(),
+                                class_id,
                                 properties: methods.clone(),
                                 parent_methods: parent_methods.clone(),
                             };
@@ -670,28 +1149,14 @@ impl VM {
                             let args = self.stack.drain(self.stack.len() - argc..).collect();
                             self.stack.pop();
 
-                            match (func.func)(args) {
+                            let name =
+                                |vm: &Self| vm.method_name(target_local, target_global, &func.name);
+                            match self.call_builtin(&func, args, name, span)? {
                                 Ok(result) => self.stack.push(result),
-                                Err(e) => {
-                                    self.handle_error(
-                                        format!("error calling {}(): {}", func.name, e),
-                                        span,
-                                    )?;
-                                    continue;
-                                }
-                            }
-                        }
 
-                        #[cfg(not(target_arch = "wasm32"))]
-                        Value::FFIFunc(lib_idx, func_name) => {
-                            let args = self.stack.drain(self.stack.len() - argc..).collect();
-                            self.stack.pop();
-
-                            match crate::stdlib::ffi::call_ffi(lib_idx, &func_name, args) {
-                                Ok(result) => self.stack.push(result),
                                 Err(e) => {
                                     self.handle_error(
-                                        format!("error calling {}(): {}", func_name, e),
+                                        format!("error calling {}(): {}", func.name, e),
                                         span,
                                     )?;
                                     continue;
@@ -942,7 +1407,33 @@ impl VM {
                 }
 
                 Instruction::GetProperty(name) => {
-                    let target = self.stack.last().unwrap_or(&Value::Null).clone();
+                    let target = self.stack.last().unwrap_or(&Value::Null);
+
+                    // an objects own properties always win over its natives
+                    let own_property = matches!(
+                        target,
+                        Value::Object(properties) if properties.contains_key(name)
+                    );
+
+                    if !own_property && let Some(hit) = self.caches.lookup(site, target) {
+                        match hit {
+                            CacheHit::Native(method) => self.stack.push(Value::NativeFn(method)),
+
+                            CacheHit::Method { chunk_id, arity } => {
+                                let inst = self.stack.pop().unwrap();
+
+                                self.stack.push(Value::InstanceFn {
+                                    instance: Box::new(inst),
+                                    chunk_id,
+                                    arity,
+                                });
+                            }
+                        }
+
+                        continue;
+                    }
+
+                    let target = target.clone();
 
                     match target {
                         Value::Object(properties) => {
@@ -955,7 +1446,11 @@ impl VM {
                                 None => {
                                     let method =
                                         match crate::natives::object::get_fn(name.to_string()) {
-                                            Some(m) => m,
+                                            Some(m) => {
+                                                let kind = NativeType::Object;
+                                                self.caches.fill_native(site, kind, m.clone());
+                                                m
+                                            }
                                             None => {
                                                 let props = crate::natives::object::list_fns()
                                                     .into_iter()
@@ -996,7 +1491,10 @@ impl VM {
 
                         Value::String(_) => {
                             let method = match crate::natives::string::get_fn(name.to_string()) {
-                                Some(m) => m,
+                                Some(m) => {
+                                    self.caches.fill_native(site, NativeType::String, m.clone());
+                                    m
+                                }
                                 None => {
                                     let closest = find_closest(
                                         name.clone(),
@@ -1026,7 +1524,10 @@ impl VM {
 
                         Value::Int(_) => {
                             let method = match crate::natives::int::get_fn(name.to_string()) {
-                                Some(m) => m,
+                                Some(m) => {
+                                    self.caches.fill_native(site, NativeType::Int, m.clone());
+                                    m
+                                }
                                 None => {
                                     let closest = find_closest(
                                         name.clone(),
@@ -1056,7 +1557,10 @@ impl VM {
 
                         Value::Float(_) => {
                             let method = match crate::natives::float::get_fn(name.to_string()) {
-                                Some(m) => m,
+                                Some(m) => {
+                                    self.caches.fill_native(site, NativeType::Float, m.clone());
+                                    m
+                                }
                                 None => {
                                     let closest = find_closest(
                                         name.clone(),
@@ -1086,7 +1590,10 @@ impl VM {
 
                         Value::Array(_) => {
                             let method = match crate::natives::array::get_fn(name.to_string()) {
-                                Some(m) => m,
+                                Some(m) => {
+                                    self.caches.fill_native(site, NativeType::Array, m.clone());
+                                    m
+                                }
                                 None => {
                                     let closest = find_closest(
                                         name.clone(),
@@ -1116,12 +1623,16 @@ impl VM {
 
                         Value::Instance {
                             class_name,
+                            class_id,
                             properties,
                             ..
                         } => {
                             if let Some(v) = properties.get(name) {
                                 match v {
                                     Value::Function { chunk_id, arity } => {
+                                        self.caches
+                                            .fill_method(site, name, class_id, *chunk_id, *arity);
+
                                         let inst = self.stack.pop().unwrap();
 
                                         self.stack.push(Value::InstanceFn {
@@ -1164,12 +1675,36 @@ impl VM {
                             }
                         }
 
-                        #[cfg(not(target_arch = "wasm32"))]
-                        Value::FFILib(idx) => {
-                            self.stack.pop();
-                            self.stack.push(Value::FFIFunc(idx, name.clone()));
+                        Value::Native(object) if !object.is_usable_by(self.id) => {
+                            self.handle_error(
+                                format!(
+                                    "cannot use {} from another interpreter",
+                                    object.type_name()
+                                ),
+                                span,
+                            )?;
+                            continue;
                         }
 
+                        Value::Native(object) => match object.get(name) {
+                            Some(value) => {
+                                self.stack.pop();
+                                self.stack.push(value);
+                            }
+
+                            None => {
+                                self.handle_error(
+                                    format!(
+                                        "undefined property '{}' on {}",
+                                        name,
+                                        object.type_name()
+                                    ),
+                                    span,
+                                )?;
+                                continue;
+                            }
+                        },
+
                         _ => {
                             self.handle_error(
                                 format!("cannot get property '{}' of {}", name, target.type_name()),
@@ -1202,12 +1737,16 @@ impl VM {
 
                         Value::Instance {
                             class_name,
+                            class_id,
                             mut properties,
                             parent_methods,
                         } => {
+                            self.caches.mark_dynamic(class_id, name);
+
                             properties.insert(name.clone(), value);
                             Value::Instance {
                                 class_name,
+                                class_id,
                                 properties,
                                 parent_methods,
                             }
@@ -1340,7 +1879,31 @@ impl VM {
                     if path.starts_with("std/") {
                         let path = path.strip_prefix("std/").unwrap().to_string();
 
-                        if let Some(module) = crate::stdlib::get(&path) {
+                        if let Err(e) = self.permissions.check_module(&path) {
+                            self.handle_error(e, span)?;
+                            continue;
+                        }
+
+                        let context = crate::stdlib::Context {
+                            permissions: self.permissions.clone(),
+                            owner: self.id,
+                        };
+
+                        if let Some(mut module) = crate::stdlib::get(&path, &context) {
+                            // some modules are partly written in modu, like std/test
+                            if let Some(source) = crate::stdlib::source(&path)
+                                && let Value::Object(members) = &mut module
+                            {
+                                let ast = crate::parser::parse_source(source, &path)
+                                    .map_err(|errors| errors[0].message.clone())?;
+
+                                members.extend(self.run_module(
+                                    ast,
+                                    std::path::PathBuf::from(format!("std/{}", path)),
+                                    source.to_string(),
+                                )?);
+                            }
+
                             if let Some(alias) = alias {
                                 if alias == "*" {
                                     if let Value::Object(properties) = module {
@@ -1360,12 +1923,21 @@ impl VM {
                             self.handle_error(format!("unknown stdlib module '{}'", path), span)?;
                             continue;
                         }
+                    } else if let Some(name) = path.strip_prefix("native:") {
+                        let resolved = self
+                            .import_dir()
+                            .join(crate::extension::file_name(name));
+
+                        match self.import_native(&resolved) {
+                            Ok(module) => {
+                                let name = crate::extension::default_name(&resolved);
+                                self.bind_import(module, alias.as_ref(), name);
+                            }
+
+                            Err(e) => self.handle_error(e, span)?,
+                        }
                     } else {
-                        let current_dir = self
-                            .source_path
-                            .parent()
-                            .map(|p| p.to_path_buf())
-                            .unwrap_or_else(|| std::path::PathBuf::from("."));
+                        let current_dir = self.import_dir();
 
                         let is_package = !path.contains("/") && !path.ends_with(".modu");
 
@@ -1394,6 +1966,23 @@ impl VM {
                             current_dir.join(&with_ext)
                         };
 
+                        // a package can be a native extension instead of modu code
+                        if is_package && let Some(native) = package_native(&resolved) {
+                            match self.import_native(&native) {
+                                Ok(module) => {
+                                    self.bind_import(module, alias.as_ref(), path.clone())
+                                }
+                                Err(e) => self.handle_error(e, span)?,
+                            }
+
+                            continue;
+                        }
+
+                        if let Err(e) = self.permissions.check_read(&resolved) {
+                            self.handle_error(e, span)?;
+                            continue;
+                        }
+
                         let absolute = resolved.canonicalize().map_err(|_| {
                             format!(
                                 "cannot find module '{}' (looked for {})",
@@ -1405,30 +1994,21 @@ impl VM {
                         let source = std::fs::read_to_string(&resolved)
                             .map_err(|e| format!("cannot read '{}': {}", resolved.display(), e))?;
 
-                        let ast = crate::parser::parse(&source, &resolved.display().to_string());
-
-                        if ast.is_err() {
-                            self.handle_error("failed to parse package".to_string(), span)?;
-                            continue;
-                        }
-
-                        let mut compiler = crate::compiler::compiler::Compiler::new();
-                        compiler.compile_program(ast.clone().unwrap())?;
-
-                        let mut vm = VM::new(compiler.chunks, absolute, source);
-                        vm.run(0)?;
-
-                        let chunk_offset = self.chunks.len();
-                        self.chunks.extend(vm.chunks);
-
-                        let exports: HashMap<String, Value> = vm
-                            .globals
-                            .into_iter()
-                            .filter(|(_, v)| !matches!(v, Value::BuiltinFn(_)))
-                            .map(|(k, v)| (k, remap(v, chunk_offset)))
-                            .collect();
+                        let ast = match crate::parser::parse_source(
+                            &source,
+                            &resolved.display().to_string(),
+                        ) {
+                            Ok(ast) => ast,
+                            Err(errors) => {
+                                self.handle_error(
+                                    format!("failed to parse package: {}", errors[0]),
+                                    span,
+                                )?;
+                                continue;
+                            }
+                        };
 
-                        let module = Value::Object(exports);
+                        let module = Value::Object(self.run_module(ast, absolute, source)?);
 
                         if let Some(alias) = alias {
                             if alias == "*" {
@@ -1471,7 +2051,10 @@ impl VM {
                     match (child, parent) {
                         (
                             Value::Class {
-                                name, mut methods, ..
+                                name,
+                                class_id,
+                                mut methods,
+                                ..
                             },
                             Value::Class {
                                 methods: parent_methods,
@@ -1486,6 +2069,7 @@ impl VM {
 
                             self.stack.push(Value::Class {
                                 name,
+                                class_id,
                                 methods,
                                 parent_methods: saved_methods,
                             });
@@ -1571,10 +2155,15 @@ impl VM {
 
                 Instruction::JumpIfFalse(offset) => {
                     let condition = self.stack.pop().unwrap_or(Value::Null);
+                    let jumped = !condition.truthy();
 
-                    if !condition.truthy() {
+                    if jumped {
                         frame.ip = *offset;
                     }
+
+                    if let Some(coverage) = &mut self.coverage {
+                        coverage.branch(site, jumped);
+                    }
                 }
 
                 Instruction::Jump(offset) => {
@@ -1600,36 +2189,359 @@ impl VM {
         }
     }
 
-    fn runtime_error(&self, msg: String, span: SimpleSpan) -> String {
-        let filename = self.source_path.to_string_lossy().to_string();
+    // these skip try/catch, or a script could just catch them and keep going
+    fn check_budgets(&self) -> Result<(), ModuError> {
+        if self.interrupt.take() {
+            return Err(self.budget_error(ErrorKind::Interrupted, "interrupted".to_string()));
+        }
+
+        if let Some(max) = self.limits.max_instructions
+            && self.instructions > max
+        {
+            return Err(self.budget_error(
+                ErrorKind::Budget,
+                format!("instruction limit exceeded ({} instructions)", max),
+            ));
+        }
+
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.limits.timeout)
+            && Instant::now() >= deadline
+        {
+            return Err(self.budget_error(
+                ErrorKind::Budget,
+                format!("time limit exceeded ({:?})", timeout),
+            ));
+        }
+
+        if let Some(max) = self.limits.max_memory
+            && self.instructions.is_multiple_of(MEMORY_CHECK_INTERVAL)
+        {
+            let used = self.memory_used();
+
+            if used > max {
+                return Err(self
+                    .budget_error(
+                        ErrorKind::Budget,
+                        format!(
+                            "memory limit exceeded (about {} used, the limit is {})",
+                            format_bytes(used),
+                            format_bytes(max)
+                        ),
+                    )
+                    .with_help(
+                        "only values the script holds count, not native handles like files \
+                         or what host functions keep",
+                    ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // walks every value on the stack and in globals, so only done every so often.
+    // native objects like files are opaque and count as just their handle, and
+    // neither compiled code nor whatever host functions hold on to counts at all
+    pub fn memory_used(&self) -> usize {
+        self.stack
+            .iter()
+            .chain(self.globals.values())
+            .map(Value::approx_size)
+            .sum()
+    }
+
+    // big strings can blow past the limit in one go, before the next check
+    fn check_allocation(&self, bytes: usize) -> Result<(), ModuError> {
+        match self.limits.max_memory {
+            Some(max) if bytes > max => Err(self.budget_error(
+                ErrorKind::Budget,
+                format!(
+                    "memory limit exceeded (tried to allocate {}, the limit is {})",
+                    format_bytes(bytes),
+                    format_bytes(max)
+                ),
+            )),
+
+            _ => Ok(()),
+        }
+    }
+
+    fn budget_error(&self, kind: ErrorKind, msg: String) -> ModuError {
+        let span = self
+            .frames
+            .last()
+            .and_then(|f| {
+                let spans = &self.chunks[f.chunk_id].spans;
+                spans.get(f.ip.saturating_sub(1).min(spans.len().saturating_sub(1)))
+            })
+            .cloned()
+            .unwrap_or(SimpleSpan::from(0..0));
+
+        let mut err = self.runtime_error(msg, None, span);
+        err.kind = kind;
+        err
+    }
+
+    fn runtime_error(&self, msg: String, help: Option<String>, span: SimpleSpan) -> ModuError {
+        let file = self
+            .frames
+            .last()
+            .and_then(|f| self.chunks.get(f.chunk_id))
+            .and_then(|c| c.source.clone())
+            .unwrap_or_else(|| SourceFile::new(&self.source_path, &self.source));
+
+        let mut err = ModuError::new(ErrorKind::Runtime, msg).at(span.start..span.end, file);
+        err.help = help;
+        err
+    }
+
+    pub fn enable_cache_stats(&mut self) {
+        self.caches.enable_stats();
+    }
+
+    // one line per property access site, most used first
+    pub fn cache_stats(&self) -> Option<String> {
+        let stats = self.caches.stats()?;
+
+        let mut sites: Vec<_> = stats.iter().collect();
+        sites.sort_by_key(|(site, s)| (std::cmp::Reverse(s.hits + s.misses), **site));
+
+        let (hits, misses) = sites
+            .iter()
+            .fold((0, 0), |(h, m), (_, s)| (h + s.hits, m + s.misses));
+
+        let mut out = format!(
+            "inline cache: {} hits, {} misses ({:.1}% hit rate)\n",
+            hits,
+            misses,
+            hit_rate(hits, misses)
+        );
+
+        for ((chunk_id, ip), s) in sites {
+            let property = match &self.chunks[*chunk_id].instructions[*ip] {
+                Instruction::GetProperty(name) => name.as_str(),
+                _ => "?",
+            };
+
+            out.push_str(&format!(
+                "  {:<24} {:04} .{:<16} {:>8} hits {:>6} misses ({:.1}%)\n",
+                self.chunks[*chunk_id].name,
+                ip,
+                property,
+                s.hits,
+                s.misses,
+                hit_rate(s.hits, s.misses)
+            ));
+        }
+
+        Some(out)
+    }
+
+    // a frame can only be replaced if nothing still needs it once the callee returns
+    fn can_reuse_frame(&self) -> bool {
+        let frame = self.frames.last().unwrap();
+
+        frame.base > 0
+            && frame.self_target.is_none()
+            && self
+                .error_handlers
+                .last()
+                .is_none_or(|h| h.frame_depth < self.frames.len())
+    }
+
+    fn recursion_error(&mut self, span: SimpleSpan) -> Result<(), ModuError> {
+        let names: Vec<&str> = self
+            .frames
+            .iter()
+            .map(|f| self.chunks[f.chunk_id].name.as_str())
+            .collect();
+
+        let help = match find_cycle(&names) {
+            Some((cycle, times)) => {
+                let mut path = cycle.to_vec();
+                path.push(cycle[0]);
+
+                format!(
+                    "recursion cycle: {} (repeated {} times)",
+                    path.join(" -> ").yellow(),
+                    times
+                )
+            }
+
+            None => format!(
+                "most recent calls: {}",
+                names[names.len().saturating_sub(5)..].join(" -> ").yellow()
+            ),
+        };
+
+        self.handle_error_with_help(
+            format!(
+                "maximum recursion depth exceeded ({} frames)",
+                self.limits.max_frames
+            ),
+            help,
+            span,
+        )
+    }
+
+    // where relative imports are looked up, next to the running file
+    fn import_dir(&self) -> std::path::PathBuf {
+        if let Some(dir) = &self.import_dir {
+            return dir.clone();
+        }
+
+        self.source_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_else(|| std::path::PathBuf::from("."))
+    }
+
+    // runs modu code for an import in a vm of its own and takes its chunks over,
+    // what it leaves in globals is the module
+    fn run_module(
+        &mut self,
+        ast: Vec<SpannedExpr>,
+        path: std::path::PathBuf,
+        source: String,
+    ) -> Result<HashMap<String, Value>, ModuError> {
+        let mut compiler = crate::compiler::compiler::Compiler::new();
+        compiler.compile_program(ast)?;
+        compiler.attach_source(SourceFile::new(&path, &source));
+
+        // the import spends from the same budget as the code importing it
+        let mut limits = self.limits;
+        limits.max_instructions = limits
+            .max_instructions
+            .map(|max| max.saturating_sub(self.instructions));
+        limits.timeout = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+        let mut vm = VM::with_stdio(compiler.chunks, path, source, limits, self.stdio.clone());
+        vm.permissions = self.permissions.clone();
+        vm.interrupt = self.interrupt.clone();
+        vm.id = self.id;
+        vm.coverage = self.coverage.as_ref().map(Recorder::fork);
+        vm.profiler = self.profiler.as_ref().map(Profiler::fork);
+
+        // Instant::now panics on wasm, so only when someone's profiling
+        let started = self.profiler.is_some().then(Instant::now);
+        let result = vm.run(0);
+        self.instructions += vm.instructions;
+
+        // the module's own functions are in the profile already
+        if let (Some(profiler), Some(started)) = (&mut self.profiler, started) {
+            profiler.exclude(started.elapsed(), vm.instructions);
+        }
+        result?;
+
+        self.caches.merge_dynamic(&vm.caches);
+
+        let chunk_offset = self.chunks.len();
+        self.chunks.extend(vm.chunks);
+
+        Ok(vm
+            .globals
+            .into_iter()
+            .filter(|(_, v)| !matches!(v, Value::BuiltinFn(_)))
+            .map(|(k, v)| (k, remap(v, chunk_offset)))
+            .collect())
+    }
+
+    fn import_native(&self, path: &std::path::Path) -> Result<Value, String> {
+        self.permissions.check_native()?;
+        self.permissions.check_read(path)?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let absolute = path.canonicalize().map_err(|_| {
+                format!(
+                    "cannot find native extension (looked for {})",
+                    path.display()
+                )
+            })?;
+
+            crate::extension::load(&absolute)
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        Err("native extensions are not supported in the browser".to_string())
+    }
+
+    fn bind_import(&mut self, module: Value, alias: Option<&String>, default: String) {
+        match (alias, module) {
+            (Some(alias), Value::Object(properties)) if alias == "*" => {
+                for (key, value) in properties {
+                    self.globals.insert(key, value);
+                }
+            }
+
+            (Some(alias), module) => {
+                self.globals.insert(alias.clone(), module);
+            }
+
+            (None, module) => {
+                self.globals.insert(default, module);
+            }
+        }
+    }
+
+    // the one place host functions run. exit() only leaves its status on the
+    // vm, this turns it into an ErrorKind::Exit that unwinds everything instead
+    // of killing the process, try/catch can't stop it either
+    fn call_builtin(
+        &mut self,
+        func: &BuiltinFn,
+        args: Vec<Value>,
+        name: impl FnOnce(&Self) -> String,
+        span: SimpleSpan,
+    ) -> Result<Result<Value, String>, ModuError> {
+        let started = self.profiler.is_some().then(Instant::now);
+        let result = (func.func)(args);
 
-        let mut buf: Vec<u8> = Vec::new();
-        Report::build(ReportKind::Error, (&filename, span.start..span.end))
-            .with_message(msg)
-            .with_label(Label::new((&filename, span.start..span.end)).with_color(Color::Red))
-            .finish()
-            .write((&filename, Source::from(self.source.clone())), &mut buf)
-            .ok();
+        if let Some(started) = started {
+            let name = name(self);
+            self.profile_builtin(&name, started);
+        }
 
-        String::from_utf8_lossy(&buf).to_string()
+        if let Some(code) = self.exit.take() {
+            let mut err = self.runtime_error(format!("exit({})", code), None, span);
+            // a status is a byte, -1 ends up as 255 like in a shell
+            err.kind = ErrorKind::Exit(code as u8);
+            return Err(err);
+        }
+
+        Ok(result)
     }
 
-    fn runtime_error_with_help(&self, msg: String, help: String, span: SimpleSpan) -> String {
-        let filename = self.source_path.to_string_lossy().to_string();
+    fn profile_builtin(&mut self, name: &str, started: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.builtin(name, started.elapsed());
+        }
+    }
 
-        let mut buf: Vec<u8> = Vec::new();
-        Report::build(ReportKind::Error, (&filename, span.start..span.end))
-            .with_message(msg)
-            .with_label(Label::new((&filename, span.start..span.end)).with_color(Color::Red))
-            .with_help(help)
-            .finish()
-            .write((&filename, Source::from(self.source.clone())), &mut buf)
-            .ok();
+    // `http.get` when it was called on a variable, just `get` otherwise
+    fn method_name(
+        &self,
+        target_local: &Option<usize>,
+        target_global: &Option<String>,
+        name: &str,
+    ) -> String {
+        let target = match (target_local, target_global) {
+            (Some(slot), _) => self
+                .frames
+                .last()
+                .and_then(|frame| self.chunks[frame.chunk_id].local_names.get(*slot)),
+            (_, Some(global)) => Some(global),
+            _ => None,
+        };
 
-        String::from_utf8_lossy(&buf).to_string()
+        match target {
+            Some(target) => format!("{}.{}", target, name),
+            None => name.to_string(),
+        }
     }
 
-    fn handle_error(&mut self, msg: String, span: SimpleSpan) -> Result<(), String> {
+    fn handle_error(&mut self, msg: String, span: SimpleSpan) -> Result<(), ModuError> {
         if let Some(handler) = self.error_handlers.pop() {
             self.stack.truncate(handler.stack_depth);
 
@@ -1645,7 +2557,7 @@ impl VM {
 
             Ok(())
         } else {
-            Err(self.runtime_error(msg, span))
+            Err(self.runtime_error(msg, None, span))
         }
     }
 
@@ -1654,7 +2566,7 @@ impl VM {
         msg: String,
         help: String,
         span: SimpleSpan,
-    ) -> Result<(), String> {
+    ) -> Result<(), ModuError> {
         if let Some(handler) = self.error_handlers.pop() {
             self.stack.truncate(handler.stack_depth);
 
@@ -1670,7 +2582,7 @@ impl VM {
 
             Ok(())
         } else {
-            Err(self.runtime_error_with_help(msg, help, span))
+            Err(self.runtime_error(msg, Some(help), span))
         }
     }
 }
@@ -1693,10 +2605,12 @@ fn remap(value: Value, offset: usize) -> Value {
 
         Value::Class {
             name,
+            class_id,
             methods,
             parent_methods,
         } => Value::Class {
             name,
+            class_id,
             methods: methods
                 .into_iter()
                 .map(|(k, v)| (k, remap(v, offset)))
@@ -1709,10 +2623,12 @@ fn remap(value: Value, offset: usize) -> Value {
 
         Value::Instance {
             class_name,
+            class_id,
             properties,
             parent_methods,
         } => Value::Instance {
             class_name,
+            class_id,
             properties: properties
                 .into_iter()
                 .map(|(k, v)| (k, remap(v, offset)))
@@ -1736,3 +2652,13 @@ fn remap(value: Value, offset: usize) -> Value {
         v => v,
     }
 }
+
+// the library a package's project.toml points at with `native = "..."`, if any
+fn package_native(lib: &std::path::Path) -> Option<std::path::PathBuf> {
+    let dir = lib.parent()?;
+    let manifest = std::fs::read_to_string(dir.join("project.toml")).ok()?;
+    let manifest = toml::from_str::<toml::Value>(&manifest).ok()?;
+    let native = manifest.get("package")?.get("native")?.as_str()?;
+
+    Some(dir.join(crate::extension::file_name(native)))
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::scratch;
+use modu::{ErrorKind, Interpreter, Limits, Value};
+use predicates::prelude::*;
+use std::fs;
+use std::time::{Duration, Instant};
+
+fn limited(limits: Limits) -> Interpreter {
+    Interpreter::with_limits(limits)
+}
+
+#[test]
+fn instruction_limit() {
+    let mut modu = limited(Limits {
+        max_instructions: Some(10_000),
+        ..Limits::default()
+    });
+
+    let err = modu
+        .eval("let n = 0;\nloop {\n    n = n + 1;\n}")
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Budget);
+    assert_eq!(
+        err.message,
+        "instruction limit exceeded (10000 instructions)"
+    );
+    assert_eq!(err.location().map(|(line, _)| line), Some(3));
+
+    // try/catch doesnt get a say
+    let err = modu
+        .eval("loop {\n    try {\n        loop {}\n    } catch e {}\n}")
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Budget);
+
+    // every eval gets a fresh budget
+    assert_eq!(modu.eval("1 + 1;").unwrap(), Value::Int(2));
+    assert!(matches!(modu.get_global("n"), Some(Value::Int(n)) if n > 1000));
+}
+
+#[test]
+fn timeout() {
+    let mut modu = limited(Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    });
+
+    let start = Instant::now();
+    let err = modu.eval("loop {}").unwrap_err();
+
+    assert_eq!(err.kind, ErrorKind::Budget);
+    assert_eq!(err.message, "time limit exceeded (50ms)");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn memory_limit() {
+    let mut modu = limited(Limits {
+        max_memory: Some(1024 * 1024),
+        ..Limits::default()
+    });
+
+    // doubling gets caught before the big allocation happens
+    let err = modu
+        .eval("let s = \"ab\";\nloop {\n    s = s + s;\n}")
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Budget);
+    assert!(
+        err.message
+            .starts_with("memory limit exceeded (tried to allocate")
+    );
+
+    let err = modu.eval("\"x\" * 100000000;").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Budget);
+
+    // slow growth shows up in the periodic check
+    let mut modu = limited(Limits {
+        max_memory: Some(64 * 1024),
+        ..Limits::default()
+    });
+
+    let err = modu
+        .eval("let items = [];\nloop {\n    items.push(\"some text\");\n}")
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Budget);
+    assert!(
+        err.message.starts_with("memory limit exceeded (about"),
+        "{}",
+        err.message
+    );
+    assert!(err.help.unwrap().contains("not native handles"));
+}
+
+#[test]
+fn stack_limit() {
+    let mut modu = limited(Limits {
+        max_stack: 64,
+        ..Limits::default()
+    });
+
+    // no calls at all, just one expression that needs more than 64 values
+    let items = (0..100)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let err = modu.eval(&format!("[{}];", items)).unwrap_err();
+    assert_eq!(err.message, "stack overflow");
+
+    // unlike the budgets it can be caught
+    let caught = modu
+        .eval(&format!(
+            "let msg = null;\ntry {{ [{}]; }} catch e {{ msg = e; }}\nmsg;",
+            items
+        ))
+        .unwrap();
+    assert_eq!(caught, Value::String("stack overflow".to_string()));
+}
+
+#[test]
+fn interrupt_from_another_thread() {
+    let mut modu = Interpreter::new();
+    let handle = modu.interrupt_handle();
+
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+
+    let err = modu
+        .eval("let i = 0;\nloop {\n    i = i + 1;\n}")
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Interrupted);
+    interrupter.join().unwrap();
+
+    // the interrupt is used up, the next run is fine
+    assert_eq!(modu.eval("i > 0;").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn cli_flags() {
+    let dir = scratch("budgets", "cli_flags");
+    let file = dir.join("main.modu");
+    fs::write(&file, "print(\"start\");\nloop {}\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("run")
+        .arg(&file)
+        .args(["--max-instructions", "5000"])
+        .assert()
+        .code(1)
+        .stdout("start\n")
+        .stderr(predicate::str::contains(
+            "instruction limit exceeded (5000 instructions)",
+        ));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("run")
+        .arg(&file)
+        .arg("--timeout=0.05")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("time limit exceeded (50ms)"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,122 @@
+// builds examples/c/embed.c against the cdylib and runs it
+#![cfg(unix)]
+
+mod common;
+
+use common::scratch;
+use modu::capi::*;
+use std::ffi::{CStr, CString, c_void};
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_example() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // cargo test builds the cdylib in deps, it only gets copied up on cargo build
+    let libs = Path::new(env!("CARGO_BIN_EXE_modu"))
+        .parent()
+        .unwrap()
+        .join("deps");
+    let dir = scratch("capi", "embed");
+    let binary = dir.join("embed");
+
+    let compiled = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(manifest.join("examples/c/embed.c"))
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg("-L")
+        .arg(&libs)
+        .arg("-lmodu")
+        .arg(format!("-Wl,-rpath,{}", libs.display()))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-o")
+        .arg(&binary)
+        .status()
+        .expect("a c compiler is needed for this test");
+
+    assert!(compiled.success());
+
+    let output = Command::new(&binary).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "result: 20 (int)
+caught: error calling sum(): sum() only adds ints
+debug: true (bool)
+name: modu (string)
+port: 8080 (int)
+greet: hello c (string)
+error: undefined variable 'undefined_thing' at 2:5 (bytes 15..30)
+sum() was called 2 times
+"
+    );
+}
+
+#[test]
+fn native_handles() {
+    let code = CString::new("import \"std/fs\";\nfs.open(\"Cargo.toml\", \"r\");").unwrap();
+
+    unsafe {
+        let modu = modu_new();
+        let file = modu_eval(modu, code.as_ptr());
+        assert!(!file.is_null());
+
+        assert_eq!(modu_type(file), ModuType::Native);
+        assert_eq!(CStr::from_ptr(modu_type_name(file)).to_str(), Ok("file"));
+
+        modu_value_free(file);
+        modu_free(modu);
+    }
+}
+
+// evals on the interpreter it was registered on, while that one is running it
+unsafe extern "C" fn reenter(
+    user_data: *mut c_void,
+    _args: *const *const ModuValue,
+    _argc: usize,
+) -> *mut ModuValue {
+    let modu = user_data as *mut ModuInterpreter;
+    let code = CString::new("1;").unwrap();
+
+    unsafe {
+        let result = modu_eval(modu, code.as_ptr());
+        if result.is_null() {
+            return modu_string(modu_error_message(modu));
+        }
+        result
+    }
+}
+
+#[test]
+fn reentrant_eval() {
+    let name = CString::new("reenter").unwrap();
+    let code = CString::new("reenter();").unwrap();
+
+    unsafe {
+        let modu = modu_new();
+        assert!(modu_register_fn(
+            modu,
+            name.as_ptr(),
+            reenter,
+            modu as *mut c_void
+        ));
+
+        let result = modu_eval(modu, code.as_ptr());
+        assert!(!result.is_null());
+        assert_eq!(
+            CStr::from_ptr(modu_as_string(result)).to_str(),
+            Ok("cannot run code while this interpreter is already running it")
+        );
+        modu_value_free(result);
+
+        // and once it's done running it can be used again
+        let result = modu_eval(modu, code.as_ptr());
+        assert!(!result.is_null());
+        modu_value_free(result);
+
+        modu_free(modu);
+    }
+}
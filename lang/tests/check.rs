@@ -0,0 +1,103 @@
+mod common;
+
+use common::{modu, scratch};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn follows_imports_without_running() {
+    let dir = scratch("check", "follows_imports_without_running");
+    fs::create_dir_all(dir.join("src/lib")).unwrap();
+
+    fs::write(
+        dir.join("src/main.modu"),
+        "import \"lib/util.modu\" as util;
+import \"std/math\" as math;
+import \"std/nope\" as nope;
+import \"missing.modu\" as missing;
+
+const LIMIT = 10;
+LIMIT = 11;
+print(\"ran\");
+",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("src/lib/util.modu"),
+        "import \"helper.modu\";\nconst A = 1;\nfn f() {\n    A = 2;\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.join("src/lib/helper.modu"), "break;\nlet a = ;\n").unwrap();
+
+    let output = modu(&dir, &["check", "src/main.modu", "--format", "json"]);
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("ran"));
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let found: Vec<(String, u64, &str)> = report
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| {
+            let file = e["file"].as_str().unwrap();
+            let file = Path::new(file).file_name().unwrap().to_string_lossy();
+            (
+                file.to_string(),
+                e["line"].as_u64().unwrap(),
+                e["code"].as_str().unwrap(),
+            )
+        })
+        .collect();
+
+    let expected = [
+        ("main.modu", 3, "E0007"),
+        ("main.modu", 4, "E0007"),
+        ("main.modu", 7, "E0006"),
+        ("util.modu", 3, "E0006"),
+        ("helper.modu", 1, "E0005"),
+        ("helper.modu", 2, "E0003"),
+    ];
+    assert_eq!(
+        found,
+        expected.map(|(file, line, code)| (file.to_string(), line, code))
+    );
+
+    assert_eq!(report[0]["message"], "cannot find module 'std/nope'");
+    assert_eq!(report[0]["column"], 8);
+    assert_eq!(report[2]["message"], "cannot assign to constant 'LIMIT'");
+
+    // every file once, even when it's named and imported
+    let output = modu(&dir, &["check", "src"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("6 errors in 3 files"));
+    assert!(stderr.contains("[E0006]"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn clean_files_pass() {
+    let dir = scratch("check", "clean_files_pass");
+
+    fs::write(
+        dir.join("main.modu"),
+        "import \"std/json\" as json;\nprint(json.stringify([1]));\n",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["check"]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+
+    let output = modu(&dir, &["check", "--format=json"]);
+    assert!(output.status.success());
+    assert_eq!(
+        serde_json::from_slice::<Value>(&output.stdout).unwrap(),
+        Value::Array(Vec::new())
+    );
+
+    assert!(!modu(&dir, &["check", "nope.modu"]).status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
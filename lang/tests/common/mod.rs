@@ -0,0 +1,40 @@
+// helpers shared by the integration tests, pulled in with `mod common;`.
+// not every test file uses all of them
+#![allow(dead_code)]
+
+use modu::{Interpreter, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// an empty temp dir of its own, so tests running in parallel don't collide
+pub fn scratch(prefix: &str, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("modu_{}_{}_{}", prefix, name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+// the modu binary run from dir, without colors so output compares as plain text
+pub fn modu(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_modu"))
+        .args(args)
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap()
+}
+
+// runs code that should throw and hands back what it threw
+pub fn error_of(modu: &mut Interpreter, code: &str) -> String {
+    let code = format!(
+        "let err = null;\ntry {{\n{}\n}} catch e {{\nerr = e;\n}}\nerr;",
+        code
+    );
+
+    match modu.eval(&code).unwrap() {
+        Value::String(s) => s,
+        other => panic!("expected an error, got {}", other),
+    }
+}
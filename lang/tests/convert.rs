@@ -0,0 +1,154 @@
+use modu::{Interpreter, Value, from_value, to_value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Server {
+    host: String,
+    port: u16,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Fast,
+    Limited(u32),
+    Custom { workers: u8 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    debug: Option<bool>,
+    ratio: f64,
+    servers: Vec<Server>,
+    modes: Vec<Mode>,
+    limits: HashMap<String, i64>,
+}
+
+#[test]
+fn read_struct_from_modu() {
+    let mut modu = Interpreter::new();
+
+    modu.eval(
+        r#"
+        let config = {
+            "name": "prod",
+            "debug": null,
+            "ratio": 1,
+            "servers": [
+                { "host": "a.local", "port": 80, "tags": ["web"] },
+                { "host": "b.local", "port": 8080, "tags": [] },
+            ],
+            "modes": ["Fast", { "Limited": 3 }, { "Custom": { "workers": 4 } }],
+            "limits": { "cpu": 2 },
+        };
+        "#,
+    )
+    .unwrap();
+
+    let config: Config = modu.get_global_as("config").unwrap();
+
+    assert_eq!(config.name, "prod");
+    assert_eq!(config.debug, None);
+    assert_eq!(config.ratio, 1.0);
+    assert_eq!(config.servers[1].port, 8080);
+    assert_eq!(
+        config.modes,
+        vec![Mode::Fast, Mode::Limited(3), Mode::Custom { workers: 4 }]
+    );
+    assert_eq!(config.limits["cpu"], 2);
+}
+
+#[test]
+fn round_trip_through_modu() {
+    let mut modu = Interpreter::new();
+
+    let server = Server {
+        host: "localhost".to_string(),
+        port: 3000,
+        tags: vec!["dev".to_string()],
+    };
+
+    modu.set_global_from("server", &server).unwrap();
+    modu.eval(
+        r#"
+        let tags = server.tags;
+        tags.push("local");
+        server.port = server.port + 1;
+        server.tags = tags;
+        "#,
+    )
+    .unwrap();
+
+    let server: Server = modu.get_global_as("server").unwrap();
+    assert_eq!(server.port, 3001);
+    assert_eq!(server.tags, vec!["dev", "local"]);
+
+    let value = to_value(&Mode::Custom { workers: 2 }).unwrap();
+    assert_eq!(
+        from_value::<Mode>(value).unwrap(),
+        Mode::Custom { workers: 2 }
+    );
+}
+
+#[test]
+fn errors_name_the_modu_path() {
+    let mut modu = Interpreter::new();
+
+    modu.eval(
+        r#"
+        let config = {
+            "name": "prod",
+            "ratio": 0.5,
+            "servers": [
+                { "host": "a", "port": 1, "tags": [] },
+                { "host": "b", "port": 2, "tags": [] },
+                { "host": "c", "port": "http", "tags": [] },
+            ],
+            "modes": [],
+            "limits": {},
+        };
+        "#,
+    )
+    .unwrap();
+
+    let err = modu.get_global_as::<Config>("config").unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("config.servers[2].port"));
+    assert!(
+        err.to_string()
+            .starts_with("config.servers[2].port: invalid type")
+    );
+
+    modu.eval("config.servers[2].port = 70000;").unwrap();
+    let err = modu.get_global_as::<Config>("config").unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("config.servers[2].port"));
+
+    modu.eval("config.servers[2] = { \"host\": \"c\", \"tags\": [] };")
+        .unwrap();
+    let err = modu.get_global_as::<Config>("config").unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("config.servers[2]"));
+    assert!(err.message.contains("missing field `port`"));
+}
+
+#[test]
+fn values_serialize_to_json() {
+    let mut modu = Interpreter::new();
+
+    let value = modu
+        .eval("let o = { \"b\": [1, 2.5, \"x\"], \"a\": null };\no;")
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        r#"{"a":null,"b":[1,2.5,"x"]}"#
+    );
+
+    let parsed: Value = serde_json::from_str(r#"{"n": 1, "list": [true]}"#).unwrap();
+    assert_eq!(
+        from_value::<HashMap<String, Value>>(parsed).unwrap().len(),
+        2
+    );
+
+    let func = modu.eval("fn f() {}\nf;").unwrap();
+    assert!(serde_json::to_string(&func).is_err());
+}
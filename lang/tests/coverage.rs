@@ -0,0 +1,151 @@
+mod common;
+
+use common::{modu, scratch};
+use std::fs;
+
+// imported by both projects, so coverage covers more than one file
+const UTIL: &str = "fn sign(n) {
+    if n < 0 {
+        return -1;
+    }
+    return 1;
+}
+
+fn unused() {
+    print(\"never\");
+}
+";
+
+// the DA and BRDA lines of one file in a tracefile
+fn record(lcov: &str, file: &str) -> Vec<String> {
+    let start = lcov
+        .find(&format!("SF:{}", file))
+        .unwrap_or_else(|| panic!("no record for {} in\n{}", file, lcov));
+
+    lcov[start..]
+        .lines()
+        .take_while(|line| *line != "end_of_record")
+        .filter(|line| line.starts_with("DA:") || line.starts_with("BRDA:"))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn run_coverage() {
+    let dir = scratch("coverage", "run");
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib/util.modu"), UTIL).unwrap();
+
+    fs::write(
+        dir.join("main.modu"),
+        "import \"lib/util.modu\" as util;
+
+let total = 0;
+for i in 0..3 {
+    total = total + util.sign(i);
+}
+
+if total > 100 {
+    print(\"big\");
+} else {
+    print(total);
+}
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["run", "main.modu", "--coverage=out.lcov"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("lib/util.modu        4/6 66.7%        1/2 50.0%  3, 9"));
+    assert!(stderr.contains("main.modu            6/7 85.7%        3/4 75.0%  9"));
+    assert!(stderr.contains("total              10/13 76.9%        4/6 66.7%"));
+
+    let lcov = fs::read_to_string(dir.join("out.lcov")).unwrap();
+    let root = dir.canonicalize().unwrap();
+
+    // imported code is counted under its own file
+    assert_eq!(
+        record(&lcov, &root.join("lib/util.modu").display().to_string()),
+        [
+            "BRDA:2,0,0,0",
+            "BRDA:2,0,1,3",
+            "DA:1,1",
+            "DA:2,3",
+            "DA:3,0",
+            "DA:5,3",
+            "DA:8,1",
+            "DA:9,0"
+        ]
+    );
+    assert_eq!(
+        record(&lcov, &root.join("main.modu").display().to_string()),
+        [
+            "BRDA:4,0,0,3",
+            "BRDA:4,0,1,1",
+            "BRDA:8,0,0,0",
+            "BRDA:8,0,1,1",
+            "DA:1,1",
+            "DA:3,1",
+            "DA:4,4",
+            "DA:5,3",
+            "DA:8,1",
+            "DA:9,0",
+            "DA:11,1"
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_coverage() {
+    let dir = scratch("coverage", "test");
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("lib/util.modu"), UTIL).unwrap();
+
+    fs::write(
+        dir.join("util_test.modu"),
+        "import \"lib/util.modu\" as util;
+import \"std/test\" as t;
+
+fn test_negative() {
+    t.assert_eq(util.sign(-5), -1);
+}
+
+fn test_positive() {
+    t.assert_eq(util.sign(5), 1);
+}
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["test", "--coverage"]);
+    assert!(output.status.success());
+
+    // the runs of every test add up, and std/test isn't part of it
+    let lcov = fs::read_to_string(dir.join("lcov.info")).unwrap();
+    let util = dir.canonicalize().unwrap().join("lib/util.modu");
+    assert!(!lcov.contains("std/test"));
+    assert_eq!(
+        record(&lcov, &util.display().to_string()),
+        [
+            "BRDA:2,0,0,1",
+            "BRDA:2,0,1,1",
+            "DA:1,2",
+            "DA:2,2",
+            "DA:3,1",
+            "DA:5,1",
+            "DA:8,2",
+            "DA:9,0"
+        ]
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("lib/util.modu"));
+    assert!(stderr.contains("wrote lcov.info"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
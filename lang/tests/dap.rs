@@ -0,0 +1,329 @@
+mod common;
+
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const PROGRAM: &str = "fn add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+
+let x = 1;
+let y = add(x, 2);
+let list = [1, 2];
+print(y);
+let z = y * 2;
+print(z);
+";
+
+fn scratch(name: &str, code: &str) -> PathBuf {
+    let dir = common::scratch("dap", name);
+    fs::write(dir.join("main.modu"), code).unwrap();
+
+    dir.canonicalize().unwrap()
+}
+
+// just enough of an editor to drive `modu dap`
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    seq: i64,
+    pending: VecDeque<Value>, // read while waiting for something else
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_modu"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        Client {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            seq: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut line = String::new();
+            assert!(self.stdout.read_line(&mut line).unwrap() > 0, "dap exited");
+
+            match line.trim().strip_prefix("Content-Length:") {
+                Some(n) => length = n.trim().parse().unwrap(),
+                None if line.trim().is_empty() => break,
+                None => {}
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn next(&mut self, matches: impl Fn(&Value) -> bool) -> Value {
+        if let Some(i) = self.pending.iter().position(&matches) {
+            return self.pending.remove(i).unwrap();
+        }
+
+        loop {
+            let message = self.read();
+
+            if matches(&message) {
+                return message;
+            }
+
+            self.pending.push_back(message);
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+
+        let body = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+
+        let seq = self.seq;
+        self.next(|m| m["type"] == "response" && m["request_seq"] == seq)
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        self.next(|m| m["type"] == "event" && m["event"] == event)
+    }
+
+    fn output(&mut self) -> String {
+        self.pending
+            .iter()
+            .filter(|m| m["event"] == "output")
+            .map(|m| m["body"]["output"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn launch(&mut self, dir: &Path, breakpoints: &[i64], stop_on_entry: bool) {
+        let init = self.request("initialize", json!({ "adapterID": "modu" }));
+        assert_eq!(init["success"], true);
+        assert_eq!(init["body"]["supportsConfigurationDoneRequest"], true);
+        self.event("initialized");
+
+        let program = dir.join("main.modu");
+        let launch = self.request(
+            "launch",
+            json!({ "program": program, "stopOnEntry": stop_on_entry }),
+        );
+        assert_eq!(launch["success"], true);
+
+        let lines: Vec<Value> = breakpoints.iter().map(|l| json!({ "line": l })).collect();
+        let set = self.request(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": lines }),
+        );
+        assert_eq!(
+            set["body"]["breakpoints"].as_array().unwrap().len(),
+            breakpoints.len()
+        );
+
+        self.request(
+            "setExceptionBreakpoints",
+            json!({ "filters": ["uncaught"] }),
+        );
+        assert_eq!(
+            self.request("configurationDone", json!({}))["success"],
+            true
+        );
+    }
+
+    fn finish(mut self) {
+        assert_eq!(self.request("disconnect", json!({}))["success"], true);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[test]
+fn breakpoints_and_variables() {
+    let dir = scratch("breakpoints", PROGRAM);
+    let mut client = Client::start();
+
+    client.launch(&dir, &[2], false);
+
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["body"]["reason"], "breakpoint");
+
+    let threads = client.request("threads", json!({}));
+    assert_eq!(threads["body"]["threads"][0]["id"], 1);
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["body"]["stackFrames"].as_array().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0]["name"], "add");
+    assert_eq!(frames[0]["line"], 2);
+    assert_eq!(frames[0]["column"], 15);
+    assert_eq!(frames[0]["source"]["name"], "main.modu");
+    assert_eq!(frames[1]["name"], "main");
+    assert_eq!(frames[1]["line"], 7);
+
+    let frame = frames[0]["id"].clone();
+    let scopes = client.request("scopes", json!({ "frameId": frame }));
+    let locals = scopes["body"]["scopes"][0]["variablesReference"].clone();
+
+    let variables = client.request("variables", json!({ "variablesReference": locals }));
+    let names: Vec<String> = variables["body"]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            format!(
+                "{}={}",
+                v["name"].as_str().unwrap(),
+                v["value"].as_str().unwrap()
+            )
+        })
+        .collect();
+    assert_eq!(names, ["a=1", "b=2", "sum=null"]);
+
+    let eval = client.request(
+        "evaluate",
+        json!({ "expression": "a + b * 10", "frameId": frame }),
+    );
+    assert_eq!(eval["body"]["result"], "21");
+
+    let eval = client.request(
+        "evaluate",
+        json!({ "expression": "nope", "frameId": frame }),
+    );
+    assert_eq!(eval["success"], false);
+
+    // out to the caller, then over a line
+    client.request("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "step");
+
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "step");
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["body"]["stackFrames"][0]["line"], 9);
+
+    // arrays can be opened up
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let globals = scopes["body"]["scopes"][1]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": globals }));
+    let list = variables["body"]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|v| v["name"] == "list")
+        .unwrap()
+        .clone();
+    assert_eq!(list["value"], "[1, 2]");
+
+    let items = client.request(
+        "variables",
+        json!({ "variablesReference": list["variablesReference"] }),
+    );
+    assert_eq!(items["body"]["variables"][1]["value"], "2");
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("exited")["body"]["exitCode"], 0);
+    client.event("terminated");
+    assert_eq!(client.output(), "3\n6\n");
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stop_on_entry_and_breakpoints_while_running() {
+    let dir = scratch("entry", PROGRAM);
+    let mut client = Client::start();
+
+    client.launch(&dir, &[], true);
+    assert_eq!(client.event("stopped")["body"]["reason"], "entry");
+
+    let set = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": dir.join("main.modu") }, "breakpoints": [{ "line": 10 }] }),
+    );
+    assert_eq!(set["body"]["breakpoints"][0]["verified"], true);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["body"]["reason"], "breakpoint");
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["body"]["stackFrames"][0]["line"], 10);
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn uncaught_errors() {
+    let dir = scratch("error", "let a = 1;\nlet b = a + \"x\";\nprint(b);\n");
+    let mut client = Client::start();
+
+    client.launch(&dir, &[], false);
+
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["body"]["reason"], "exception");
+    assert!(!stopped["body"]["text"].as_str().unwrap().is_empty());
+
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["body"]["stackFrames"][0]["line"], 2);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("exited")["body"]["exitCode"], 1);
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pausing_a_running_program() {
+    let dir = scratch(
+        "pause",
+        "let i = 0;\nprint(\"running\");\nloop {\n    i = i + 1;\n}\n",
+    );
+    let mut client = Client::start();
+
+    client.launch(&dir, &[], false);
+    client.event("output");
+
+    // not paused yet, so there is nothing to look at
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    assert_eq!(trace["success"], false);
+
+    let pause = client.request("pause", json!({ "threadId": 1 }));
+    assert_eq!(pause["success"], true);
+    assert_eq!(client.event("stopped")["body"]["reason"], "pause");
+
+    // somewhere in the loop, it may not have gone around yet
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let line = trace["body"]["stackFrames"][0]["line"].as_i64().unwrap();
+    assert!(line == 3 || line == 4);
+
+    let eval = client.request("evaluate", json!({ "expression": "i >= 0" }));
+    assert_eq!(eval["body"]["result"], "true");
+
+    // disconnecting ends the loop
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
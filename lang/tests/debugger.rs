@@ -0,0 +1,224 @@
+mod common;
+
+use modu::debugger::{Breakpoints, Debugger, Frontend, Reason, Resume};
+use modu::vm::vm::VM;
+use modu::{ErrorKind, Interpreter, Value};
+use predicates::prelude::*;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+const PROGRAM: &str = "fn add(a, b) {
+    let sum = a + b;
+    return sum;
+}
+
+let x = 1;
+let y = add(x, 2);
+for i in 0..3 {
+    x = x + i;
+}
+print(y);
+";
+
+fn scratch(name: &str, code: &str) -> PathBuf {
+    let dir = common::scratch("debugger", name);
+    fs::write(dir.join("main.modu"), code).unwrap();
+
+    dir
+}
+
+// resumes the way it was told to and writes down every stop
+struct Script {
+    resumes: VecDeque<Resume>,
+    stops: Arc<Mutex<Vec<String>>>,
+    eval: Option<&'static str>,
+}
+
+impl Frontend for Script {
+    fn paused(&mut self, vm: &VM, reason: &Reason, _: &mut Breakpoints) -> Resume {
+        let frame = vm.frames().pop().unwrap();
+
+        let mut stop = match reason {
+            Reason::Entry => "entry",
+            Reason::Breakpoint => "breakpoint",
+            Reason::Step => "step",
+            Reason::Pause => "pause",
+            Reason::Error(_) => "error",
+        }
+        .to_string();
+
+        stop += &format!(" {} {}", frame.name, frame.line().unwrap());
+
+        if let Some(code) = self.eval {
+            match vm.evaluate(frame.depth, code) {
+                Ok(value) => stop += &format!(" = {}", value),
+                Err(e) => stop += &format!(" ! {}", e.message),
+            }
+        }
+
+        self.stops.lock().unwrap().push(stop);
+        self.resumes.pop_front().unwrap_or(Resume::Continue)
+    }
+}
+
+fn debug(
+    name: &str,
+    code: &str,
+    resumes: &[Resume],
+    configure: impl FnOnce(Debugger<Script>) -> Debugger<Script>,
+) -> (Result<Value, modu::ModuError>, Vec<String>) {
+    let dir = scratch(name, code);
+    let stops = Arc::new(Mutex::new(Vec::new()));
+
+    let script = Script {
+        resumes: resumes.iter().copied().collect(),
+        stops: stops.clone(),
+        eval: None,
+    };
+
+    let mut modu = Interpreter::new();
+    modu.set_stdout(modu::OutputBuffer::default());
+    modu.vm().set_hook(configure(Debugger::new(script)));
+
+    let result = modu.eval_file(dir.join("main.modu"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stops = stops.lock().unwrap().clone();
+    (result, stops)
+}
+
+#[test]
+fn stepping() {
+    use Resume::*;
+
+    let (result, stops) = debug(
+        "stepping",
+        PROGRAM,
+        &[
+            StepOver, StepOver, StepIn, StepIn, StepOut, StepOver, StepOver,
+        ],
+        |d| d,
+    );
+
+    result.unwrap();
+    assert_eq!(
+        stops,
+        [
+            "entry main 1",
+            "step main 6",
+            "step main 7",
+            "step add 2",
+            "step add 3",
+            "step main 8",
+            "step main 9",
+            "step main 8",
+        ]
+    );
+}
+
+#[test]
+fn breakpoints() {
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add("main.modu", 9);
+    breakpoints.add("main.modu", 3);
+
+    let (result, stops) = debug("breakpoints", PROGRAM, &[], |d| {
+        d.stop_on_entry(false).with_breakpoints(breakpoints)
+    });
+
+    result.unwrap();
+
+    // every time around the loop
+    assert_eq!(
+        stops,
+        [
+            "breakpoint add 3",
+            "breakpoint main 9",
+            "breakpoint main 9",
+            "breakpoint main 9",
+        ]
+    );
+}
+
+#[test]
+fn evaluating_in_a_frame() {
+    let dir = scratch("evaluate", PROGRAM);
+    let stops = Arc::new(Mutex::new(Vec::new()));
+
+    let mut breakpoints = Breakpoints::default();
+    breakpoints.add("main.modu", 3);
+
+    let script = Script {
+        resumes: VecDeque::new(),
+        stops: stops.clone(),
+        eval: Some("sum * 10 + x;"),
+    };
+
+    let mut modu = Interpreter::new();
+    modu.vm().set_hook(
+        Debugger::new(script)
+            .stop_on_entry(false)
+            .with_breakpoints(breakpoints),
+    );
+    modu.eval_file(dir.join("main.modu")).unwrap();
+
+    // locals and globals both, and the program doesnt see anything of it
+    assert_eq!(*stops.lock().unwrap(), ["breakpoint add 3 = 31"]);
+    assert_eq!(modu.get_global("y"), Some(Value::Int(3)));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn uncaught_errors() {
+    let code = "let x = 0;\ntry {\n    x = 1 / 0;\n} catch e {\n    x = 2;\n}\nlet y = x / 0;\n";
+
+    let (result, stops) = debug("errors", code, &[], |d| d.stop_on_entry(false));
+    assert_eq!(result.unwrap_err().message, "division by zero");
+    assert_eq!(stops, ["error main 7"]);
+
+    let (result, stops) = debug("no_errors", code, &[], |d| {
+        d.stop_on_entry(false).break_on_error(false)
+    });
+    assert!(result.is_err());
+    assert!(stops.is_empty());
+}
+
+#[test]
+fn stopping() {
+    let (result, stops) = debug("stop", PROGRAM, &[Resume::Stop], |d| d);
+
+    assert_eq!(result.unwrap_err().kind, ErrorKind::Interrupted);
+    assert_eq!(stops, ["entry main 1"]);
+}
+
+#[test]
+fn cli_session() {
+    let dir = scratch("cli", PROGRAM);
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .env("NO_COLOR", "1")
+        .args(["debug", "main.modu"])
+        .write_stdin("break 2\ncontinue\nbacktrace\nlocals\nprint a * 100\nout\nprint y\nquit\n")
+        .assert()
+        .stdout(predicate::str::contains(
+            "Breakpoint set at main.modu:2
+(modu) Paused at main.modu:2 (breakpoint)
+   2 |     let sum = a + b;
+(modu) > #0 add at main.modu:2
+  #1 main at main.modu:7
+(modu) a = 1
+b = 2
+sum = null
+(modu) 100
+(modu) Paused at main.modu:8
+   8 | for i in 0..3 {
+(modu) 3
+(modu) Program stopped",
+        ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
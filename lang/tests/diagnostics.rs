@@ -0,0 +1,84 @@
+mod common;
+
+use common::{modu, scratch};
+use modu::error::{ModuError, Severity};
+use modu::parser::parse_source;
+use std::fs;
+
+fn errors(code: &str) -> Vec<ModuError> {
+    parse_source(code, "main.modu").err().unwrap_or_default()
+}
+
+// (line, code) of every error
+fn found(code: &str) -> Vec<(usize, u16)> {
+    errors(code)
+        .iter()
+        .map(|e| (e.location().unwrap().0, e.code.unwrap()))
+        .collect()
+}
+
+#[test]
+fn recovers_at_statements_and_blocks() {
+    let code = "fn g() {
+    let x = ;
+    if x {
+        let z = ;
+    }
+    return 1;
+}
+let y = ;
+print(g());
+";
+
+    assert_eq!(found(code), [(2, 3), (4, 3), (8, 3)]);
+}
+
+#[test]
+fn every_kind_at_once() {
+    let code = "let a = 1 @ 2;
+print(a);
+break;
+let b = [1, 2;
+}
+fn f() {
+    continue;
+}
+return;
+";
+
+    assert_eq!(
+        found(code),
+        [(1, 1), (3, 5), (4, 3), (5, 3), (7, 5), (9, 4)]
+    );
+
+    let errors = errors(code);
+    assert!(errors.iter().all(|e| e.severity == Severity::Error));
+    assert_eq!(errors[3].message, "There is no block for this '}' to close");
+}
+
+#[test]
+fn good_code_still_parses() {
+    assert!(parse_source("fn f(a) {\n    return a;\n}\nprint(f(1));\n", "main.modu").is_ok());
+    assert!(parse_source("", "main.modu").is_ok());
+}
+
+#[test]
+fn all_rendered_in_one_go() {
+    let errors = errors("let a = ;\nlet b = ;\nbreak;\n");
+    assert_eq!(errors.len(), 3);
+
+    let rendered = ModuError::render_all(&errors);
+    assert!(rendered.contains("[E0003]"));
+    assert!(rendered.contains("[E0005]"));
+
+    let dir = scratch("diagnostics", "all_rendered_in_one_go");
+    fs::write(dir.join("main.modu"), "let a = ;\nprint(1);\nlet b = ;\n").unwrap();
+
+    let output = modu(&dir, &["run", "main.modu"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("[E0003]").count(), 2);
+    assert!(output.stdout.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use common::scratch;
+use modu::vm::value::NativeObject;
+use modu::{ErrorKind, Interpreter, Value};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+#[test]
+fn eval_returns_last_expression() {
+    let mut modu = Interpreter::new();
+
+    assert_eq!(
+        modu.eval("let x = 20;\nx * 2 + 2;").unwrap(),
+        Value::Int(42)
+    );
+    assert_eq!(modu.eval("let y = 1;").unwrap(), Value::Null);
+}
+
+#[test]
+fn globals_persist_between_evals() {
+    let mut modu = Interpreter::new();
+
+    modu.eval("fn add(a, b) { return a + b; }").unwrap();
+    modu.eval("class Counter { fn init() { self.n = 0; } }")
+        .unwrap();
+    modu.eval("let total = add(1, 2);").unwrap();
+
+    assert_eq!(modu.get_global("total"), Some(Value::Int(3)));
+    assert_eq!(modu.eval("add(total, 4);").unwrap(), Value::Int(7));
+}
+
+#[test]
+fn call_and_set_global() {
+    let mut modu = Interpreter::new();
+
+    modu.eval("fn greet(name) { return greeting + \", \" + name; }")
+        .unwrap();
+    modu.set_global("greeting", Value::String("hi".to_string()));
+
+    let result = modu
+        .call("greet", vec![Value::String("modu".to_string())])
+        .unwrap();
+
+    assert_eq!(result, Value::String("hi, modu".to_string()));
+    assert!(modu.call("missing", vec![]).is_err());
+}
+
+fn host_double(args: Vec<Value>) -> Result<Value, String> {
+    match args.as_slice() {
+        [Value::Int(n)] => Ok(Value::Int(n * 2)),
+        _ => Err("double() expects an int".to_string()),
+    }
+}
+
+#[test]
+fn host_functions() {
+    let mut modu = Interpreter::new();
+    modu.register_fn("double", host_double);
+
+    assert_eq!(modu.eval("double(21);").unwrap(), Value::Int(42));
+
+    let caught = modu
+        .eval("let msg = null;\ntry { double(\"x\"); } catch e { msg = e; }\nmsg;")
+        .unwrap();
+    assert_eq!(
+        caught,
+        Value::String("error calling double(): double() expects an int".to_string())
+    );
+}
+
+#[test]
+fn exit_is_only_the_builtin() {
+    let mut modu = Interpreter::new();
+
+    let err = modu.eval("let e = exit;\ne(3);").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Exit(3));
+
+    // a host function with the same name is just a function
+    modu.register_fn("exit", |_| Ok(Value::Int(5)));
+    assert_eq!(modu.eval("exit(1);").unwrap(), Value::Int(5));
+
+    // and nothing a host function fails with can pass for an exit
+    modu.register_fn("fake", |_| Err("\u{0}exit\u{0}9".to_string()));
+    let err = modu.eval("fake();").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Runtime);
+}
+
+#[test]
+fn structured_errors() {
+    let mut modu = Interpreter::new();
+
+    let err = modu.eval("let x = ;").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Parse);
+    assert_eq!(err.path(), Some(std::path::Path::new("<eval>")));
+
+    modu.eval("fn boom() {\n    return 1 / 0;\n}").unwrap();
+    let err = modu.eval_with_name("\n\nboom();", "main.modu").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Runtime);
+    // points into the eval that defined the function, not the one that called it
+    assert_eq!(err.path(), Some(std::path::Path::new("<eval>")));
+    assert_eq!(err.location().map(|(line, _)| line), Some(2));
+
+    let err = modu.eval_file("does/not/exist.modu").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io);
+}
+
+#[test]
+fn closures_capture_state() {
+    let mut modu = Interpreter::new();
+
+    let calls = Arc::new(AtomicI64::new(0));
+    let counter = calls.clone();
+    modu.register_fn("tick", move |_| {
+        Ok(Value::Int(counter.fetch_add(1, Ordering::Relaxed) + 1))
+    });
+
+    modu.eval("tick(); tick();").unwrap();
+    assert_eq!(modu.eval("tick();").unwrap(), Value::Int(3));
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+}
+
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[test]
+fn native_objects() {
+    let mut modu = Interpreter::new();
+    let dropped = Arc::new(AtomicBool::new(false));
+    let flag = dropped.clone();
+
+    let point = NativeObject::new("point", Point { x: 3, y: 4 })
+        .with_display(|p: &Point| format!("({}, {})", p.x, p.y))
+        .with_getter(|object, name| {
+            let p = object.downcast_ref::<Point>()?;
+            match name {
+                "x" => Some(Value::Int(p.x)),
+                "y" => Some(Value::Int(p.y)),
+                _ => None,
+            }
+        })
+        .with_finalizer(move |_: &mut Point| flag.store(true, Ordering::Relaxed));
+
+    modu.set_global("p", Value::Native(point));
+
+    assert_eq!(modu.eval("p.x + p.y;").unwrap(), Value::Int(7));
+    assert_eq!(
+        modu.eval("str(p) + \" \" + type(p);").unwrap(),
+        Value::String("(3, 4) point".to_string())
+    );
+    assert!(modu.eval("p.z;").is_err());
+
+    let copy = modu.get_global("p").unwrap();
+    modu.set_global("p", Value::Null);
+    assert!(!dropped.load(Ordering::Relaxed));
+
+    drop(copy);
+    assert!(dropped.load(Ordering::Relaxed));
+}
+
+#[test]
+fn native_objects_named_like_builtins() {
+    let mut modu = Interpreter::new();
+
+    // the same call site sees a real string first, then a host object that
+    // calls itself "string" and has its own length
+    let fake = NativeObject::new("string", ()).with_getter(|_, name| match name {
+        "len" => Some(Value::Int(99)),
+        _ => None,
+    });
+    modu.set_global("fake", Value::Native(fake));
+
+    modu.eval("fn len_of(s) {\n    return s.len;\n}").unwrap();
+    assert_eq!(
+        modu.eval("type(len_of(\"abc\"));").unwrap(),
+        Value::String("native_fn".to_string())
+    );
+    assert_eq!(modu.eval("len_of(fake);").unwrap(), Value::Int(99));
+}
+
+#[test]
+fn eval_imports_from_the_import_dir() {
+    let dir = scratch("embedding", "imports");
+    let package = dir.join(".modu").join("packages").join("greet");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(package.join("lib.modu"), "let hello = \"hi\";\n").unwrap();
+    std::fs::write(dir.join("src").join("utils.modu"), "let x = 4;\n").unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.set_import_dir(dir.join("src"));
+
+    // packages are found further up, like they are from a file in src
+    let value = modu
+        .eval("import \"utils.modu\" as utils;\nimport \"greet\" as greet;\ngreet.hello + str(utils.x);")
+        .unwrap();
+    assert_eq!(value, Value::String("hi4".to_string()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
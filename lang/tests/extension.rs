@@ -0,0 +1,185 @@
+// builds examples/extension against the same modu the tests use and imports it
+#![cfg(unix)]
+
+mod common;
+
+use common::{error_of, scratch};
+use modu::{Interpreter, Permissions, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+// compiled once, straight with rustc against the modu cargo just built
+fn extension() -> &'static Path {
+    static BUILT: OnceLock<PathBuf> = OnceLock::new();
+
+    BUILT.get_or_init(|| {
+        let deps = Path::new(env!("CARGO_BIN_EXE_modu"))
+            .parent()
+            .unwrap()
+            .join("deps");
+
+        // with the cdylib next to it the rlib doesnt get a hash in its name
+        let rlib = deps.join("libmodu.rlib");
+
+        let out =
+            scratch("extension", "build").join(format!("libtext{}", std::env::consts::DLL_SUFFIX));
+
+        let status = Command::new("rustc")
+            .args([
+                "--edition",
+                "2024",
+                "--crate-type",
+                "cdylib",
+                "--crate-name",
+                "text",
+            ])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/extension/lib.rs"))
+            .arg("--extern")
+            .arg(format!("modu={}", rlib.display()))
+            .arg("-L")
+            .arg(format!("dependency={}", deps.display()))
+            .arg("-o")
+            .arg(&out)
+            .status()
+            .unwrap();
+
+        assert!(status.success());
+        out
+    })
+}
+
+#[test]
+fn native_import() {
+    let dir = scratch("extension", "import");
+    fs::copy(extension(), dir.join(extension().file_name().unwrap())).unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"native:libtext\";\nimport \"native:libtext\" as other;\n\nlet reversed = text.reverse(\"modu\");\nlet words = text.words(\"a b  c\");\nlet version = text.version;\n\ntext.count();\nlet counts = [text.count(), other.count()];\n",
+    )
+    .unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.eval_file(dir.join("main.modu")).unwrap();
+
+    assert_eq!(
+        modu.get_global("reversed"),
+        Some(Value::String("udom".to_string()))
+    );
+    assert_eq!(modu.eval("words.len();").unwrap(), Value::Int(3));
+    assert_eq!(
+        modu.get_global("version"),
+        Some(Value::String("1.0.0".to_string()))
+    );
+
+    // every import gets its own state
+    assert_eq!(
+        modu.get_global("counts"),
+        Some(Value::Array(vec![Value::Int(2), Value::Int(1)]))
+    );
+
+    // errors from the extension are catchable like any builtin's
+    assert_eq!(
+        error_of(&mut modu, "text.reverse(1);"),
+        "error calling reverse(): reverse() takes a string"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn package_from_project_toml() {
+    let dir = scratch("extension", "package");
+    let package = dir.join(".modu").join("packages").join("text");
+
+    fs::create_dir_all(&package).unwrap();
+    fs::copy(extension(), package.join(extension().file_name().unwrap())).unwrap();
+    fs::write(
+        package.join("project.toml"),
+        "[package]\nname = \"text\"\nversion = \"1.0.0\"\nnative = \"libtext\"\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"text\";\nlet reversed = text.reverse(\"abc\");\n",
+    )
+    .unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.eval_file(dir.join("main.modu")).unwrap();
+    assert_eq!(
+        modu.get_global("reversed"),
+        Some(Value::String("cba".to_string()))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bad_extensions() {
+    let dir = scratch("extension", "bad");
+    fs::write(dir.join("libnothing.so"), "not a library").unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.set_global("dir", Value::String(dir.display().to_string()));
+
+    let err = error_of(&mut modu, "import \"native:missing\";");
+    assert!(err.starts_with("cannot find native extension"), "{}", err);
+
+    fs::write(dir.join("main.modu"), "import \"native:libnothing\";").unwrap();
+    let err = modu.eval_file(dir.join("main.modu")).unwrap_err();
+    assert!(
+        err.message.starts_with("cannot load native extension"),
+        "{}",
+        err
+    );
+
+    // a real library, just not an extension
+    #[cfg(target_os = "linux")]
+    {
+        let err = error_of(
+            &mut modu,
+            "import \"native:/lib/x86_64-linux-gnu/libc.so.6\";",
+        );
+        assert!(err.contains("is not a modu extension"), "{}", err);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sandbox_needs_a_grant() {
+    let dir = scratch("extension", "sandbox");
+    fs::copy(extension(), dir.join(extension().file_name().unwrap())).unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"native:libtext\";\nlet reversed = text.reverse(\"ab\");\n",
+    )
+    .unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_read(&dir));
+    let err = modu.eval_file(dir.join("main.modu")).unwrap_err();
+    assert!(
+        err.message
+            .contains("native extensions are not available in the sandbox"),
+        "{}",
+        err
+    );
+    assert!(err.message.contains("--allow=native"), "{}", err);
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(
+        Permissions::sandbox()
+            .allow_module("native")
+            .allow_read(&dir),
+    );
+    modu.eval_file(dir.join("main.modu")).unwrap();
+    assert_eq!(
+        modu.get_global("reversed"),
+        Some(Value::String("ba".to_string()))
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
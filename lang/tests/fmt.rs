@@ -0,0 +1,192 @@
+mod common;
+
+use common::{modu, scratch};
+use modu::formatter::{Options, format};
+use modu::parser::parse_source;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn modu_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            modu_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "modu") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn formatting_keeps_the_program() {
+    let mut files = Vec::new();
+    modu_files(Path::new("examples"), &mut files);
+    modu_files(Path::new("tests/cases"), &mut files);
+    assert!(files.len() > 40);
+
+    for indent in [4, 2] {
+        let options = Options { indent };
+
+        for file in &files {
+            let name = file.display().to_string();
+            let source = fs::read_to_string(file).unwrap();
+
+            let formatted = format(&source, &name, &options)
+                .unwrap_or_else(|_| panic!("{} did not format", name));
+
+            assert_eq!(
+                parse_source(&source, &name).unwrap(),
+                parse_source(&formatted, &name).unwrap(),
+                "{} changed",
+                name
+            );
+
+            // comments survive
+            let comments = |s: &str| s.matches("//").count() + s.matches("/*").count();
+            assert_eq!(comments(&source), comments(&formatted), "{}", name);
+
+            assert_eq!(
+                format(&formatted, &name, &options).unwrap(),
+                formatted,
+                "{} is not stable",
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn layout() {
+    let source = "// top
+let obj = {\"a\": 1, \"b\": [1,2,
+3]};   // trailing
+let big = {
+  \"x\": -1, \"y\": !true,
+
+
+  \"z\": {}
+};
+fn f(a,b) { if a>b { return a-b; } else if a == b { return 0; }
+  /* block */
+  return -(a);
+}
+
+
+class A extends B { fn init() { super.init(); } }
+try { f(1,2); } catch e { print(e); }
+for i in 0..=10 { print(i..5); }
+";
+
+    let expected = "// top
+let obj = { \"a\": 1, \"b\": [1, 2, 3] }; // trailing
+let big = {
+  \"x\": -1,
+  \"y\": !true,
+
+  \"z\": {}
+};
+fn f(a, b) {
+  if a > b {
+    return a - b;
+  } else if a == b {
+    return 0;
+  }
+  /* block */
+  return -(a);
+}
+
+class A extends B {
+  fn init() {
+    super.init();
+  }
+}
+try {
+  f(1, 2);
+} catch e {
+  print(e);
+}
+for i in 0..=10 {
+  print(i..5);
+}
+";
+
+    assert_eq!(
+        format(source, "t.modu", &Options { indent: 2 }).unwrap(),
+        expected
+    );
+    assert!(format("let a = ;", "t.modu", &Options::default()).is_err());
+}
+
+#[test]
+fn line_comment_inside_a_group() {
+    let source = "let obj = { \"a\": 1, // first
+\"b\": 2 };
+print(obj, [1, // one
+2]);
+";
+
+    let expected = "let obj = {
+    \"a\": 1, // first
+    \"b\": 2
+};
+print(obj, [
+    1, // one
+    2
+]);
+";
+
+    let formatted = format(source, "t.modu", &Options::default()).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(
+        format(&formatted, "t.modu", &Options::default()).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn check_and_write() {
+    let dir = scratch("fmt", "check_and_write");
+    fs::create_dir_all(dir.join(".modu")).unwrap();
+
+    fs::write(dir.join("main.modu"), "let a=1;\nif a==1 { print(a); }\n").unwrap();
+    fs::write(dir.join("done.modu"), "print(1);\n").unwrap();
+    fs::write(dir.join(".modu/skipped.modu"), "let a=1;\n").unwrap();
+    fs::write(dir.join("project.toml"), "[fmt]\nindent = 2\n").unwrap();
+
+    let check = modu(&dir, &["fmt", "--check"]);
+    assert!(!check.status.success());
+
+    let stdout = String::from_utf8_lossy(&check.stdout);
+    assert!(stdout.contains("main.modu is not formatted"));
+    assert!(!stdout.contains("done.modu"));
+    assert!(!stdout.contains("skipped.modu"));
+
+    assert!(modu(&dir, &["fmt"]).status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("main.modu")).unwrap(),
+        "let a = 1;\nif a == 1 {\n  print(a);\n}\n"
+    );
+    assert!(modu(&dir, &["fmt", "--check"]).status.success());
+
+    // the flag wins over project.toml
+    assert!(
+        modu(&dir, &["fmt", "--indent", "4", "main.modu"])
+            .status
+            .success()
+    );
+    assert!(
+        fs::read_to_string(dir.join("main.modu"))
+            .unwrap()
+            .contains("\n    print(a);")
+    );
+
+    fs::write(dir.join("broken.modu"), "let a = ;\n").unwrap();
+    assert!(!modu(&dir, &["fmt", "broken.modu"]).status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("broken.modu")).unwrap(),
+        "let a = ;\n"
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
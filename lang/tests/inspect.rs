@@ -0,0 +1,102 @@
+mod common;
+
+use common::{modu, scratch};
+use std::fs;
+
+// the instruction and its annotation, without the padding between them
+fn lines(out: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(out)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn inspect() {
+    let dir = scratch("inspect", "inspect");
+
+    fs::write(
+        dir.join("main.modu"),
+        "fn add(a, b) {
+    return a + b;
+}
+
+if add(1, 2) > 2 {
+    print(\"big\");
+}
+",
+    )
+    .unwrap();
+
+    // nothing runs, so nothing gets printed by the script
+    let output = modu(&dir, &["disasm", "main.modu"]);
+    assert!(output.status.success());
+
+    let disasm = lines(&output.stdout);
+    for expected in [
+        "chunk 0: main (main.modu:1)",
+        "0000 Push(0) ; fn add/2 (chunk 1)",
+        "5 | if add(1, 2) > 2 {",
+        "0008 JumpIfFalse(13) ; -> 0013",
+        "0010 Push(3) ; \"big\"",
+        "0013 >> PushNull",
+        "chunk 1: add (main.modu:2), locals: a, b",
+        "0000 LoadLocal(0) ; a",
+    ] {
+        assert!(
+            disasm.iter().any(|line| line == expected),
+            "{} not in\n{:#?}",
+            expected,
+            disasm
+        );
+    }
+    assert!(!disasm.iter().any(|line| line == "big"));
+
+    let output = modu(&dir, &["disasm", "main.modu", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let jump = &json["chunks"][0]["instructions"][8];
+    assert_eq!(jump["op"], "JumpIfFalse");
+    assert_eq!(jump["target"], 13);
+    assert_eq!(jump["line"], 5);
+    assert_eq!(json["chunks"][0]["instructions"][13]["jump_target"], true);
+    assert_eq!(json["chunks"][1]["locals"], serde_json::json!(["a", "b"]));
+
+    let output = modu(&dir, &["ast", "main.modu"]);
+    assert!(output.status.success());
+
+    let ast = lines(&output.stdout);
+    for expected in [
+        "1:1 Function name=\"add\"",
+        "2:5 children: Return",
+        "2:12 value: Add",
+        "5:1 If",
+        "5:4 condition: GreaterThan",
+        "6:11 args: String value=\"big\"",
+    ] {
+        assert!(
+            ast.iter().any(|line| line == expected),
+            "{} not in\n{:#?}",
+            expected,
+            ast
+        );
+    }
+
+    let output = modu(&dir, &["ast", "main.modu", "--format=json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json[0]["kind"], "Function");
+    assert_eq!(json[0]["body"]["children"][0]["value"]["kind"], "Add");
+    assert_eq!(json[1]["branches"][0]["condition"]["line"], 5);
+    assert_eq!(json[1]["branches"][0]["condition"]["column"], 4);
+
+    fs::write(dir.join("broken.modu"), "let = 1;\n").unwrap();
+    assert_eq!(modu(&dir, &["ast", "broken.modu"]).status.code(), Some(3));
+    assert_eq!(modu(&dir, &["disasm"]).status.code(), Some(2));
+    assert_eq!(
+        modu(&dir, &["disasm", "main.modu", "--format", "xml"])
+            .status
+            .code(),
+        Some(2)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
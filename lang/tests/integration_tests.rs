@@ -1,3 +1,6 @@
+mod common;
+
+use common::scratch;
 use predicates::prelude::*;
 use std::fs;
 use std::path::Path;
@@ -21,6 +24,24 @@ fn run_test(name: &str) {
         .stdout(predicate::str::diff(expected_output));
 }
 
+// stdout up to the failure, then the status and what stderr has to mention
+fn run_failing_test(name: &str, code: i32, error: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cases");
+
+    let expected_output = fs::read_to_string(dir.join(format!("{}.expected", name)))
+        .expect("Failed to read expected output file");
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("run")
+        .arg(dir.join(format!("{}.modu", name)))
+        .assert()
+        .code(code)
+        .stdout(predicate::str::diff(expected_output))
+        .stderr(predicate::str::contains(error));
+}
+
 #[test]
 fn basic_print() {
     run_test("basic_print");
@@ -36,11 +57,21 @@ fn comparisons() {
     run_test("comparisons");
 }
 
+#[test]
+fn compile_error() {
+    run_failing_test("compile_error", 4, "cannot assign to constant 'LIMIT'");
+}
+
 #[test]
 fn crypto() {
     run_test("crypto");
 }
 
+#[test]
+fn declarations() {
+    run_test("declarations");
+}
+
 #[test]
 fn encoding() {
     run_test("encoding");
@@ -51,6 +82,16 @@ fn error_handling() {
     run_test("error_handling");
 }
 
+#[test]
+fn exit_code() {
+    run_failing_test("exit_code", 7, "");
+}
+
+#[test]
+fn fs_handles() {
+    run_test("fs_handles");
+}
+
 #[test]
 fn http() {
     run_test("http");
@@ -61,6 +102,11 @@ fn if_statements() {
     run_test("if_statements");
 }
 
+#[test]
+fn inline_caches() {
+    run_test("inline_caches");
+}
+
 #[test]
 fn loops() {
     run_test("loops");
@@ -91,12 +137,71 @@ fn strings() {
     run_test("strings");
 }
 
+#[test]
+fn tail_calls() {
+    run_test("tail_calls");
+}
+
 #[test]
 fn type_conversion() {
     run_test("type_conversion");
 }
 
+#[test]
+fn uncaught_error() {
+    run_failing_test("uncaught_error", 1, "cannot divide by zero");
+}
+
 #[test]
 fn variables() {
     run_test("variables");
 }
+
+#[test]
+fn parse_error() {
+    let dir = scratch("cli", "parse_error");
+    let file = dir.join("main.modu");
+    fs::write(&file, "print(\"never runs\");\nlet a = ;\n").unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("run")
+        .arg(&file)
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr(predicate::str::contains("[E0003]"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn usage_errors() {
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("nope")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown command: nope"));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .args(["run", "does_not_exist.modu"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "file not found: does_not_exist.modu",
+        ));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .args(["run", "--max-depth", "lots"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--max-depth expects a number"));
+
+    // as deep as it goes, the stack room it implies can't overflow
+    let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases/basic_print.modu");
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("run")
+        .arg(file)
+        .args(["--max-depth", &usize::MAX.to_string()])
+        .assert()
+        .success();
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{error_of, scratch};
+use modu::{Interpreter, OutputBuffer, Value};
+use std::fs;
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn interpreters_move_between_threads() {
+    assert_send::<Interpreter>();
+    assert_send::<Value>();
+
+    let dir = scratch("isolation", "move");
+    let path = dir.join("moved.txt");
+    fs::write(&path, "moved").unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.set_global("path", Value::String(path.display().to_string()));
+    modu.eval("import \"std/fs\";\nlet f = fs.open(path, \"r\");")
+        .unwrap();
+
+    // the open file goes along with it
+    let mut modu = std::thread::spawn(move || {
+        assert_eq!(
+            modu.eval("fs.read(f);").unwrap(),
+            Value::String("moved".to_string())
+        );
+        modu
+    })
+    .join()
+    .unwrap();
+
+    modu.eval("fs.close(f);").unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn worker_pool() {
+    let workers: Vec<_> = (0..8)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let mut modu = Interpreter::new();
+                let output = OutputBuffer::default();
+                modu.set_stdout(output.clone());
+                modu.set_global("id", Value::Int(i));
+
+                modu.eval(
+                    "let total = 0;\nfor n in 0..1000 {\n    total = total + id;\n}\nprint(\"worker \", id);",
+                )
+                .unwrap();
+
+                (modu.get_global("total"), output.contents())
+            })
+        })
+        .collect();
+
+    for (i, worker) in workers.into_iter().enumerate() {
+        let (total, output) = worker.join().unwrap();
+        assert_eq!(total, Some(Value::Int(i as i64 * 1000)));
+        assert_eq!(output, format!("worker {}\n", i));
+    }
+}
+
+#[test]
+fn file_handles_stay_in_their_interpreter() {
+    let dir = scratch("isolation", "files");
+    let path = dir.join("secret.txt");
+    fs::write(&path, "secret").unwrap();
+
+    let mut first = Interpreter::new();
+    first.set_global("path", Value::String(path.display().to_string()));
+    first
+        .eval("import \"std/fs\";\nlet f = fs.open(path, \"r\");")
+        .unwrap();
+
+    let mut second = Interpreter::new();
+    second.eval("import \"std/fs\";").unwrap();
+    second.set_global("f", first.get_global("f").unwrap());
+
+    let err = error_of(&mut second, "fs.read(f);");
+    assert!(err.contains("belongs to another interpreter"), "{}", err);
+
+    let err = error_of(&mut second, "fs.close(f);");
+    assert!(err.contains("belongs to another interpreter"), "{}", err);
+
+    // handles cant be made up either
+    let err = error_of(&mut second, "fs.read(0);");
+    assert!(err.contains("argument must be a file, got int"), "{}", err);
+
+    // and the file is still open where it came from
+    assert_eq!(
+        first.eval("fs.read(f);").unwrap(),
+        Value::String("secret".to_string())
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn ffi_libraries_stay_in_their_interpreter() {
+    let mut first = Interpreter::new();
+    first
+        .eval("import \"std/ffi\";\nlet libc = ffi.load(\"libc.so.6\");\nffi.define(libc, \"abs\", [\"i32\"], \"i32\");")
+        .unwrap();
+    assert_eq!(first.eval("libc.abs(-3);").unwrap(), Value::Int(3));
+
+    let mut second = Interpreter::new();
+    second.eval("import \"std/ffi\";").unwrap();
+    second.set_global("libc", first.get_global("libc").unwrap());
+
+    let err = error_of(&mut second, "libc.abs(-3);");
+    assert_eq!(err, "cannot use ffi_lib from another interpreter");
+
+    let err = error_of(&mut second, "ffi.unload(libc);");
+    assert!(
+        err.contains("library belongs to another interpreter"),
+        "{}",
+        err
+    );
+
+    assert_eq!(first.eval("libc.abs(-4);").unwrap(), Value::Int(4));
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::{modu, scratch};
+use modu::linter::{Config, lint};
+use modu::parser::parse_source;
+use modu::vm::chunk::SourceFile;
+use serde_json::Value;
+use std::fs;
+
+const CODE: &str = "import \"std/json\" as json;
+import \"std/math\" as math;
+
+fn add(a, b, unused) {
+    let total = a + b;
+    let _scratch = 1;
+    return total;
+    print(math.PI);
+}
+
+fn pick(xs) {
+    for xs in [1] {
+        print(xs);
+    }
+    return xs;
+}
+
+class Point {
+    fn init(x, y) {
+        self.x = x;
+        self.y = y;
+    }
+}
+
+counter = 1;
+prnt(add(1, 2));
+print(add(1, 2, 3), counter);
+Point(1);
+
+if 1 == 2 {
+    print(\"never\");
+}
+
+while true {
+    break;
+}
+";
+
+fn run(code: &str, config: &Config) -> Vec<(String, usize)> {
+    let file = SourceFile::new("main.modu", code);
+    let ast = parse_source(code, "main.modu").unwrap();
+
+    lint(&file, &ast, config)
+        .into_iter()
+        .map(|l| (l.rule.to_string(), file.line(l.span.start)))
+        .collect()
+}
+
+fn found(lints: &[(String, usize)]) -> Vec<(&str, usize)> {
+    lints
+        .iter()
+        .map(|(rule, line)| (rule.as_str(), *line))
+        .collect()
+}
+
+#[test]
+fn rules() {
+    let lints = run(CODE, &Config::default());
+
+    assert_eq!(
+        found(&lints),
+        [
+            ("unused_import", 1),
+            ("unused_parameter", 4),
+            ("unreachable_code", 8),
+            ("shadowed_local", 12),
+            ("undefined_assignment", 25),
+            ("typo", 26),
+            ("wrong_arity", 26),
+            ("wrong_arity", 28),
+            ("constant_condition", 30),
+            ("constant_condition", 34),
+        ]
+    );
+}
+
+#[test]
+fn unused_locals() {
+    let code = "fn f() {\n    let a = 1;\n    let b = 2;\n    a = 3;\n    return b;\n}\nf();\n";
+    assert_eq!(
+        found(&run(code, &Config::default())),
+        [("unused_variable", 2)]
+    );
+
+    // globals could be used by whatever imports the file
+    assert!(run("let a = 1;\n", &Config::default()).is_empty());
+}
+
+#[test]
+fn turning_rules_off() {
+    let mut config = Config::default();
+    config.set("wrong_arity", false).unwrap();
+    config.set("typo", false).unwrap();
+    assert!(config.set("nope", false).is_err());
+
+    let lints = run(CODE, &config);
+    assert!(
+        !lints
+            .iter()
+            .any(|(rule, _)| rule == "wrong_arity" || rule == "typo")
+    );
+
+    let project: toml::Value = toml::from_str("[lint]\nunused_import = false\n").unwrap();
+    let lints = run(CODE, &Config::from_toml(&project).unwrap());
+    assert!(!lints.iter().any(|(rule, _)| rule == "unused_import"));
+
+    let project: toml::Value = toml::from_str("[lint]\nunused_import = 1\n").unwrap();
+    assert!(Config::from_toml(&project).is_err());
+
+    // in the file, for one line or all of it
+    let code = CODE
+        .replace("prnt(add(1, 2));", "prnt(add(1, 2)); // lint-ignore typo")
+        .replace("Point(1);", "// lint-ignore\nPoint(1);")
+        .replace(
+            "counter = 1;",
+            "// lint-disable constant_condition, unused_import\ncounter = 1;",
+        );
+
+    let lints = run(&code, &Config::default());
+    let rules: Vec<&str> = lints.iter().map(|(rule, _)| rule.as_str()).collect();
+
+    assert_eq!(
+        rules,
+        [
+            "unused_parameter",
+            "unreachable_code",
+            "shadowed_local",
+            "undefined_assignment",
+            "wrong_arity",
+        ]
+    );
+}
+
+#[test]
+fn json_output() {
+    let dir = scratch("lint", "json_output");
+
+    fs::write(dir.join("main.modu"), CODE).unwrap();
+    fs::write(dir.join("clean.modu"), "print(1);\n").unwrap();
+    fs::write(
+        dir.join("project.toml"),
+        "[lint]\nconstant_condition = false\n",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["lint", "--format", "json", "--allow", "typo"]);
+    assert!(!output.status.success());
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let report = report.as_array().unwrap();
+    assert_eq!(report.len(), 7);
+
+    assert_eq!(report[0]["file"], "./main.modu");
+    assert_eq!(report[0]["severity"], "warning");
+    assert_eq!(report[0]["rule"], "unused_import");
+    assert_eq!(report[0]["line"], 1);
+    assert_eq!(report[0]["column"], 22);
+    assert_eq!(report[0]["end_column"], 26);
+
+    assert_eq!(report[4]["rule"], "undefined_assignment");
+    assert_eq!(
+        report[4]["help"],
+        "use `let counter = ...` to make a new variable"
+    );
+
+    assert!(modu(&dir, &["lint", "clean.modu"]).status.success());
+
+    fs::write(dir.join("broken.modu"), "let a = ;\n").unwrap();
+    let output = modu(&dir, &["lint", "--format=json", "broken.modu"]);
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report[0]["severity"], "error");
+    assert_eq!(report[0]["line"], 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
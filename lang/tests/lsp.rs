@@ -0,0 +1,370 @@
+mod common;
+
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const MAIN: &str = "import \"utils.modu\" as utils;
+import \"std/math\" as math;
+
+class Counter {
+    fn init(start) {
+        self.value = start;
+    }
+
+    fn inc(by) {
+        self.value += by;
+    }
+}
+
+fn twice(x) {
+    let doubled = x * 2;
+    return doubled;
+}
+
+let counter = Counter(1);
+counter.inc(twice(2));
+print(utils.greet(\"modu\"));
+print(math.sin(0.0));
+";
+
+const UTILS: &str = "fn greet(name) {
+    return \"hi \" + name;
+}
+
+let version = \"1.0\";
+";
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = common::scratch("lsp", name);
+    fs::write(dir.join("main.modu"), MAIN).unwrap();
+    fs::write(dir.join("utils.modu"), UTILS).unwrap();
+
+    dir.canonicalize().unwrap()
+}
+
+fn uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+// just enough of an editor to drive `modu lsp`
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    id: i64,
+    notifications: VecDeque<Value>,
+}
+
+impl Client {
+    fn start(root: &Path) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_modu"))
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut client = Client {
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            id: 0,
+            notifications: VecDeque::new(),
+        };
+
+        let init = client.request("initialize", json!({ "rootUri": uri(root) }));
+        assert_eq!(init["capabilities"]["definitionProvider"], true);
+        client.notify("initialized", json!({}));
+
+        client
+    }
+
+    fn write(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn read(&mut self) -> Value {
+        let mut length = 0;
+
+        loop {
+            let mut line = String::new();
+            assert!(self.stdout.read_line(&mut line).unwrap() > 0, "lsp exited");
+
+            match line.trim().strip_prefix("Content-Length:") {
+                Some(n) => length = n.trim().parse().unwrap(),
+                None if line.trim().is_empty() => break,
+                None => {}
+            }
+        }
+
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+        let id = self.id;
+
+        self.write(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.read();
+
+            if message["id"] == id {
+                return message["result"].clone();
+            }
+
+            self.notifications.push_back(message);
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.write(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn diagnostics(&mut self) -> Vec<Value> {
+        let message = match self.notifications.pop_front() {
+            Some(message) => message,
+            None => self.read(),
+        };
+
+        assert_eq!(message["method"], "textDocument/publishDiagnostics");
+        message["params"]["diagnostics"].as_array().unwrap().clone()
+    }
+
+    fn open(&mut self, path: &Path, text: &str) {
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": uri(path),
+                "languageId": "modu",
+                "version": 1,
+                "text": text,
+            }}),
+        );
+    }
+
+    fn at(&mut self, method: &str, path: &Path, line: u64, character: u64) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": uri(path) },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": true },
+            }),
+        )
+    }
+
+    fn labels(&mut self, path: &Path, line: u64, character: u64) -> Vec<String> {
+        self.at("textDocument/completion", path, line, character)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn finish(mut self) {
+        assert_eq!(self.request("shutdown", Value::Null), Value::Null);
+        self.notify("exit", Value::Null);
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn line_of(location: &Value) -> (String, u64, u64) {
+    let uri = location["uri"].as_str().unwrap();
+    let file = uri.rsplit('/').next().unwrap().to_string();
+    let start = &location["range"]["start"];
+
+    (
+        file,
+        start["line"].as_u64().unwrap(),
+        start["character"].as_u64().unwrap(),
+    )
+}
+
+#[test]
+fn diagnostics() {
+    let dir = scratch("diagnostics");
+    let main = dir.join("main.modu");
+    let mut client = Client::start(&dir);
+
+    client.open(&main, MAIN);
+    assert!(client.diagnostics().is_empty());
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(&main), "version": 2 },
+            "contentChanges": [{ "text": "let a = 1;\nreturn a;\n" }],
+        }),
+    );
+
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+    assert!(
+        diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Return statement not allowed")
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(&main), "version": 3 },
+            "contentChanges": [{ "text": "let a = ;\n" }],
+        }),
+    );
+
+    assert_eq!(client.diagnostics()[0]["range"]["start"]["line"], 0);
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn navigation() {
+    let dir = scratch("navigation");
+    let main = dir.join("main.modu");
+    let mut client = Client::start(&dir);
+
+    client.open(&main, MAIN);
+    client.diagnostics();
+
+    // a function, a method through what the variable was made from, and a local
+    let twice = client.at("textDocument/definition", &main, 19, 13);
+    assert_eq!(line_of(&twice), ("main.modu".to_string(), 13, 3));
+
+    let inc = client.at("textDocument/definition", &main, 19, 9);
+    assert_eq!(line_of(&inc), ("main.modu".to_string(), 8, 7));
+
+    let doubled = client.at("textDocument/definition", &main, 15, 12);
+    assert_eq!(line_of(&doubled), ("main.modu".to_string(), 14, 8));
+
+    // into another file, through the import and from the import path
+    let greet = client.at("textDocument/definition", &main, 20, 13);
+    assert_eq!(line_of(&greet), ("utils.modu".to_string(), 0, 3));
+
+    let file = client.at("textDocument/definition", &main, 0, 10);
+    assert_eq!(line_of(&file), ("utils.modu".to_string(), 0, 0));
+
+    let references = client.at("textDocument/references", &main, 20, 13);
+    let mut found: Vec<_> = references.as_array().unwrap().iter().map(line_of).collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            ("main.modu".to_string(), 20, 12),
+            ("utils.modu".to_string(), 0, 3)
+        ]
+    );
+
+    let references = client.at("textDocument/references", &main, 14, 9);
+    assert_eq!(references.as_array().unwrap().len(), 2);
+
+    let hover = client.at("textDocument/hover", &main, 19, 13);
+    let text = hover["contents"]["value"].as_str().unwrap();
+    assert!(text.contains("fn twice(x)"));
+    assert!(text.contains("takes 1 argument"));
+
+    let hover = client.at("textDocument/hover", &main, 20, 2);
+    assert!(
+        hover["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .contains("builtin function")
+    );
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri(&main) } }),
+    );
+    let names: Vec<&str> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["utils", "math", "Counter", "twice", "counter"]);
+
+    let methods: Vec<&str> = symbols[2]["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(methods, ["init", "inc"]);
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn completion() {
+    let dir = scratch("completion");
+    let main = dir.join("main.modu");
+    let mut client = Client::start(&dir);
+
+    client.open(&main, MAIN);
+    client.diagnostics();
+
+    // locals only inside their function
+    let inside = client.labels(&main, 15, 11);
+    for name in [
+        "doubled", "x", "twice", "counter", "Counter", "utils", "print", "let",
+    ] {
+        assert!(inside.contains(&name.to_string()), "{} missing", name);
+    }
+
+    let outside = client.labels(&main, 21, 0);
+    assert!(!outside.contains(&"doubled".to_string()));
+
+    // members, while the file doesn't parse because of the dot
+    let text = format!("{}math.\ncounter.\nutils.\n\"a\".\n", MAIN);
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(&main), "version": 2 },
+            "contentChanges": [{ "text": text }],
+        }),
+    );
+    assert!(!client.diagnostics().is_empty());
+
+    let math = client.labels(&main, 22, 5);
+    assert!(math.contains(&"sin".to_string()));
+    assert!(math.contains(&"PI".to_string()));
+
+    assert_eq!(client.labels(&main, 23, 8), ["init", "inc"]);
+    assert_eq!(client.labels(&main, 24, 6), ["greet", "version"]);
+
+    // the natives of whatever it could be
+    let natives = client.labels(&main, 25, 4);
+    assert!(natives.contains(&"to_upper".to_string()));
+    assert!(natives.contains(&"push".to_string()));
+
+    // self in a method is the class
+    let in_class = MAIN.replace("self.value += by;", "self.");
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(&main), "version": 3 },
+            "contentChanges": [{ "text": in_class }],
+        }),
+    );
+    client.diagnostics();
+
+    assert_eq!(client.labels(&main, 9, 13), ["init", "inc", "value"]);
+
+    client.finish();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::{modu, scratch};
+use std::fs;
+
+#[test]
+fn profile() {
+    let dir = scratch("profile", "profile");
+    fs::create_dir_all(dir.join("lib")).unwrap();
+
+    fs::write(
+        dir.join("lib/util.modu"),
+        "fn shout(s) {\n    return s.to_upper();\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"lib/util.modu\" as util;
+import \"std/crypto\" as crypto;
+
+fn fib(n) {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fn work() {
+    for i in 0..3 {
+        crypto.sha256(util.shout(\"x\"));
+    }
+}
+
+print(fib(10));
+work();
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["run", "main.modu", "--profile"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "55\n");
+
+    // function and calls, the times change every run
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let calls: Vec<(&str, &str, &str)> = stderr
+        .lines()
+        .skip_while(|line| !line.starts_with("function"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("wrote"))
+        .map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            (columns[0], columns[1], columns[columns.len() - 1])
+        })
+        .collect();
+
+    for expected in [
+        ("fib", "177", "main.modu:5"),
+        ("work", "1", "main.modu:12"),
+        ("shout", "3", "lib/util.modu:2"),
+        ("util.modu", "1", "lib/util.modu:1"),
+        ("crypto.sha256", "3", "builtin"),
+        ("string.to_upper", "3", "builtin"),
+        ("print", "1", "builtin"),
+    ] {
+        assert!(
+            calls.contains(&expected),
+            "{:?} not in\n{}",
+            expected,
+            stderr
+        );
+    }
+
+    // builtins get their own frames, under whatever called them
+    let folded = fs::read_to_string(dir.join("profile.folded")).unwrap();
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+
+    for stack in [
+        "main",
+        "main;fib;fib",
+        "main;util.modu",
+        "main;print",
+        "main;work;crypto.sha256",
+        "main;work;shout;string.to_upper",
+    ] {
+        assert!(stacks.contains(&stack), "{} not in\n{}", stack, folded);
+    }
+
+    assert!(
+        folded
+            .lines()
+            .all(|line| line.rsplit_once(' ').unwrap().1.parse::<u64>().is_ok())
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use common::error_of;
+use modu::{Interpreter, Permissions, Value};
+use predicates::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = common::scratch("sandbox", name);
+
+    fs::create_dir_all(dir.join("data")).unwrap();
+    fs::create_dir_all(dir.join("secret")).unwrap();
+    fs::write(dir.join("data").join("notes.txt"), "hello").unwrap();
+    fs::write(dir.join("secret").join("key.txt"), "hunter2").unwrap();
+
+    dir
+}
+
+#[test]
+fn unchecked_modules_need_a_grant() {
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox());
+
+    assert!(
+        error_of(&mut modu, "import \"std/os\";")
+            .contains("permission denied: std/os is not available in the sandbox")
+    );
+    assert!(error_of(&mut modu, "import \"std/ffi\";").contains("--allow=ffi"));
+
+    // pure modules are always there
+    assert_eq!(
+        modu.eval("import \"std/math\";\ntype(math.sin(0.0));")
+            .unwrap(),
+        Value::String("float".to_string())
+    );
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_module("os"));
+    assert_eq!(
+        modu.eval("import \"std/os\";\ntype(os.pid());").unwrap(),
+        Value::String("int".to_string())
+    );
+}
+
+#[test]
+fn fs_is_limited_to_allowed_paths() {
+    let dir = scratch("fs");
+    let data = dir.join("data");
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_read(&data));
+    modu.set_global("dir", Value::String(dir.display().to_string()));
+    modu.eval("import \"std/fs\";").unwrap();
+
+    assert_eq!(
+        modu.eval("let f = fs.open(dir + \"/data/notes.txt\", \"r\");\nfs.read(f);")
+            .unwrap(),
+        Value::String("hello".to_string())
+    );
+
+    let denied = error_of(&mut modu, "fs.open(dir + \"/secret/key.txt\", \"r\");");
+    assert!(
+        denied.contains("permission denied: cannot read"),
+        "{}",
+        denied
+    );
+    assert!(denied.contains("--allow-read="), "{}", denied);
+
+    // climbing out with .. doesnt work, even through a missing directory
+    assert!(
+        error_of(
+            &mut modu,
+            "fs.open(dir + \"/data/../secret/key.txt\", \"r\");"
+        )
+        .contains("permission denied")
+    );
+    assert!(
+        error_of(&mut modu, "fs.exists(dir + \"/data/nope/../../secret\");")
+            .contains("permission denied")
+    );
+
+    // reading isnt writing
+    assert!(
+        error_of(&mut modu, "fs.open(dir + \"/data/new.txt\", \"w\");")
+            .contains("permission denied: cannot write")
+    );
+    assert!(
+        error_of(&mut modu, "fs.remove(dir + \"/data/notes.txt\");")
+            .contains("permission denied: cannot write")
+    );
+    assert!(data.join("notes.txt").exists());
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_write(&data));
+    modu.set_global("dir", Value::String(dir.display().to_string()));
+    modu.eval("import \"std/fs\";\nlet f = fs.open(dir + \"/data/new.txt\", \"w\");\nfs.write(f, \"x\");\nfs.close(f);")
+        .unwrap();
+    assert_eq!(fs::read_to_string(data.join("new.txt")).unwrap(), "x");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn imports_follow_the_policy() {
+    let dir = scratch("imports");
+
+    fs::write(dir.join("data").join("util.modu"), "let answer = 42;").unwrap();
+    fs::write(
+        dir.join("secret").join("keys.modu"),
+        "let key = \"hunter2\";",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"data/util\";\nlet key = null;\ntry {\nimport \"secret/keys\";\n} catch e {\nkey = e;\n}\n",
+    )
+    .unwrap();
+
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_read(dir.join("data")));
+    modu.eval_file(dir.join("main.modu")).unwrap();
+
+    assert_eq!(modu.eval("util.answer;").unwrap(), Value::Int(42));
+
+    match modu.get_global("key") {
+        Some(Value::String(e)) => assert!(e.contains("permission denied: cannot read"), "{}", e),
+        other => panic!("expected a permission error, got {:?}", other),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn http_is_limited_to_allowed_hosts() {
+    let mut modu = Interpreter::new();
+    modu.set_permissions(Permissions::sandbox().allow_net("localhost:1"));
+    modu.eval("import \"std/http\";").unwrap();
+
+    // denied before anything goes over the network
+    assert!(
+        error_of(&mut modu, "http.get(\"http://example.com/\");")
+            .contains("permission denied: network access to 'example.com:80'")
+    );
+    assert!(
+        error_of(&mut modu, "http.post(\"http://localhost:2/\", \"hi\");")
+            .contains("permission denied")
+    );
+
+    // allowed, but nothing listens there
+    assert!(
+        error_of(&mut modu, "http.get(\"http://localhost:1/\");")
+            .contains("http.get() request failed")
+    );
+}
+
+#[test]
+fn cli_flags() {
+    let dir = scratch("cli");
+    let file = dir.join("main.modu");
+
+    fs::write(
+        &file,
+        "import \"std/fs\";\nlet f = fs.open(\"data/notes.txt\", \"r\");\nprint(fs.read(f));\nimport \"std/os\";\n",
+    )
+    .unwrap();
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "--sandbox", "--allow-read=data", "main.modu"])
+        .assert()
+        .code(1)
+        .stdout("hello\n")
+        .stderr(predicate::str::contains("std/os is not available"));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "--sandbox", "--allow=os", "main.modu"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "permission denied: cannot read 'data/notes.txt'",
+        ));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "--allow-read=data", "main.modu"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "--allow-read only does something with --sandbox",
+        ));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_flags_with_a_space() {
+    let dir = scratch("cli_space");
+
+    fs::write(
+        dir.join("main.modu"),
+        "import \"std/fs\";\nprint(fs.read(fs.open(\"data/notes.txt\", \"r\")));\nfs.open(\"secret/key.txt\", \"r\");\n",
+    )
+    .unwrap();
+
+    // the same as --allow-read=data, not everything with data as the file to run
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "main.modu", "--sandbox", "--allow-read", "data"])
+        .assert()
+        .code(1)
+        .stdout("hello\n")
+        .stderr(predicate::str::contains(
+            "permission denied: cannot read 'secret/key.txt'",
+        ));
+
+    // without a value it's an error instead of a grant for everything
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "main.modu", "--sandbox", "--allow-read"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--allow-read needs a value"));
+
+    assert_cmd::cargo::cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .current_dir(&dir)
+        .args(["run", "main.modu", "--sandbox", "--allow-read=*"])
+        .assert()
+        .code(0)
+        .stdout("hello\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::scratch;
+use modu::{Interpreter, OutputBuffer, Value};
+use std::fs;
+use std::io::Cursor;
+
+#[test]
+fn print_goes_to_the_writer() {
+    let mut modu = Interpreter::new();
+    let output = OutputBuffer::default();
+    modu.set_stdout(output.clone());
+
+    modu.eval("print(\"hello \", 42);\nprint([1, 2]);").unwrap();
+    assert_eq!(output.take(), "hello 42\n[1, 2]\n");
+
+    modu.eval("print(null);").unwrap();
+    assert_eq!(output.contents(), "null\n");
+}
+
+#[test]
+fn input_reads_from_the_reader() {
+    let mut modu = Interpreter::new();
+    let output = OutputBuffer::default();
+    modu.set_stdout(output.clone());
+    modu.set_stdin(Cursor::new("alice\r\nbob  \n"));
+
+    modu.eval("let a = input(\"name? \");\nlet b = input();\nlet c = input();")
+        .unwrap();
+
+    assert_eq!(
+        modu.get_global("a"),
+        Some(Value::String("alice".to_string()))
+    );
+    assert_eq!(modu.get_global("b"), Some(Value::String("bob".to_string())));
+    assert_eq!(modu.get_global("c"), Some(Value::String(String::new())));
+    assert_eq!(output.contents(), "name? ");
+}
+
+#[test]
+fn interpreters_dont_share_output() {
+    let mut first = Interpreter::new();
+    let mut second = Interpreter::new();
+
+    let first_output = OutputBuffer::default();
+    let second_output = OutputBuffer::default();
+    first.set_stdout(first_output.clone());
+    second.set_stdout(second_output.clone());
+
+    first.eval("fn greet() { print(\"first\"); }").unwrap();
+    second.eval("print(\"second\");").unwrap();
+    first.call("greet", vec![]).unwrap();
+
+    assert_eq!(first_output.contents(), "first\n");
+    assert_eq!(second_output.contents(), "second\n");
+}
+
+#[test]
+fn imports_print_to_the_same_place() {
+    let dir = scratch("stdio", "imports");
+
+    fs::write(
+        dir.join("lib.modu"),
+        "print(\"loading lib\");\nfn shout(s) { print(s + \"!\"); }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("main.modu"),
+        "import \"lib.modu\";\nlib.shout(\"hi\");",
+    )
+    .unwrap();
+
+    let mut modu = Interpreter::new();
+    let output = OutputBuffer::default();
+    modu.set_stdout(output.clone());
+
+    modu.eval_file(dir.join("main.modu")).unwrap();
+    assert_eq!(output.contents(), "loading lib\nhi!\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,235 @@
+mod common;
+
+use common::{modu, scratch};
+use std::fs;
+
+#[test]
+fn runs_tests_and_reports_failures() {
+    let dir = scratch("test", "report");
+    fs::create_dir_all(dir.join("tests")).unwrap();
+
+    fs::write(
+        dir.join("tests/math_test.modu"),
+        "import \"std/test\" as t;
+
+fn test_add() {
+    t.assert_eq(1 + 1, 2);
+}
+
+fn test_arrays() {
+    print(\"checking arrays\");
+    t.assert_eq([1, {\"a\": 2}], [1, {\"a\": 3}, 4]);
+}
+
+fn boom() {
+    error(\"boom\");
+}
+
+fn test_raises() {
+    t.assert_contains(t.assert_raises(boom), \"boom\");
+}
+
+fn test_approx() {
+    t.assert_approx(0.1 + 0.2, 0.3);
+}
+
+// expect-error: division by zero
+fn test_divide_by_zero() {
+    let a = 1 / 0;
+}
+
+// expect-error
+fn test_should_fail() {
+    let a = 1;
+}
+
+fn not_a_test() {
+    t.fail(\"never runs\");
+}
+",
+    )
+    .unwrap();
+    // not a test file, never picked up
+    fs::write(
+        dir.join("tests/helper.modu"),
+        "fn test_nope() {\n    error(\"nope\");\n}\n",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["test"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("running 6 tests from 1 file"));
+    assert!(stdout.contains("math_test.modu::test_add ... ok"));
+    assert!(stdout.contains("math_test.modu::test_arrays ... FAILED"));
+    assert!(stdout.contains("math_test.modu::test_raises ... ok"));
+    assert!(stdout.contains("math_test.modu::test_approx ... ok"));
+    assert!(stdout.contains("math_test.modu::test_divide_by_zero ... ok"));
+    assert!(stdout.contains("math_test.modu::test_should_fail ... FAILED"));
+    assert!(!stdout.contains("never runs"));
+    assert!(!stdout.contains("test_nope"));
+
+    // what the test printed comes with the failure
+    assert!(stdout.contains("checking arrays"));
+    assert!(stdout.contains("[1].a: expected 3, got 2"));
+    assert!(stdout.contains("[2]: missing, expected 4"));
+    assert!(stdout.contains("expected an error, but the test passed"));
+    assert!(stdout.contains("4 passed, 2 failed, 0 filtered out"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn filters_and_isolation() {
+    let dir = scratch("test", "filters");
+    fs::create_dir_all(dir.join("tests")).unwrap();
+
+    fs::write(
+        dir.join("tests/state_test.modu"),
+        "import \"std/test\" as t;
+
+let count = 0;
+
+fn test_first() {
+    count = count + 1;
+    t.assert_eq(count, 1);
+}
+
+fn test_second() {
+    count = count + 1;
+    t.assert_eq(count, 1);
+}
+
+// expect-error: out of range
+fn test_wrong_error() {
+    error(\"something else\");
+}
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["test", "first", "second"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("2 passed, 0 failed, 1 filtered out"));
+
+    let output = modu(&dir, &["test", "state_test.modu::test_wrong"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("expected an error containing \"out of range\""));
+    assert!(stdout.contains("something else"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn assertion_messages() {
+    let dir = scratch("test", "messages");
+    fs::create_dir_all(dir.join("tests")).unwrap();
+
+    fs::write(
+        dir.join("asserts_test.modu"),
+        "import \"std/test\" as t;
+
+fn test_eq() {
+    t.assert_eq(\"a\", \"b\", \"names\");
+}
+
+fn test_ne() {
+    t.assert_ne(1, 1);
+}
+
+fn test_approx() {
+    t.assert_approx(1.0, 1.1, 0.01);
+}
+
+fn test_approx_message() {
+    t.assert_approx(0.5, 0.6, \"ratio\");
+}
+
+fn test_approx_tolerance_and_message() {
+    t.assert_approx(2, 3, 0.1, \"total\");
+}
+
+fn test_object() {
+    t.assert_eq({\"a\": 1, \"b\": 2}, {\"a\": 1, \"c\": 2});
+}
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["test"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("names: expected \"b\", got \"a\""));
+    assert!(stdout.contains("expected something other than 1"));
+    assert!(stdout.contains("expected about 1.1, got 1"));
+    assert!(stdout.contains("ratio: expected about 0.6, got 0.5 (tolerance 0.000000001)"));
+    assert!(stdout.contains("total: expected about 3, got 2 (tolerance 0.1)"));
+    assert!(stdout.contains(".b: not expected, got 2"));
+    assert!(stdout.contains(".c: missing, expected 2"));
+    assert!(stdout.contains("0 passed, 6 failed"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn snapshots() {
+    let dir = scratch("test", "snapshots");
+    fs::create_dir_all(dir.join("tests/snapshots")).unwrap();
+
+    fs::write(
+        dir.join("tests/snapshots/ids.modu"),
+        "import \"std/uuid\" as uuid;
+import \"std/time\" as time;
+
+print(\"id \" + uuid.v4());
+print(\"at \" + time.now_utc());
+print(\"took 12ms\");
+exit(3);
+",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("project.toml"),
+        "[test.masks]\n\"took \\\\d+ms\" = \"took [time]\"\n",
+    )
+    .unwrap();
+
+    // nothing stored yet
+    let output = modu(&dir, &["test", "--snapshot"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("run with --update to create it"));
+
+    let output = modu(&dir, &["test", "--snapshot", "--update"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(dir.join("tests/snapshots/ids.snap")).unwrap(),
+        "exit code: 3\n--- stdout\nid [uuid]\nat [timestamp]\ntook [time]\n--- stderr\n"
+    );
+
+    // a new uuid and time every run, still the same snapshot
+    let output = modu(&dir, &["test", "--snapshot"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1 passed, 0 failed"));
+
+    fs::write(
+        dir.join("tests/snapshots/ids.modu"),
+        "print(\"something else\");\nlet a = 1 / 0;\n",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["test", "--snapshot"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("- exit code: 3"));
+    assert!(stdout.contains("+ exit code: 1"));
+    assert!(stdout.contains("- id [uuid]"));
+    assert!(stdout.contains("+ something else"));
+    assert!(stdout.contains("+ Error: division by zero"));
+    assert!(stdout.contains("tests/snapshots/ids.modu:2:9"));
+
+    assert_eq!(modu(&dir, &["test", "--update"]).status.code(), Some(2));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::{modu, scratch};
+use std::fs;
+
+// chunk, ip, location and instruction, without the stack
+fn steps(trace: &str) -> Vec<String> {
+    trace
+        .lines()
+        .map(|line| {
+            let line = line.split(" [").next().unwrap();
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        })
+        .collect()
+}
+
+#[test]
+fn trace() {
+    let dir = scratch("trace", "trace");
+
+    fs::write(
+        dir.join("main.modu"),
+        "fn add(a, b) {
+    return a + b;
+}
+
+let xs = [1, 2, 3];
+let s = add(1, 2);
+print(add(s, \"x\"));
+",
+    )
+    .unwrap();
+
+    let output = modu(&dir, &["run", "main.modu", "--trace"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("main             0005  main.modu:5      MakeArray(3)"));
+    assert!(stderr.contains("[1, 2, 3]"));
+    assert!(stderr.contains("StoreGlobal(\"xs\")                [[3 items]]"));
+    assert!(stderr.contains("uncaught error: cannot add int and string"));
+
+    // only what ran in add, to a file
+    let output = modu(
+        &dir,
+        &["run", "main.modu", "--trace=add.trace", "--trace-fn=add"],
+    );
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("LoadLocal"));
+
+    let trace = fs::read_to_string(dir.join("add.trace")).unwrap();
+    assert_eq!(
+        steps(&trace),
+        [
+            "add 0000 main.modu:2 LoadLocal(0)",
+            "add 0001 main.modu:2 LoadLocal(1)",
+            "add 0002 main.modu:2 Add",
+            "add 0003 main.modu:2 Return",
+            "add 0000 main.modu:2 LoadLocal(0)",
+            "add 0001 main.modu:2 LoadLocal(1)",
+            "add 0002 main.modu:2 Add",
+            "uncaught error: cannot add int and string",
+        ]
+    );
+    assert!(trace.contains("[…, 3, \"x\", 3, \"x\"]"));
+
+    let output = modu(
+        &dir,
+        &[
+            "run",
+            "main.modu",
+            "--trace=lines.trace",
+            "--trace-lines",
+            "main.modu:6",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    let trace = fs::read_to_string(dir.join("lines.trace")).unwrap();
+    assert_eq!(
+        steps(&trace),
+        [
+            "main 0007 main.modu:6 LoadGlobal(\"add\")",
+            "main 0008 main.modu:6 Push(1)",
+            "main 0009 main.modu:6 Push(2)",
+            "main 0010 main.modu:6 Call(2)",
+            "main 0011 main.modu:6 StoreGlobal(\"s\")",
+            "uncaught error: cannot add int and string",
+        ]
+    );
+
+    assert_eq!(
+        modu(&dir, &["run", "main.modu", "--trace-fn=add"])
+            .status
+            .code(),
+        Some(2)
+    );
+    assert_eq!(
+        modu(&dir, &["run", "main.modu", "--trace", "--trace-lines=a-b"])
+            .status
+            .code(),
+        Some(2)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
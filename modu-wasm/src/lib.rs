@@ -1,9 +1,7 @@
 use wasm_bindgen::prelude::*;
-use std::sync::Mutex;
+use std::io::{BufRead, Read, Write};
 use std::panic::{catch_unwind, AssertUnwindSafe};
-use yansi::Paint;
-
-static OUTPUT: Mutex<String> = Mutex::new(String::new());
+use modu::OutputBuffer;
 
 #[wasm_bindgen(start)]
 pub fn init() {
@@ -16,28 +14,26 @@ pub fn init() {
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 #[wasm_bindgen]
-pub fn eval_modu(code: &str) -> String {    
-    let result = catch_unwind(AssertUnwindSafe(|| {
-        let ast = modu::parser::parse(code, "<browser>");
-
-        if ast.is_err() {
-            return;
-        }
-
-        let mut compiler = modu::compiler::compiler::Compiler::new();
-
-        if let Err(e) = compiler.compile_program(ast.clone().unwrap()) {
-            let mut output = OUTPUT.lock().unwrap();
-            output.push_str(&format!("{}: {}\n", "Compilation error".red(), e));
-            return;
-        }
-
-        let mut vm = modu::vm::vm::VM::new(compiler.chunks, std::path::PathBuf::from("<browser>"), code.to_string());
+pub fn eval_modu(code: &str) -> String {
+    let output = OutputBuffer::default();
 
-        if let Err(e) = vm.run(0) {
-            let mut output = OUTPUT.lock().unwrap();
-            output.push_str(&format!("{}\n", e));
-            return;
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        // a `loop {}` would freeze the tab, and Instant doesnt work here so no timeout
+        let mut interpreter = modu::Interpreter::with_limits(modu::Limits {
+            max_instructions: Some(100_000_000),
+            max_memory: Some(256 * 1024 * 1024),
+            ..modu::Limits::default()
+        });
+
+        interpreter.set_stdout(output.clone());
+        interpreter.set_stderr(output.clone());
+        interpreter.set_stdin(PromptInput::new(output.clone()));
+
+        // every error in the code at once, eval would stop at the first
+        if let Err(errors) = modu::parser::parse_source(code, "<browser>") {
+            let _ = write!(output.clone(), "{}", modu::ModuError::render_all(&errors));
+        } else if let Err(e) = interpreter.eval_with_name(code, "<browser>") {
+            let _ = writeln!(output.clone(), "{}", e.render());
         }
     }));
 
@@ -48,14 +44,43 @@ pub fn eval_modu(code: &str) -> String {
             .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
             .unwrap_or("Unknown internal error");
 
-        let mut output = OUTPUT.lock().unwrap();
-        output.push_str(&format!("Internal error: {}\n", msg));
+        let _ = writeln!(output.clone(), "Internal error: {}", msg);
     }
 
-    let string = format!("{}", OUTPUT.lock().unwrap().as_str());
-    OUTPUT.lock().unwrap().truncate(0);
+    output.take()
+}
 
-    string
+// the compiled bytecode as json, for showing next to the code.
+// {"error": ...} if it doesn't parse or compile
+#[wasm_bindgen]
+pub fn disasm_modu(code: &str) -> String {
+    match modu::inspect::compile(code, "<browser>") {
+        Ok(chunks) => modu::inspect::disassemble_json(&chunks).to_string(),
+        Err(errors) => error_json(&errors[0]),
+    }
+}
+
+// the syntax tree as json, every node with its kind, line and column
+#[wasm_bindgen]
+pub fn ast_modu(code: &str) -> String {
+    match modu::inspect::parse(code, "<browser>") {
+        Ok(ast) => {
+            let source = modu::vm::chunk::SourceFile::new("<browser>", code);
+            modu::inspect::ast_json(&ast, &source).to_string()
+        }
+        Err(errors) => error_json(&errors[0]),
+    }
+}
+
+fn error_json(error: &modu::ModuError) -> String {
+    let (line, column) = error.location().unzip();
+
+    serde_json::json!({
+        "error": error.message,
+        "line": line,
+        "column": column,
+    })
+    .to_string()
 }
 
 #[wasm_bindgen]
@@ -63,33 +88,51 @@ pub fn modu_version() -> String {
     modu::VERSION.to_string()
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn _modu_print(ptr: *const u8, len: usize) {
-    let text = unsafe {
-        std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap()
-    };
+// input() reads through a browser prompt, showing whatever the script printed
+// on the current line so far, and the answer gets echoed into the output
+struct PromptInput {
+    output: OutputBuffer,
+    line: Vec<u8>,
+    pos: usize,
+}
 
-    let mut output = OUTPUT.lock().unwrap();
-    output.push_str(text);
+impl PromptInput {
+    fn new(output: OutputBuffer) -> Self {
+        Self { output, line: Vec::new(), pos: 0 }
+    }
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn _modu_input(ptr: *const u8, len: usize, out_len: *mut usize) -> *mut u8 {
-    let prompt = unsafe {
-        std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap()
-    };
+impl Read for PromptInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
 
-    let input = web_sys::window().unwrap().prompt_with_message(prompt).unwrap_or_default().unwrap_or_default();
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
 
-    let mut output = OUTPUT.lock().unwrap();
-    output.push_str(&format!("> {}\n", input));
+        Ok(n)
+    }
+}
 
-    let mut bytes = input.into_bytes();
-    bytes.shrink_to_fit();
-    let len = bytes.len();
-    let ptr = bytes.as_mut_ptr();
-    std::mem::forget(bytes);
+impl BufRead for PromptInput {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.pos >= self.line.len() {
+            let contents = self.output.contents();
+            let prompt = contents.rsplit('\n').next().unwrap_or_default();
 
-    unsafe { *out_len = len; }
-    ptr
-}
\ No newline at end of file
+            let input = web_sys::window()
+                .and_then(|window| window.prompt_with_message(prompt).ok().flatten())
+                .unwrap_or_default();
+
+            self.line = format!("{}\n", input).into_bytes();
+            self.pos = 0;
+            self.output.write_all(&self.line)?;
+        }
+
+        Ok(&self.line[self.pos..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.pos += amount;
+    }
+}